@@ -0,0 +1,175 @@
+//! Signed-in accounts.
+
+use base64;
+use serde_json::{self, Value};
+use inth_oauth2::client::response::{FromResponse, ParseError};
+
+use id_token::IdTokenClaims;
+use jwt;
+
+/// An account the user has signed in with, as an application would cache it to offer an
+/// account chooser.
+///
+/// ```rust
+/// # extern crate base64;
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{Account, AuthorizationRequest, AzureCommon, IdTokenClaims, find_account};
+/// # fn main() {
+/// # let encode = |v: serde_json::Value| base64::encode_config(&v.to_string(), base64::URL_SAFE_NO_PAD);
+/// # let id_token = |oid: &str, username: &str| format!("{}.{}.", encode(json!({ "alg": "RS256" })), encode(json!({
+/// #     "oid": oid, "tid": "72f988bf-86f1-41af-91ab-2d7cd011db47", "preferred_username": username,
+/// # })));
+/// let accounts = vec![
+///     Account::from_id_token(&id_token("00000000-0000-0000-66f3-3332eca7ea81", "alice@contoso.com")).unwrap(),
+///     Account::from_id_token(&id_token("00000000-0000-0000-d5bb-4cd460b12fd4", "bob@contoso.com")).unwrap(),
+/// ];
+///
+/// let (uri, _) = AuthorizationRequest::new(&AzureCommon, "client-id")
+///     .select_account(Some(&accounts[1]))
+///     .build();
+/// assert!(uri.query().unwrap().contains("prompt=select_account&login_hint=bob%40contoso.com"));
+///
+/// // The user may pick another account than the one suggested
+/// # let claims = IdTokenClaims::from_json(&json!({
+/// #     "iss": "https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0",
+/// #     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+/// #     "aud": "client-id",
+/// #     "exp": 1536361411,
+/// #     "iat": 1536274711,
+/// #     "oid": "00000000-0000-0000-66f3-3332eca7ea81",
+/// #     "tid": "72f988bf-86f1-41af-91ab-2d7cd011db47",
+/// #     "preferred_username": "alice@contoso.com",
+/// # })).unwrap();
+/// // `claims` of the response's ID token, as returned by `IdTokenValidator::validate`
+/// let chosen = find_account(&accounts, &claims);
+/// assert_eq!(chosen.unwrap().username, "alice@contoso.com");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Account {
+    /// Identifies the account: `{oid}.{tid}`, the user and tenant the token was issued for.
+    pub home_account_id: String,
+
+    /// The user's object ID (`oid`) in the tenant that issued the token.
+    pub local_account_id: String,
+
+    /// The tenant that issued the token (`tid`).
+    pub tenant_id: String,
+
+    /// The username (`preferred_username`), to pass as a `login_hint`.
+    pub username: String
+}
+
+impl Account {
+    /// Reads the account from the claims of an ID token, or returns `None` if it has no `oid`
+    /// or `tid`.
+    ///
+    /// The ID token isn't validated, so must come from a trusted source, such as the
+    /// application's own token cache.
+    pub fn from_id_token(id_token: &str) -> Option<Self> {
+        let claims = jwt::decode(id_token)?.claims;
+        let oid = claims["oid"].as_str()?;
+        let tid = claims["tid"].as_str()?;
+
+        Some(Account {
+            home_account_id: format!("{}.{}", oid, tid),
+            local_account_id: oid.to_owned(),
+            tenant_id: tid.to_owned(),
+            username: claims["preferred_username"].as_str().unwrap_or_default().to_owned(),
+        })
+    }
+
+    /// Reads the account from the claims of a validated ID token, or returns `None` if they
+    /// have no `oid` or `tid`.
+    pub fn from_claims(claims: &IdTokenClaims) -> Option<Self> {
+        let oid = claims.oid.as_ref()?;
+        let tid = claims.tid.as_ref()?;
+
+        Some(Account {
+            home_account_id: format!("{}.{}", oid, tid),
+            local_account_id: oid.clone(),
+            tenant_id: tid.clone(),
+            username: claims.preferred_username.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Uses the home account ID of `client_info`, which also identifies guest accounts by
+    /// their home tenant.
+    pub fn with_client_info(mut self, client_info: &ClientInfo) -> Self {
+        self.home_account_id = client_info.home_account_id();
+        self
+    }
+
+    /// Returns true if `other` is the same account, signed in to any tenant.
+    pub fn is_same(&self, other: &Account) -> bool {
+        self.home_account_id == other.home_account_id || self.local_account_id == other.local_account_id
+    }
+}
+
+/// Returns the cached account the user chose, given the claims of the account chooser's
+/// response. The response's ID token must have been validated, eg. with `IdTokenValidator`,
+/// since anyone could otherwise claim to be any of the accounts.
+pub fn find_account<'a>(accounts: &'a [Account], claims: &IdTokenClaims) -> Option<&'a Account> {
+    let chosen = Account::from_claims(claims)?;
+
+    accounts.iter().find(|account| account.is_same(&chosen))
+}
+
+/// The `client_info` Azure AD returns when requested with `client_info=1`: the user's
+/// identifiers in their home tenant, which MSAL keys its token cache with.
+///
+/// ```rust
+/// # use inth_oauth2_azure::ClientInfo;
+/// let client_info = ClientInfo::decode(
+///     "eyJ1aWQiOiIwMDAwMDAwMC0wMDAwLTAwMDAtNjZmMy0zMzMyZWNhN2VhODEiLCJ1dGlkIjoiOTE4ODA0MGQtNmM2Ny00YzViLWIxMTItMzZhMzA0YjY2ZGFkIn0"
+/// ).unwrap();
+///
+/// assert_eq!(
+///     client_info.home_account_id(),
+///     "00000000-0000-0000-66f3-3332eca7ea81.9188040d-6c67-4c5b-b112-36a304b66dad"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClientInfo {
+    /// The user's object ID in their home tenant.
+    pub uid: String,
+
+    /// The user's home tenant ID.
+    pub utid: String
+}
+
+impl ClientInfo {
+    /// Decodes the base64url JSON `client_info` parameter.
+    pub fn decode(client_info: &str) -> Result<Self, ParseError> {
+        let bytes = base64::decode_config(client_info.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .map_err(|_| ParseError::ExpectedFieldType("client_info", "base64url"))?;
+        let json: Value = serde_json::from_slice(&bytes)
+            .map_err(|_| ParseError::ExpectedFieldType("client_info", "JSON"))?;
+
+        let field = |name| json.get(name)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or(ParseError::ExpectedFieldType(name, "string"));
+
+        Ok(ClientInfo { uid: field("uid")?, utid: field("utid")? })
+    }
+
+    /// Returns the MSAL home account ID, `{uid}.{utid}`.
+    pub fn home_account_id(&self) -> String {
+        format!("{}.{}", self.uid, self.utid)
+    }
+}
+
+/// Reads the `client_info` of a token response.
+impl FromResponse for ClientInfo {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let client_info = json.get("client_info")
+            .and_then(Value::as_str)
+            .ok_or(ParseError::ExpectedFieldType("client_info", "string"))?;
+
+        Self::decode(client_info)
+    }
+}
@@ -0,0 +1,81 @@
+//! On-premises Active Directory Federation Services.
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cloud::validate_host;
+use error::AuthorityError;
+use provider::AzureProvider;
+
+/// Users sign in through an on-premises ADFS farm (ADFS 2016 or later).
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::{Adfs, AzureProvider};
+/// let provider = Adfs::new("adfs.contoso.com");
+///
+/// assert_eq!(provider.auth_uri().as_str(), "https://adfs.contoso.com/adfs/oauth2/authorize");
+/// assert_eq!(provider.token_uri().as_str(), "https://adfs.contoso.com/adfs/oauth2/token");
+/// assert_eq!(provider.jwks_uri().unwrap().as_str(), "https://adfs.contoso.com/adfs/discovery/keys");
+/// assert_eq!(provider.discovery_uri().unwrap().as_str(), "https://adfs.contoso.com/adfs/.well-known/openid-configuration");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Adfs {
+    host: String,
+    auth_uri: Url,
+    token_uri: Url
+}
+
+impl Adfs {
+    /// Creates a provider for the ADFS farm at `host`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `host` isn't a bare hostname; use `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(host: &str) -> Self {
+        Self::try_new(host).expect("invalid ADFS host")
+    }
+
+    /// Creates a provider for the ADFS farm at `host`, checking that it's a hostname,
+    /// optionally with a port, and nothing more.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{Adfs, AuthorityError};
+    /// assert!(Adfs::try_new("adfs.contoso.com").is_ok());
+    /// assert!(Adfs::try_new("adfs.contoso.com:8443").is_ok());
+    /// assert_eq!(Adfs::try_new("adfs.contoso.com/evil"), Err(AuthorityError::InvalidHost("adfs.contoso.com/evil".into())));
+    /// assert!(Adfs::try_new("adfs contoso").is_err());
+    /// ```
+    pub fn try_new(host: &str) -> Result<Self, AuthorityError> {
+        let host = host.to_lowercase();
+        validate_host(&host)?;
+
+        let auth_uri = Url::parse( &format!("https://{}/adfs/oauth2/authorize", host) )?;
+        let token_uri = Url::parse( &format!("https://{}/adfs/oauth2/token", host) )?;
+
+        Ok(Self { host, auth_uri, token_uri })
+    }
+
+    /// Returns the ADFS hostname, eg. `adfs.contoso.com`.
+    pub fn host(&self) -> &str { &self.host }
+}
+
+impl Provider for Adfs {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.auth_uri }
+    fn token_uri(&self) -> &Url { &self.token_uri }
+}
+
+impl AzureProvider for Adfs {
+    fn device_authorization_uri(&self) -> Option<Url> { self.auth_uri.join("devicecode").ok() }
+    fn end_session_uri(&self) -> Option<Url> { self.auth_uri.join("logout").ok() }
+    fn jwks_uri(&self) -> Option<Url> { self.auth_uri.join("../discovery/keys").ok() }
+    fn userinfo_uri(&self) -> Option<Url> { self.auth_uri.join("../userinfo").ok() }
+    fn discovery_uri(&self) -> Option<Url> { self.auth_uri.join("../.well-known/openid-configuration").ok() }
+}
@@ -0,0 +1,165 @@
+//! Certificate client assertions.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64;
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+use openssl::x509::X509;
+use serde_json::{self, Value};
+use url::Url;
+use uuid::{Builder, Variant, Version};
+
+/// The `client_assertion_type` sent alongside a JWT client assertion.
+pub const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// How long a signed assertion is valid for, in seconds.
+const ASSERTION_LIFETIME: u64 = 600;
+
+/// The header that identifies the certificate an assertion was signed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Thumbprint {
+    /// `x5t`, the base64url SHA-1 thumbprint.
+    Sha1,
+
+    /// `x5t#S256`, the base64url SHA-256 thumbprint, which Azure AD prefers.
+    #[default]
+    Sha256,
+}
+
+/// Signs JWT client assertions with a certificate registered for the application, for
+/// confidential clients authenticating without a client secret.
+///
+/// Assertions identify the certificate by its SHA-256 thumbprint by default. Policies using
+/// subject name and issuer authentication also need the certificate chain, which is sent in
+/// the `x5c` header with `with_x5c`.
+///
+/// ```rust
+/// # extern crate openssl;
+/// # extern crate inth_oauth2_azure;
+/// # use openssl::asn1::Asn1Time;
+/// # use openssl::hash::MessageDigest;
+/// # use openssl::pkey::PKey;
+/// # use openssl::rsa::Rsa;
+/// # use openssl::x509::X509;
+/// # use inth_oauth2_azure::ClientAssertion;
+/// # fn main() {
+/// # let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+/// # let mut builder = X509::builder().unwrap();
+/// # builder.set_pubkey(&key).unwrap();
+/// # builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+/// # builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+/// # builder.sign(&key, MessageDigest::sha256()).unwrap();
+/// # let certificate = builder.build();
+/// let assertion = ClientAssertion::new(certificate, key).with_x5c(true);
+///
+/// let header = assertion.header().unwrap();
+/// assert!(header["x5t#S256"].is_string());
+/// assert_eq!(header["x5c"].as_array().unwrap().len(), 1);
+///
+/// let token_uri = "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/v2.0/token".parse().unwrap();
+/// let jwt = assertion.sign("client-id", &token_uri).unwrap();
+/// assert_eq!(jwt.split('.').count(), 3);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientAssertion {
+    certificate: X509,
+    key: PKey<Private>,
+    chain: Vec<X509>,
+    thumbprint: Thumbprint,
+    x5c: bool
+}
+
+impl ClientAssertion {
+    /// Creates an assertion signer from a certificate and its RSA private key.
+    pub fn new(certificate: X509, key: PKey<Private>) -> Self {
+        ClientAssertion { certificate, key, chain: Vec::new(), thumbprint: Thumbprint::default(), x5c: false }
+    }
+
+    /// Reads the certificate and private key from PEM, as exported by the Azure CLI. Any
+    /// further certificates are used as the chain.
+    pub fn from_pem(pem: &[u8]) -> Result<Self, ErrorStack> {
+        let mut certificates = X509::stack_from_pem(pem)?.into_iter();
+        let key = PKey::private_key_from_pem(pem)?;
+
+        let certificate = certificates.next().ok_or_else(ErrorStack::get)?;
+        Ok(Self::new(certificate, key).with_chain(certificates.collect()))
+    }
+
+    /// Selects the thumbprint header.
+    pub fn with_thumbprint(mut self, thumbprint: Thumbprint) -> Self {
+        self.thumbprint = thumbprint;
+        self
+    }
+
+    /// Sends the certificate, followed by its chain, in the `x5c` header.
+    pub fn with_x5c(mut self, x5c: bool) -> Self {
+        self.x5c = x5c;
+        self
+    }
+
+    /// Sets the intermediate certificates sent after the certificate in `x5c`.
+    pub fn with_chain(mut self, chain: Vec<X509>) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Returns the JOSE header of the assertions.
+    pub fn header(&self) -> Result<Value, ErrorStack> {
+        let mut header = json!({ "alg": "RS256", "typ": "JWT" });
+
+        let (name, digest) = match self.thumbprint {
+            Thumbprint::Sha1 => ("x5t", MessageDigest::sha1()),
+            Thumbprint::Sha256 => ("x5t#S256", MessageDigest::sha256()),
+        };
+        let thumbprint = self.certificate.digest(digest)?;
+        header[name] = base64::encode_config(&thumbprint, base64::URL_SAFE_NO_PAD).into();
+
+        if self.x5c {
+            let mut x5c = vec![base64::encode(&self.certificate.to_der()?)];
+            for certificate in &self.chain {
+                x5c.push(base64::encode(&certificate.to_der()?));
+            }
+            header["x5c"] = x5c.into();
+        }
+
+        Ok(header)
+    }
+
+    /// Signs an assertion for `client_id`. The audience is the token endpoint it will be
+    /// sent to.
+    pub fn sign(&self, client_id: &str, audience: &Url) -> Result<String, ErrorStack> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+
+        let mut jti = [0; 16];
+        rand_bytes(&mut jti)?;
+
+        let jti = Builder::from_bytes(jti).set_variant(Variant::RFC4122).set_version(Version::Random).build();
+
+        let claims = json!({
+            "aud": audience.as_str(),
+            "iss": client_id,
+            "sub": client_id,
+            "jti": jti.to_hyphenated().to_string(),
+            "nbf": now,
+            "iat": now,
+            "exp": now + ASSERTION_LIFETIME,
+        });
+
+        let message = format!("{}.{}", encode_json(&self.header()?), encode_json(&claims));
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(message.as_bytes())?;
+        let signature = signer.sign_to_vec()?;
+
+        Ok(format!("{}.{}", message, base64::encode_config(&signature, base64::URL_SAFE_NO_PAD)))
+    }
+}
+
+fn encode_json(value: &Value) -> String {
+    base64::encode_config(&serde_json::to_vec(value).unwrap_or_default(), base64::URL_SAFE_NO_PAD)
+}
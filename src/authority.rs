@@ -0,0 +1,369 @@
+//! Runtime-selectable authorities.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cloud::AzureCloud;
+use error::AuthorityError;
+use provider::AzureProvider;
+use tenant::{EndpointVersion, TenantId};
+use {Adfs, AzureB2C, AzureCommon, AzureConsumer, AzureExternalId, AzureOrganization, AzureTenant, AzureV1, CustomAzureProvider};
+
+/// Any of the providers in this crate, chosen at runtime.
+///
+/// Each provider is a distinct type, which makes it awkward to store a `Client` whose
+/// audience comes from configuration. `AzureAuthority` wraps them all so a single
+/// `Client<AzureAuthority>` can be used instead. Sovereign cloud authorities are
+/// represented by the `Tenant` variant, see `AzureCommon::in_cloud`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureAuthority, AzureCommon, AzureTenant};
+/// let tenant: Option<&str> = None;
+///
+/// let authority: AzureAuthority = match tenant {
+///     Some(id) => AzureTenant::new(id).into(),
+///     None => AzureCommon.into(),
+/// };
+///
+/// let client = oauth::Client::new(
+///     authority,
+///     "client-id".into(),
+///     "client-secret".into(),
+///     Some("redirect-uri".into())
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AzureAuthority {
+    /// Personal and work or school accounts, see `AzureCommon`.
+    Common(AzureCommon),
+
+    /// Work or school accounts only, see `AzureOrganization`.
+    Organization(AzureOrganization),
+
+    /// Personal accounts only, see `AzureConsumer`.
+    Consumer(AzureConsumer),
+
+    /// A specific tenant, or any authority in another cloud, see `AzureTenant`.
+    Tenant(AzureTenant),
+
+    /// An Azure AD B2C user flow, see `AzureB2C`.
+    B2C(AzureB2C),
+
+    /// A Microsoft Entra External ID tenant, see `AzureExternalId`.
+    ExternalId(AzureExternalId),
+
+    /// An on-premises ADFS farm, see `Adfs`.
+    Adfs(Adfs),
+
+    /// The Azure AD v1.0 endpoints, see `AzureV1`.
+    V1(AzureV1),
+
+    /// Arbitrary endpoints, see `CustomAzureProvider`.
+    Custom(CustomAzureProvider),
+}
+
+impl AzureAuthority {
+    /// Returns the provider for an authority or issuer URL, such as the `authority` of an
+    /// app registration or an OpenID Connect issuer.
+    ///
+    /// Recognises Azure AD authorities in every cloud, `b2clogin.com`, `ciamlogin.com` and
+    /// ADFS authorities. B2C tenants on a custom domain can't be told apart from other hosts
+    /// and must be built with `AzureB2C::with_custom_domain` instead.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::provider::Provider;
+    /// # use inth_oauth2_azure::{AuthorityError, AzureAuthority, AzureCommon, AzureTenantError};
+    /// let authority = AzureAuthority::from_authority_url("https://login.microsoftonline.com/common").unwrap();
+    /// assert_eq!(authority, AzureAuthority::Common(AzureCommon));
+    ///
+    /// let authority = AzureAuthority::from_authority_url(
+    ///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/v2.0"
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     authority.token_uri().as_str(),
+    ///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/v2.0/token"
+    /// );
+    ///
+    /// assert!(AzureAuthority::from_authority_url("https://example.com/contoso").is_err());
+    /// assert_eq!(
+    ///     AzureAuthority::from_authority_url("https://login.microsoftonline.com/bad%20tenant/v2.0"),
+    ///     Err(AuthorityError::Tenant(AzureTenantError::InvalidCharacter('%')))
+    /// );
+    /// ```
+    pub fn from_authority_url(url: &str) -> Result<Self, AuthorityError> {
+        Self::try_from(Url::parse(url)?)
+    }
+
+    /// Public cloud audiences get their own variants, everything else is a tenant.
+    pub(crate) fn in_cloud(tenant: TenantId, cloud: AzureCloud) -> Self {
+        match (tenant, cloud) {
+            (TenantId::Common, AzureCloud::Public) => AzureCommon.into(),
+            (TenantId::Organizations, AzureCloud::Public) => AzureOrganization.into(),
+            (TenantId::Consumers, AzureCloud::Public) => AzureConsumer.into(),
+            (tenant, cloud) => AzureTenant::from_tenant_id(tenant, cloud).into(),
+        }
+    }
+}
+
+impl TryFrom<Url> for AzureAuthority {
+    type Error = AuthorityError;
+
+    fn try_from(url: Url) -> Result<Self, Self::Error> {
+        let host = url.host_str().map(str::to_lowercase).ok_or(AuthorityError::MissingSegment("host"))?;
+        let segments: Vec<&str> = url.path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+
+        if host.ends_with(".b2clogin.com") {
+            // B2C issuers omit the policy, so there's not enough to go on
+            let policy = segments.get(1)
+                .filter(|p| **p != "v2.0")
+                .ok_or(AuthorityError::MissingSegment("policy"))?;
+            return Ok(AzureB2C::try_new(host.trim_end_matches(".b2clogin.com"), policy)?.into());
+        }
+
+        if host.ends_with(".ciamlogin.com") {
+            let subdomain = host.trim_end_matches(".ciamlogin.com");
+            return match segments.first() {
+                Some(tenant) if ::is_guid(tenant) => Ok(AzureExternalId::try_with_tenant_id(subdomain, tenant)?.into()),
+                _ => Ok(AzureExternalId::try_new(subdomain)?.into()),
+            };
+        }
+
+        if segments.first() == Some(&"adfs") {
+            return Ok(Adfs::try_new(&host)?.into());
+        }
+
+        let cloud = match AzureCloud::from_authority_host(&host)? {
+            AzureCloud::Custom(_) => return Err(AuthorityError::UnrecognizedHost(host)),
+            cloud => cloud,
+        };
+        let tenant = segments.first().ok_or(AuthorityError::MissingSegment("tenant"))?;
+
+        Ok(Self::in_cloud(tenant.parse()?, cloud))
+    }
+}
+
+/// Parses `common`, `organizations`, `consumers`, a tenant identifier or an authority URL,
+/// as typically found in configuration.
+///
+/// ```rust
+/// # use inth_oauth2_azure::{AzureAuthority, AzureOrganization, AzureTenant};
+/// assert_eq!("organizations".parse(), Ok(AzureAuthority::Organization(AzureOrganization)));
+/// assert_eq!(
+///     "contoso.onmicrosoft.com".parse(),
+///     Ok(AzureAuthority::Tenant(AzureTenant::new("contoso.onmicrosoft.com")))
+/// );
+/// assert!("https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_susi".parse::<AzureAuthority>().is_ok());
+/// assert!("contoso".parse::<AzureAuthority>().is_err());
+/// ```
+impl FromStr for AzureAuthority {
+    type Err = AuthorityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains("://") {
+            return Self::from_authority_url(s);
+        }
+
+        Ok(Self::in_cloud(s.parse()?, AzureCloud::Public))
+    }
+}
+
+/// Formats the authority as configuration would give it: public cloud audiences and v2.0
+/// tenants as their tenant identifier, anything else as its authority URL.
+///
+/// The result can be parsed back with `FromStr`, except for what authority URLs can't
+/// express: regional token endpoints, tenants on the v1.0 endpoints and `V1` providers
+/// (which parse back as v2.0 tenants, losing the resource of `V1`), and `Custom`
+/// providers, which are written as their authorization endpoint and don't parse at all.
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureAuthority, AzureTenant, AzureV1, CustomAzureProvider, EndpointVersion};
+/// # fn main() {
+/// let tenant = AzureAuthority::from(AzureTenant::new("contoso.onmicrosoft.com"));
+/// assert_eq!(tenant.to_string(), "contoso.onmicrosoft.com");
+/// assert_eq!(tenant.to_string().parse(), Ok(tenant));
+///
+/// let v1 = AzureTenant::builder().tenant("contoso.onmicrosoft.com").version(EndpointVersion::V1).build().unwrap();
+/// let v1 = AzureAuthority::from(v1);
+/// assert_eq!(v1.to_string(), "https://login.microsoftonline.com/contoso.onmicrosoft.com");
+/// assert!(v1.to_string().parse::<AzureAuthority>().unwrap() != v1);
+///
+/// let v1 = AzureAuthority::from(AzureV1::new("contoso.onmicrosoft.com"));
+/// assert_eq!(v1.to_string(), "https://login.microsoftonline.com/contoso.onmicrosoft.com");
+/// assert!(v1.to_string().parse::<AzureAuthority>().unwrap() != v1);
+///
+/// let custom = AzureAuthority::from(CustomAzureProvider::new("https://mock.local/authorize", "https://mock.local/token").unwrap());
+/// assert_eq!(custom.to_string(), "https://mock.local/authorize");
+/// assert!(custom.to_string().parse::<AzureAuthority>().is_err());
+/// # }
+/// ```
+impl fmt::Display for AzureAuthority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            AzureAuthority::Common(_) => write!(f, "common"),
+            AzureAuthority::Organization(_) => write!(f, "organizations"),
+            AzureAuthority::Consumer(_) => write!(f, "consumers"),
+            AzureAuthority::Tenant(ref p)
+                if *p.cloud() == AzureCloud::Public && p.version() == EndpointVersion::V2 && p.region().is_none() =>
+            {
+                write!(f, "{}", p.tenant_id())
+            },
+            AzureAuthority::Custom(ref p) => write!(f, "{}", p.auth_uri()),
+            ref authority => {
+                let uri = authority.auth_uri().as_str();
+                let end = uri.find("/oauth2/").unwrap_or(uri.len());
+                write!(f, "{}", &uri[..end])
+            },
+        }
+    }
+}
+
+impl Provider for AzureAuthority {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url {
+        match *self {
+            AzureAuthority::Common(ref p) => p.auth_uri(),
+            AzureAuthority::Organization(ref p) => p.auth_uri(),
+            AzureAuthority::Consumer(ref p) => p.auth_uri(),
+            AzureAuthority::Tenant(ref p) => p.auth_uri(),
+            AzureAuthority::B2C(ref p) => p.auth_uri(),
+            AzureAuthority::ExternalId(ref p) => p.auth_uri(),
+            AzureAuthority::Adfs(ref p) => p.auth_uri(),
+            AzureAuthority::V1(ref p) => p.auth_uri(),
+            AzureAuthority::Custom(ref p) => p.auth_uri(),
+        }
+    }
+
+    fn token_uri(&self) -> &Url {
+        match *self {
+            AzureAuthority::Common(ref p) => p.token_uri(),
+            AzureAuthority::Organization(ref p) => p.token_uri(),
+            AzureAuthority::Consumer(ref p) => p.token_uri(),
+            AzureAuthority::Tenant(ref p) => p.token_uri(),
+            AzureAuthority::B2C(ref p) => p.token_uri(),
+            AzureAuthority::ExternalId(ref p) => p.token_uri(),
+            AzureAuthority::Adfs(ref p) => p.token_uri(),
+            AzureAuthority::V1(ref p) => p.token_uri(),
+            AzureAuthority::Custom(ref p) => p.token_uri(),
+        }
+    }
+}
+
+impl AzureProvider for AzureAuthority {
+    fn device_authorization_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.device_authorization_uri(),
+            AzureAuthority::Organization(ref p) => p.device_authorization_uri(),
+            AzureAuthority::Consumer(ref p) => p.device_authorization_uri(),
+            AzureAuthority::Tenant(ref p) => p.device_authorization_uri(),
+            AzureAuthority::B2C(ref p) => p.device_authorization_uri(),
+            AzureAuthority::ExternalId(ref p) => p.device_authorization_uri(),
+            AzureAuthority::Adfs(ref p) => p.device_authorization_uri(),
+            AzureAuthority::V1(ref p) => p.device_authorization_uri(),
+            AzureAuthority::Custom(ref p) => p.device_authorization_uri(),
+        }
+    }
+
+    fn end_session_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.end_session_uri(),
+            AzureAuthority::Organization(ref p) => p.end_session_uri(),
+            AzureAuthority::Consumer(ref p) => p.end_session_uri(),
+            AzureAuthority::Tenant(ref p) => p.end_session_uri(),
+            AzureAuthority::B2C(ref p) => p.end_session_uri(),
+            AzureAuthority::ExternalId(ref p) => p.end_session_uri(),
+            AzureAuthority::Adfs(ref p) => p.end_session_uri(),
+            AzureAuthority::V1(ref p) => p.end_session_uri(),
+            AzureAuthority::Custom(ref p) => p.end_session_uri(),
+        }
+    }
+
+    fn jwks_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.jwks_uri(),
+            AzureAuthority::Organization(ref p) => p.jwks_uri(),
+            AzureAuthority::Consumer(ref p) => p.jwks_uri(),
+            AzureAuthority::Tenant(ref p) => p.jwks_uri(),
+            AzureAuthority::B2C(ref p) => p.jwks_uri(),
+            AzureAuthority::ExternalId(ref p) => p.jwks_uri(),
+            AzureAuthority::Adfs(ref p) => p.jwks_uri(),
+            AzureAuthority::V1(ref p) => p.jwks_uri(),
+            AzureAuthority::Custom(ref p) => p.jwks_uri(),
+        }
+    }
+
+    fn userinfo_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.userinfo_uri(),
+            AzureAuthority::Organization(ref p) => p.userinfo_uri(),
+            AzureAuthority::Consumer(ref p) => p.userinfo_uri(),
+            AzureAuthority::Tenant(ref p) => p.userinfo_uri(),
+            AzureAuthority::B2C(ref p) => p.userinfo_uri(),
+            AzureAuthority::ExternalId(ref p) => p.userinfo_uri(),
+            AzureAuthority::Adfs(ref p) => p.userinfo_uri(),
+            AzureAuthority::V1(ref p) => p.userinfo_uri(),
+            AzureAuthority::Custom(ref p) => p.userinfo_uri(),
+        }
+    }
+
+    fn admin_consent_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.admin_consent_uri(),
+            AzureAuthority::Organization(ref p) => p.admin_consent_uri(),
+            AzureAuthority::Consumer(ref p) => p.admin_consent_uri(),
+            AzureAuthority::Tenant(ref p) => p.admin_consent_uri(),
+            AzureAuthority::B2C(ref p) => p.admin_consent_uri(),
+            AzureAuthority::ExternalId(ref p) => p.admin_consent_uri(),
+            AzureAuthority::Adfs(ref p) => p.admin_consent_uri(),
+            AzureAuthority::V1(ref p) => p.admin_consent_uri(),
+            AzureAuthority::Custom(ref p) => p.admin_consent_uri(),
+        }
+    }
+
+    fn discovery_uri(&self) -> Option<Url> {
+        match *self {
+            AzureAuthority::Common(ref p) => p.discovery_uri(),
+            AzureAuthority::Organization(ref p) => p.discovery_uri(),
+            AzureAuthority::Consumer(ref p) => p.discovery_uri(),
+            AzureAuthority::Tenant(ref p) => p.discovery_uri(),
+            AzureAuthority::B2C(ref p) => p.discovery_uri(),
+            AzureAuthority::ExternalId(ref p) => p.discovery_uri(),
+            AzureAuthority::Adfs(ref p) => p.discovery_uri(),
+            AzureAuthority::V1(ref p) => p.discovery_uri(),
+            AzureAuthority::Custom(ref p) => p.discovery_uri(),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($v:path, $t:ty) => {
+        impl From<$t> for AzureAuthority {
+            fn from(provider: $t) -> Self {
+                $v(provider)
+            }
+        }
+    }
+}
+
+impl_from!(AzureAuthority::Common, AzureCommon);
+impl_from!(AzureAuthority::Organization, AzureOrganization);
+impl_from!(AzureAuthority::Consumer, AzureConsumer);
+impl_from!(AzureAuthority::Tenant, AzureTenant);
+impl_from!(AzureAuthority::B2C, AzureB2C);
+impl_from!(AzureAuthority::ExternalId, AzureExternalId);
+impl_from!(AzureAuthority::Adfs, Adfs);
+impl_from!(AzureAuthority::V1, AzureV1);
+impl_from!(AzureAuthority::Custom, CustomAzureProvider);
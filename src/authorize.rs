@@ -0,0 +1,464 @@
+//! Authorization requests.
+
+use std::fmt;
+use std::time::Duration;
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+
+use account::Account;
+use pkce::{Pkce, PKCE_METHOD};
+use random::{random_token, DEFAULT_ENTROPY};
+use scopes::{self, qualifier};
+
+/// Builds an authorization endpoint URI with the parameters Azure AD understands beyond
+/// those of `inth_oauth2::Client::auth_uri`.
+///
+/// A `state`, and a `nonce` when `openid` or an ID token is requested, are generated unless set explicitly.
+/// `build` returns them in an `AuthorizationSession`, which should be kept (eg. in the user's
+/// session) to check the callback against.
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AuthorizationRequest, AzureCommon, DomainHint, Pkce, Prompt, ResponseMode};
+/// let (uri, session) = AuthorizationRequest::new(&AzureCommon, "client-id")
+///     .redirect_uri("https://localhost/callback")
+///     .scope("openid profile offline_access")
+///     .prompt(Prompt::SelectAccount)
+///     .login_hint("user@contoso.com")
+///     .domain_hint(DomainHint::Domain("contoso.com".into()))
+///     .response_mode(ResponseMode::FormPost)
+///     .pkce(Pkce::new())
+///     .build();
+///
+/// let query = uri.query().unwrap();
+/// assert!(query.starts_with("response_type=code&client_id=client-id"));
+/// assert!(query.contains(&format!("state={}", session.state)));
+/// assert!(query.contains(&format!("nonce={}", session.nonce.unwrap())));
+/// assert!(query.contains("prompt=select_account&login_hint=user%40contoso.com&domain_hint=contoso.com"));
+/// assert!(query.contains("response_mode=form_post"));
+/// assert!(query.contains("code_challenge_method=S256"));
+/// assert!(session.code_verifier.is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationRequest {
+    auth_uri: Url,
+    client_id: String,
+    response_type: String,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    nonce: Option<String>,
+    prompt: Option<Prompt>,
+    login_hint: Option<String>,
+    domain_hint: Option<DomainHint>,
+    id_token_hint: Option<String>,
+    response_mode: Option<ResponseMode>,
+    code_challenge: Option<(String, String)>,
+    code_verifier: Option<String>,
+    claims: Option<String>,
+    max_age: Option<u64>,
+    entropy: usize,
+    params: Vec<(String, String)>,
+}
+
+/// The type of user interaction an authorization request requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Prompt {
+    /// The user must enter their credentials, even if they have a session.
+    Login,
+
+    /// No interaction. The request fails with `interaction_required` or `login_required`
+    /// instead of showing a page, eg. for silent token renewal.
+    None,
+
+    /// The user is asked to consent to the requested permissions, even if they already have.
+    Consent,
+
+    /// The user picks an account, even if they're signed in with only one.
+    SelectAccount,
+
+    /// The user is offered to create an account, for External ID and B2C user flows.
+    Create,
+}
+
+impl Prompt {
+    /// Returns the value of the `prompt` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Prompt::Login => "login",
+            Prompt::None => "none",
+            Prompt::Consent => "consent",
+            Prompt::SelectAccount => "select_account",
+            Prompt::Create => "create",
+        }
+    }
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How the authorization response is returned to the redirect URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseMode {
+    /// In the query string. The default for `response_type=code`.
+    Query,
+
+    /// In the URI fragment, so only the browser sees it. The default when the response
+    /// includes tokens.
+    Fragment,
+
+    /// As an urlencoded POST body, see `Callback::from_form`. Recommended for web apps, since
+    /// the response never appears in the URI.
+    FormPost,
+}
+
+impl ResponseMode {
+    /// Returns the value of the `response_mode` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResponseMode::Query => "query",
+            ResponseMode::Fragment => "fragment",
+            ResponseMode::FormPost => "form_post",
+        }
+    }
+}
+
+impl fmt::Display for ResponseMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where the user's account lives, so Azure AD can skip home realm discovery and send them
+/// straight to the right sign-in page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DomainHint {
+    /// Personal Microsoft accounts.
+    Consumers,
+
+    /// Work and school accounts.
+    Organizations,
+
+    /// A domain verified in the user's tenant, eg. `contoso.com`. Federated domains redirect
+    /// to the organization's identity provider.
+    Domain(String),
+}
+
+impl DomainHint {
+    /// Returns the value of the `domain_hint` parameter.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            DomainHint::Consumers => "consumers",
+            DomainHint::Organizations => "organizations",
+            DomainHint::Domain(ref domain) => domain,
+        }
+    }
+}
+
+impl fmt::Display for DomainHint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The values generated for an authorization request, to verify its callback with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuthorizationSession {
+    /// The `state` the callback must echo.
+    pub state: String,
+
+    /// The `nonce` ID tokens must contain.
+    pub nonce: Option<String>,
+
+    /// The PKCE `code_verifier` to redeem the authorization code with.
+    pub code_verifier: Option<String>,
+
+    /// The `max_age` the user's authentication must not exceed, in seconds, to pass to
+    /// `IdTokenValidator::max_age`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_age: Option<u64>,
+}
+
+impl AuthorizationRequest {
+    /// Starts a request to the provider's authorization endpoint, for the authorization
+    /// code flow.
+    pub fn new<P: Provider>(provider: &P, client_id: &str) -> Self {
+        AuthorizationRequest {
+            auth_uri: provider.auth_uri().clone(),
+            client_id: client_id.to_owned(),
+            response_type: "code".to_owned(),
+            redirect_uri: None,
+            scope: None,
+            state: None,
+            nonce: None,
+            prompt: None,
+            login_hint: None,
+            domain_hint: None,
+            id_token_hint: None,
+            response_mode: None,
+            code_challenge: None,
+            code_verifier: None,
+            claims: None,
+            max_age: None,
+            entropy: DEFAULT_ENTROPY,
+            params: Vec::new(),
+        }
+    }
+
+    /// Sets the `response_type`, eg. `code id_token`.
+    pub fn response_type(mut self, response_type: &str) -> Self {
+        self.response_type = response_type.to_owned();
+        self
+    }
+
+    /// Uses the hybrid flow (`response_type=code id_token`): an ID token is returned with
+    /// the authorization code, so the user can be signed in before the code is redeemed.
+    ///
+    /// The response is POSTed to the redirect URI unless another `response_mode` is set,
+    /// since Azure AD never returns tokens in the query. Check it with
+    /// `AuthorizationResponse::verify_hybrid`.
+    pub fn hybrid(mut self) -> Self {
+        self.response_type = "code id_token".to_owned();
+        self.response_mode = self.response_mode.or(Some(ResponseMode::FormPost));
+        self
+    }
+
+    /// Sets the redirect URI, exactly as registered for the application.
+    pub fn redirect_uri(mut self, redirect_uri: &str) -> Self {
+        self.redirect_uri = Some(redirect_uri.to_owned());
+        self
+    }
+
+    /// Sets the space-delimited scopes.
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_owned());
+        self
+    }
+
+    /// Requests the scopes an API call needs on top of those already `granted`, as listed in
+    /// the access token's `scp` claim. The user is only asked to consent (`prompt=consent`)
+    /// when a needed scope hasn't been granted yet.
+    ///
+    /// Granted scopes are qualified with the resource of the needed ones, which must belong to
+    /// the token's resource.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AuthorizationRequest, AzureCommon};
+    /// let (uri, _) = AuthorizationRequest::new(&AzureCommon, "client-id")
+    ///     .incremental_consent("User.Read", &["api://orders/Orders.Read", "api://orders/User.Read"])
+    ///     .build();
+    /// let query = uri.query().unwrap();
+    /// assert!(query.contains("scope=api%3A%2F%2Forders%2FUser.Read+api%3A%2F%2Forders%2FOrders.Read&"));
+    /// assert!(query.contains("prompt=consent"));
+    ///
+    /// let (uri, _) = AuthorizationRequest::new(&AzureCommon, "client-id")
+    ///     .incremental_consent("User.Read Mail.Read", &["Mail.Read"])
+    ///     .build();
+    /// assert!(!uri.query().unwrap().contains("prompt"));
+    /// ```
+    pub fn incremental_consent<S: AsRef<str>>(mut self, granted: &str, needed: &[S]) -> Self {
+        let prefix = needed.iter()
+            .map(AsRef::as_ref)
+            .find_map(|s| qualifier(s).map(|i| &s[..i]));
+
+        let mut scope: Vec<String> = granted.split_whitespace()
+            .map(|g| match prefix {
+                Some(prefix) if !scopes::is_oidc(g) => format!("{}/{}", prefix, g),
+                _ => g.to_owned(),
+            })
+            .collect();
+
+        let mut consent = false;
+        for needed in needed.iter().map(AsRef::as_ref) {
+            if scopes::is_granted(granted, needed) || scope.iter().any(|s| s == needed) {
+                continue;
+            }
+
+            // The OpenID Connect scopes are never listed in `scp`, nor need consent again
+            consent |= !scopes::is_oidc(needed);
+            scope.push(needed.to_owned());
+        }
+
+        self.scope = Some(scope.join(" "));
+        if consent {
+            self.prompt = Some(Prompt::Consent);
+        }
+        self
+    }
+
+    /// Sets the `state` instead of generating one.
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_owned());
+        self
+    }
+
+    /// Sets the `nonce` instead of generating one.
+    pub fn nonce(mut self, nonce: &str) -> Self {
+        self.nonce = Some(nonce.to_owned());
+        self
+    }
+
+    /// Sets the `prompt`.
+    pub fn prompt(mut self, prompt: Prompt) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// Signs the user in without interaction (`prompt=none`), using their existing session
+    /// with Azure AD. Pass a `login_hint` when more than one account may be signed in.
+    ///
+    /// The callback fails with an error instead of showing a page when the user must
+    /// interact; see `Callback::into_silent`.
+    pub fn silent(self) -> Self {
+        self.prompt(Prompt::None)
+    }
+
+    /// Shows the account chooser (`prompt=select_account`), suggesting `account` if given.
+    /// Find the account the user chose with `find_account`.
+    pub fn select_account(self, account: Option<&Account>) -> Self {
+        let request = self.prompt(Prompt::SelectAccount);
+
+        match account {
+            Some(account) if !account.username.is_empty() => request.login_hint(&account.username),
+            _ => request,
+        }
+    }
+
+    /// Pre-fills the username with a UPN or email address, eg. `user@contoso.com`.
+    pub fn login_hint(mut self, login_hint: &str) -> Self {
+        self.login_hint = Some(login_hint.to_owned());
+        self
+    }
+
+    /// Skips home realm discovery.
+    pub fn domain_hint(mut self, domain_hint: DomainHint) -> Self {
+        self.domain_hint = Some(domain_hint);
+        self
+    }
+
+    /// Passes an ID token previously issued to the user, eg. to re-authenticate them with
+    /// `prompt=none`, or for B2C flows that require it.
+    pub fn id_token_hint(mut self, id_token: &str) -> Self {
+        self.id_token_hint = Some(id_token.to_owned());
+        self
+    }
+
+    /// Sets how the response is returned.
+    pub fn response_mode(mut self, response_mode: ResponseMode) -> Self {
+        self.response_mode = Some(response_mode);
+        self
+    }
+
+    /// Sets a PKCE code challenge and its method, eg. `S256`.
+    pub fn code_challenge(mut self, code_challenge: &str, method: &str) -> Self {
+        self.code_challenge = Some((code_challenge.to_owned(), method.to_owned()));
+        self
+    }
+
+    /// Protects the request with PKCE. The verifier is returned in the session.
+    pub fn pkce(mut self, pkce: Pkce) -> Self {
+        self.code_challenge = Some((pkce.challenge().to_owned(), PKCE_METHOD.to_owned()));
+        self.code_verifier = Some(pkce.verifier().to_owned());
+        self
+    }
+
+    /// Requires the user to have authenticated within `max_age`, signing in again otherwise.
+    /// Check the ID token with `IdTokenValidator::max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age.as_secs());
+        self
+    }
+
+    /// Sets the JSON `claims` parameter, from a `ClaimsRequest` or a `claims_challenge`.
+    pub fn claims(mut self, claims: &str) -> Self {
+        self.claims = Some(claims.to_owned());
+        self
+    }
+
+    /// Requests the user's `ClientInfo` with the response (`client_info=1`).
+    pub fn client_info(self) -> Self {
+        self.param("client_info", "1")
+    }
+
+    /// Sets the entropy of the generated `state` and `nonce`, in bytes. Defaults to 32.
+    pub fn entropy(mut self, bytes: usize) -> Self {
+        self.entropy = bytes;
+        self
+    }
+
+    /// Adds any other query parameter.
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Returns the authorization endpoint URI to direct the user to, and the values to check
+    /// the callback against.
+    pub fn build(self) -> (Url, AuthorizationSession) {
+        let openid = self.scope.as_ref().is_some_and(|s| s.split_whitespace().any(|s| s == "openid"))
+            || self.response_type.split_whitespace().any(|t| t == "id_token");
+
+        let entropy = self.entropy;
+        let state = self.state.unwrap_or_else(|| random_token(entropy));
+        let nonce = match self.nonce {
+            Some(nonce) => Some(nonce),
+            None if openid => Some(random_token(entropy)),
+            None => None,
+        };
+
+        let mut uri = self.auth_uri;
+
+        {
+            let mut query = uri.query_pairs_mut();
+
+            query.append_pair("response_type", &self.response_type);
+            query.append_pair("client_id", &self.client_id);
+
+            if let Some(ref redirect_uri) = self.redirect_uri {
+                query.append_pair("redirect_uri", redirect_uri);
+            }
+            if let Some(ref scope) = self.scope {
+                query.append_pair("scope", scope);
+            }
+            query.append_pair("state", &state);
+            if let Some(ref nonce) = nonce {
+                query.append_pair("nonce", nonce);
+            }
+            if let Some(prompt) = self.prompt {
+                query.append_pair("prompt", prompt.as_str());
+            }
+            if let Some(ref login_hint) = self.login_hint {
+                query.append_pair("login_hint", login_hint);
+            }
+            if let Some(ref domain_hint) = self.domain_hint {
+                query.append_pair("domain_hint", domain_hint.as_str());
+            }
+            if let Some(ref id_token_hint) = self.id_token_hint {
+                query.append_pair("id_token_hint", id_token_hint);
+            }
+            if let Some(response_mode) = self.response_mode {
+                query.append_pair("response_mode", response_mode.as_str());
+            }
+            if let Some((ref challenge, ref method)) = self.code_challenge {
+                query.append_pair("code_challenge", challenge);
+                query.append_pair("code_challenge_method", method);
+            }
+            if let Some(max_age) = self.max_age {
+                query.append_pair("max_age", &max_age.to_string());
+            }
+            if let Some(ref claims) = self.claims {
+                query.append_pair("claims", claims);
+            }
+            for (name, value) in &self.params {
+                query.append_pair(name, value);
+            }
+        }
+
+        (uri, AuthorizationSession { state, nonce, code_verifier: self.code_verifier, max_age: self.max_age })
+    }
+}
@@ -0,0 +1,304 @@
+//! Azure AD B2C.
+
+use reqwest;
+use url::Url;
+use url::form_urlencoded::Serializer;
+use inth_oauth2::{Client, ClientError};
+use inth_oauth2::client::response::FromResponse;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cloud::validate_host;
+use error::{AuthorityError, AzureTenantError};
+use {is_guid, short_name};
+use provider::AzureProvider;
+use request::post_token;
+
+/// The conventional name of a B2C resource owner password credentials user flow.
+pub const B2C_ROPC_POLICY: &str = "B2C_1_ROPC_Auth";
+
+/// The error code B2C returns when a user clicks "Forgot your password?" in a sign-in
+/// user flow that doesn't handle password reset itself.
+pub const B2C_PASSWORD_RESET_ERROR: &str = "AADB2C90118";
+
+/// Returns true if an `error_description` from a B2C callback means the user asked to
+/// reset their password, and authorization should be restarted with a password reset
+/// user flow.
+///
+/// ```rust
+/// # use inth_oauth2_azure::is_password_reset_error;
+/// assert!(is_password_reset_error("AADB2C90118: The user has forgotten their password."));
+/// assert!(!is_password_reset_error("AADB2C90091: The user has cancelled entering self-asserted information."));
+/// ```
+pub fn is_password_reset_error(error_description: &str) -> bool {
+    error_description.trim_start().starts_with(B2C_PASSWORD_RESET_ERROR)
+}
+
+/// Returns the authorization URI that restarts authorization with a password reset user
+/// flow, after a callback failed with `AADB2C90118`.
+///
+/// The client ID and redirect URI are taken from `client`; only the policy changes.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureB2C, is_password_reset_error, password_reset_auth_uri};
+/// let client = oauth::Client::new(
+///     AzureB2C::new("contoso", "B2C_1_susi"),
+///     "client-id".into(),
+///     "client-secret".into(),
+///     Some("https://localhost/callback".into())
+/// );
+///
+/// let error_description = "AADB2C90118: The user has forgotten their password.";
+/// if is_password_reset_error(error_description) {
+///     let uri = password_reset_auth_uri(&client, "B2C_1_reset", Some("openid"), None).unwrap();
+///     assert!(uri.path().contains("/B2C_1_reset/"));
+/// }
+/// ```
+pub fn password_reset_auth_uri(
+    client: &Client<AzureB2C>,
+    reset_policy: &str,
+    scope: Option<&str>,
+    state: Option<&str>,
+) -> Result<Url, AuthorityError> {
+    let reset = Client::new(
+        client.provider.try_with_policy(reset_policy)?,
+        client.client_id.clone(),
+        client.client_secret.clone(),
+        client.redirect_uri.clone(),
+    );
+
+    Ok(reset.auth_uri(scope, state))
+}
+
+/// Users sign in through an Azure AD B2C user flow (policy).
+///
+/// The tenant can be given either as its short name or as its full `onmicrosoft.com` domain.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::{AzureB2C, AzureProvider};
+/// let provider = AzureB2C::new("contoso", "B2C_1_susi");
+///
+/// assert_eq!(
+///     provider.auth_uri().as_str(),
+///     "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_susi/oauth2/v2.0/authorize"
+/// );
+/// assert_eq!(
+///     provider.jwks_uri().unwrap().as_str(),
+///     "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_susi/discovery/v2.0/keys"
+/// );
+/// assert_eq!(
+///     provider.discovery_uri().unwrap().as_str(),
+///     "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_susi/v2.0/.well-known/openid-configuration"
+/// );
+/// ```
+///
+/// Tenants using a [custom domain][custom] can override the authority host:
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureB2C;
+/// let provider = AzureB2C::with_custom_domain("login.contoso.com", "contoso", "B2C_1_susi");
+///
+/// assert_eq!(
+///     provider.token_uri().as_str(),
+///     "https://login.contoso.com/contoso.onmicrosoft.com/B2C_1_susi/oauth2/v2.0/token"
+/// );
+/// ```
+///
+/// [custom]: https://docs.microsoft.com/en-us/azure/active-directory-b2c/custom-domain
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureB2C {
+    host: String,
+    tenant: String,
+    policy: String,
+    auth_uri: Url,
+    token_uri: Url
+}
+
+impl AzureB2C {
+    /// Creates a provider for a user flow in a B2C tenant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tenant isn't a valid name or the policy a valid user flow name; use
+    /// `try_new` when they come from configuration or user input.
+    pub fn new(tenant: &str, policy: &str) -> Self {
+        Self::try_new(tenant, policy).expect("invalid B2C tenant or user flow")
+    }
+
+    /// Creates a provider for a user flow in a B2C tenant, checking that the tenant is a
+    /// valid name and the policy a valid user flow name.
+    ///
+    /// The `b2clogin.com` host is named after the tenant, so GUIDs are only accepted with a
+    /// custom domain.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AuthorityError, AzureB2C};
+    /// assert!(AzureB2C::try_new("Contoso.OnMicrosoft.com", "B2C_1_susi").is_ok());
+    /// assert!(AzureB2C::try_new("contoso/evil", "B2C_1_susi").is_err());
+    /// assert!(AzureB2C::try_new("a2b9a3b4-4f71-4e2a-9a0e-6d0b4b1b5a3c", "B2C_1_susi").is_err());
+    /// assert_eq!(
+    ///     AzureB2C::try_new("contoso", "../../../attacker"),
+    ///     Err(AuthorityError::InvalidPolicy("../../../attacker".into()))
+    /// );
+    /// ```
+    pub fn try_new(tenant: &str, policy: &str) -> Result<Self, AuthorityError> {
+        let tenant = short_name(tenant);
+        if is_guid(&tenant) {
+            return Err(AuthorityError::InvalidHost(format!("{}.b2clogin.com", tenant)));
+        }
+        let host = format!("{}.b2clogin.com", tenant);
+
+        Self::build(&host, tenant, policy)
+    }
+
+    /// Creates a provider for a B2C tenant served from a custom domain such as
+    /// `login.contoso.com`.
+    ///
+    /// The tenant may be given as its short name, its `onmicrosoft.com` domain or its GUID.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the host, tenant or policy isn't valid; use `try_with_custom_domain` when
+    /// they come from configuration or user input.
+    pub fn with_custom_domain(host: &str, tenant: &str, policy: &str) -> Self {
+        Self::try_with_custom_domain(host, tenant, policy).expect("invalid B2C host, tenant or user flow")
+    }
+
+    /// Creates a provider for a B2C tenant served from a custom domain, checking that the
+    /// host is a bare hostname, the tenant a valid name or GUID and the policy a valid user
+    /// flow name.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AuthorityError, AzureB2C};
+    /// assert!(AzureB2C::try_with_custom_domain("login.contoso.com", "contoso", "B2C_1_susi").is_ok());
+    /// assert_eq!(
+    ///     AzureB2C::try_with_custom_domain("login.contoso.com/evil", "contoso", "B2C_1_susi"),
+    ///     Err(AuthorityError::InvalidHost("login.contoso.com/evil".into()))
+    /// );
+    /// assert!(AzureB2C::try_with_custom_domain("login.contoso.com", "contoso", "B2C_1_x?evil=1#").is_err());
+    /// ```
+    pub fn try_with_custom_domain(host: &str, tenant: &str, policy: &str) -> Result<Self, AuthorityError> {
+        Self::build(host, short_name(tenant), policy)
+    }
+
+    fn build(host: &str, tenant: String, policy: &str) -> Result<Self, AuthorityError> {
+        validate_host(host)?;
+
+        // Short names and GUIDs alike are DNS labels
+        if tenant.is_empty() {
+            return Err(AuthorityError::Tenant(AzureTenantError::Empty));
+        }
+        if let Some(c) = tenant.chars().find(|&c| !(c.is_ascii_alphanumeric() || c == '-')) {
+            return Err(AuthorityError::Tenant(AzureTenantError::InvalidCharacter(c)));
+        }
+
+        if policy.is_empty() || !policy.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(AuthorityError::InvalidPolicy(policy.to_owned()));
+        }
+        let policy = policy.to_owned();
+
+        // GUID tenant identifiers are used as-is, names are qualified with the B2C domain
+        let tenant_segment = if is_guid(&tenant) {
+            tenant.clone()
+        } else {
+            format!("{}.onmicrosoft.com", tenant)
+        };
+
+        let base = format!("https://{}/{}/{}/oauth2/v2.0", host, tenant_segment, policy);
+        let auth_uri = Url::parse( &format!("{}/authorize", base) )?;
+        let token_uri = Url::parse( &format!("{}/token", base) )?;
+
+        Ok(Self { host: host.to_owned(), tenant, policy, auth_uri, token_uri })
+    }
+
+    /// Returns a provider for another user flow in the same tenant and on the same host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy isn't a valid user flow name; use `try_with_policy` when it comes
+    /// from configuration or user input.
+    pub fn with_policy(&self, policy: &str) -> Self {
+        self.try_with_policy(policy).expect("invalid B2C user flow")
+    }
+
+    /// Returns a provider for another user flow in the same tenant and on the same host,
+    /// checking that the policy is a valid user flow name.
+    pub fn try_with_policy(&self, policy: &str) -> Result<Self, AuthorityError> {
+        Self::build(&self.host, self.tenant.clone(), policy)
+    }
+
+    /// Creates a provider bound to the tenant's resource owner password credentials (ROPC)
+    /// user flow, `B2C_1_ROPC_Auth`.
+    ///
+    /// Use `new` instead if the ROPC user flow has a different name.
+    pub fn ropc(tenant: &str) -> Self {
+        Self::new(tenant, B2C_ROPC_POLICY)
+    }
+
+    /// Requests a token with a user's username and password through an ROPC user flow.
+    ///
+    /// The application must be registered as a public client, so no client secret is sent.
+    /// `openid`, `offline_access` and the client ID are always requested so that a refresh
+    /// token is returned; additional scopes can be passed in `scope`.
+    ///
+    /// ROPC bypasses most of B2C's protections and should be reserved for automated test
+    /// accounts and migrations.
+    pub fn request_token_with_password(
+        &self,
+        http_client: &reqwest::Client,
+        client_id: &str,
+        username: &str,
+        password: &str,
+        scope: Option<&str>,
+    ) -> Result<Bearer<Refresh>, ClientError> {
+        let scope = match scope {
+            Some(scope) => format!("openid offline_access {} {}", client_id, scope),
+            None => format!("openid offline_access {}", client_id),
+        };
+
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", "password");
+        body.append_pair("response_type", "token id_token");
+        body.append_pair("scope", &scope);
+        body.append_pair("username", username);
+        body.append_pair("password", password);
+
+        let json = post_token(http_client, &self.token_uri, client_id, None, body)?;
+        let token = Bearer::from_response(&json)?;
+        Ok(token)
+    }
+
+    /// Returns the authority host, eg. `contoso.b2clogin.com` or a custom domain.
+    pub fn host(&self) -> &str { &self.host }
+
+    /// Returns the short name (or GUID) of the B2C tenant, eg. `contoso`.
+    pub fn tenant(&self) -> &str { &self.tenant }
+
+    /// Returns the user flow (policy) this provider signs users in with.
+    pub fn policy(&self) -> &str { &self.policy }
+}
+
+impl Provider for AzureB2C {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.auth_uri }
+    fn token_uri(&self) -> &Url { &self.token_uri }
+}
+
+impl AzureProvider for AzureB2C {
+    // Device authorization and userinfo aren't available in B2C user flows
+    fn device_authorization_uri(&self) -> Option<Url> { None }
+    fn end_session_uri(&self) -> Option<Url> { self.auth_uri.join("logout").ok() }
+    fn jwks_uri(&self) -> Option<Url> { self.auth_uri.join("../../discovery/v2.0/keys").ok() }
+    fn userinfo_uri(&self) -> Option<Url> { None }
+    fn discovery_uri(&self) -> Option<Url> { self.auth_uri.join("../../v2.0/.well-known/openid-configuration").ok() }
+}
@@ -0,0 +1,101 @@
+//! Interactive sign-in with the system browser.
+
+use std::io;
+use std::process::{Command, Stdio};
+
+use reqwest;
+use url::Url;
+use inth_oauth2::ClientError;
+
+use callback::Callback;
+use error::RedeemError;
+use ext::ClientExt;
+use loopback::LoopbackListener;
+use pkce::Pkce;
+use provider::AzureProvider;
+use public::PublicClient;
+
+/// Opens `url` in the user's default browser, with `open` on macOS, `xdg-open` on other Unix
+/// systems and the URL protocol handler on Windows.
+///
+/// Requires the `browser` feature.
+pub fn open_in_browser(url: &Url) -> io::Result<()> {
+    let mut command = opener(url.as_str());
+
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("failed to open browser: {}", status)))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("open");
+    command.arg(url);
+    command
+}
+
+// `start` would need its arguments quoted for cmd.exe, which mangles URLs containing `&`
+#[cfg(windows)]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("rundll32");
+    command.args(["url.dll,FileProtocolHandler", url]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(url);
+    command
+}
+
+/// Signs the user in with the authorization code flow: listens on a loopback redirect URI,
+/// opens the sign-in page in the system browser, waits for the callback, and redeems the
+/// code with PKCE through `ClientExt::redeem_code`.
+///
+/// The client's own redirect URI is ignored in favor of the listener's. Requires the
+/// `browser` feature.
+///
+/// ```rust,no_run
+/// # extern crate reqwest;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureCommon, PublicClient, RedeemError, interactive_signin};
+/// # fn main() -> Result<(), RedeemError> {
+/// let client = PublicClient::new(AzureCommon, "client-id".into(), None);
+/// let token = interactive_signin(&client, &reqwest::Client::new(), "openid offline_access User.Read")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn interactive_signin<P: AzureProvider + Clone>(
+    client: &PublicClient<P>,
+    http_client: &reqwest::Client,
+    scope: &str,
+) -> Result<P::Token, RedeemError> {
+    let listener = LoopbackListener::bind().map_err(ClientError::from)?;
+    let client = PublicClient {
+        provider: client.provider.clone(),
+        client_id: client.client_id.clone(),
+        redirect_uri: Some(listener.redirect_uri().to_owned()),
+        origin: client.origin.clone(),
+    };
+
+    let (uri, session) = client.authorization_request()
+        .scope(scope)
+        .pkce(Pkce::new())
+        .build();
+
+    open_in_browser(&uri).map_err(ClientError::from)?;
+
+    match listener.accept()? {
+        Callback::Success(response) => client.redeem_code(http_client, &response, &session),
+        Callback::Error(err) => Err(RedeemError::Client(ClientError::OAuth2(err.into()))),
+    }
+}
@@ -0,0 +1,64 @@
+//! Caching of provider metadata and keys.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// A cache of the JSON documents Azure AD publishes, such as key sets, keyed by their URI.
+///
+/// `JwksCache` caches in memory by default, so each process fetches the keys itself.
+/// Services with many instances can share one cache instead, eg. in Redis, by implementing
+/// this trait over it.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2_azure;
+/// # use std::time::Duration;
+/// # use inth_oauth2_azure::{MemoryCache, MetadataCache};
+/// # fn main() {
+/// let cache = MemoryCache::new();
+/// let uri = "https://login.microsoftonline.com/common/discovery/v2.0/keys";
+///
+/// cache.put(uri, &json!({ "keys": [] }), Duration::from_secs(60));
+/// assert_eq!(cache.get(uri), Some(json!({ "keys": [] })));
+///
+/// cache.put(uri, &json!({ "keys": [] }), Duration::from_secs(0));
+/// assert_eq!(cache.get(uri), None);
+/// # }
+/// ```
+pub trait MetadataCache: Send + Sync {
+    /// Returns the document cached for `key`, unless it has expired.
+    fn get(&self, key: &str) -> Option<Value>;
+
+    /// Caches `value` for `key` for `ttl`.
+    fn put(&self, key: &str, value: &Value, ttl: Duration);
+}
+
+/// A `MetadataCache` in the process' memory.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: RwLock<HashMap<String, (Value, Instant)>>,
+}
+
+impl MemoryCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MetadataCache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Value> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.get(key)
+            .filter(|&&(_, expires)| Instant::now() < expires)
+            .map(|(value, _)| value.clone())
+    }
+
+    fn put(&self, key: &str, value: &Value, ttl: Duration) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key.to_owned(), (value.clone(), Instant::now() + ttl));
+    }
+}
@@ -0,0 +1,306 @@
+//! Authorization callbacks.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use url::{form_urlencoded, Url};
+use inth_oauth2::client::response::ParseError;
+use inth_oauth2::error::OAuth2Error;
+
+use account::ClientInfo;
+use authorize::AuthorizationSession;
+use error::CallbackError;
+use jwt;
+
+/// The parameters Azure AD returns to the redirect URI.
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::Callback;
+/// // The body of the POST to the redirect URI, with `response_mode=form_post`
+/// let body = "code=0.AAAA&state=12345&session_state=fe1540c3";
+///
+/// match Callback::from_form(body).unwrap() {
+///     Callback::Success(response) => {
+///         assert_eq!(response.code.as_ref().map(|c| &c[..]), Some("0.AAAA"));
+///         assert_eq!(response.state, "12345");
+///     },
+///     Callback::Error(err) => panic!("{}", err),
+/// }
+///
+/// let body = "error=access_denied&error_description=The+user+cancelled&state=12345";
+/// assert!(match Callback::from_form(body).unwrap() {
+///     Callback::Error(err) => err.error == "access_denied",
+///     _ => false,
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Callback {
+    /// The user signed in.
+    Success(AuthorizationResponse),
+
+    /// The request failed or the user declined.
+    Error(AuthorizationError),
+}
+
+/// The result of a silent authorization request (`prompt=none`), see
+/// `AuthorizationRequest::silent`.
+///
+/// ```rust
+/// # use inth_oauth2_azure::{parse_callback, SilentCallback};
+/// let callback = parse_callback("?error=login_required&state=12345").unwrap();
+///
+/// match callback.into_silent() {
+///     SilentCallback::Success(response) => { /* redeem response.code */ },
+///     SilentCallback::InteractionRequired(_) => { /* fall back to an interactive request */ },
+///     SilentCallback::Error(err) => panic!("{}", err),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SilentCallback {
+    /// The user's session was still valid.
+    Success(AuthorizationResponse),
+
+    /// The user must sign in, consent or pick an account, so the request must be repeated
+    /// interactively.
+    InteractionRequired(AuthorizationError),
+
+    /// The request failed for another reason.
+    Error(AuthorizationError),
+}
+
+/// A successful authorization response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationResponse {
+    /// The authorization code, to redeem for tokens.
+    pub code: Option<String>,
+
+    /// The ID token, when the `response_type` includes `id_token`.
+    pub id_token: Option<String>,
+
+    /// The `state` sent with the request.
+    pub state: String,
+
+    /// Identifies the user's session, for front-channel logout.
+    pub session_state: Option<String>,
+
+    /// The user's home account, when requested with `AuthorizationRequest::client_info`.
+    pub client_info: Option<ClientInfo>,
+}
+
+/// An authorization error response.
+///
+/// See [RFC 6749, section 4.1.2.1](https://tools.ietf.org/html/rfc6749#section-4.1.2.1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationError {
+    /// The error code, eg. `access_denied` or `interaction_required`.
+    pub error: String,
+
+    /// Human-readable text providing additional information about the error.
+    pub error_description: Option<String>,
+
+    /// The `AADSTS` error codes, eg. `50011` for a redirect URI mismatch.
+    pub error_codes: Vec<u64>,
+
+    /// The `state` sent with the request.
+    pub state: Option<String>,
+}
+
+impl Callback {
+    /// Parses the urlencoded body POSTed to the redirect URI with `response_mode=form_post`.
+    pub fn from_form(body: &str) -> Result<Self, ParseError> {
+        let mut params = parse_params(body);
+
+        if let Some(err) = AuthorizationError::from_params(&mut params) {
+            return Ok(Callback::Error(err));
+        }
+
+        let code = params.remove("code");
+        let id_token = params.remove("id_token");
+        if code.is_none() && id_token.is_none() {
+            return Err(ParseError::ExpectedFieldType("code", "string"));
+        }
+
+        Ok(Callback::Success(AuthorizationResponse {
+            code,
+            id_token,
+            state: params.remove("state").ok_or(ParseError::ExpectedFieldType("state", "string"))?,
+            session_state: params.remove("session_state"),
+            client_info: match params.remove("client_info") {
+                Some(client_info) => Some(ClientInfo::decode(&client_info)?),
+                None => None,
+            },
+        }))
+    }
+
+    /// Distinguishes the errors of a silent request that only mean the user must interact.
+    pub fn into_silent(self) -> SilentCallback {
+        match self {
+            Callback::Success(response) => SilentCallback::Success(response),
+            Callback::Error(err) if err.requires_interaction() => SilentCallback::InteractionRequired(err),
+            Callback::Error(err) => SilentCallback::Error(err),
+        }
+    }
+
+    /// Parses the URI fragment (without the `#`) returned with `response_mode=fragment`, the
+    /// default for the hybrid flow.
+    pub fn from_fragment(fragment: &str) -> Result<Self, ParseError> {
+        Self::from_form(fragment)
+    }
+}
+
+/// Parses the request to the redirect URI: either its full URL, or just its query string or
+/// fragment.
+///
+/// ```rust
+/// # use inth_oauth2_azure::{parse_callback, Callback};
+/// let callback = parse_callback("https://localhost/callback?code=0.AAAA&state=12345").unwrap();
+/// assert!(match callback {
+///     Callback::Success(response) => response.state == "12345",
+///     _ => false,
+/// });
+///
+/// let callback = parse_callback(
+///     "?error=invalid_request&error_description=AADSTS50011%3A+Reply+URL+mismatch&error_codes=%5B50011%5D"
+/// ).unwrap();
+/// match callback {
+///     Callback::Error(err) => {
+///         assert_eq!(err.error, "invalid_request");
+///         assert_eq!(err.error_codes, vec![50011]);
+///     },
+///     _ => panic!("expected an error"),
+/// }
+/// ```
+pub fn parse_callback(url_or_query: &str) -> Result<Callback, ParseError> {
+    if let Ok(url) = Url::parse(url_or_query) {
+        return Callback::from_form(url.query().or_else(|| url.fragment()).unwrap_or_default());
+    }
+
+    Callback::from_form(url_or_query.trim_start_matches(['?', '#']))
+}
+
+impl AuthorizationError {
+    /// Returns true if the request failed because it couldn't interact with the user, as with
+    /// `prompt=none`: `login_required`, `interaction_required`, `consent_required` or
+    /// `account_selection_required`.
+    pub fn requires_interaction(&self) -> bool {
+        matches!(
+            self.error.as_str(),
+            "login_required" | "interaction_required" | "consent_required" | "account_selection_required"
+        )
+    }
+
+    /// Takes the error parameters out of a callback, if it has any.
+    pub(crate) fn from_params(params: &mut HashMap<String, String>) -> Option<Self> {
+        let error = params.remove("error")?;
+
+        // Returned as a JSON array, or sometimes comma-separated
+        let error_codes = params.remove("error_codes")
+            .map(|codes| {
+                codes.trim_matches(['[', ']'])
+                    .split(',')
+                    .filter_map(|code| code.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(AuthorizationError {
+            error,
+            error_description: params.remove("error_description"),
+            error_codes,
+            state: params.remove("state"),
+        })
+    }
+}
+
+/// Parses urlencoded parameters. Repeated parameters keep their last value.
+pub(crate) fn parse_params(s: &str) -> HashMap<String, String> {
+    form_urlencoded::parse(s.as_bytes()).into_owned().collect()
+}
+
+impl AuthorizationResponse {
+    /// Checks the response of a hybrid flow (`response_type=code id_token`) against the
+    /// session of its request: the `state`, and the ID token's `nonce` and `c_hash`, which
+    /// binds the authorization code to the ID token.
+    ///
+    /// This doesn't verify the ID token's signature, which must still be validated before
+    /// trusting its claims.
+    ///
+    /// ```rust
+    /// # extern crate base64;
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AuthorizationResponse, AuthorizationSession, CallbackError};
+    /// # fn main() {
+    /// # let encode = |v: serde_json::Value| base64::encode_config(&v.to_string(), base64::URL_SAFE_NO_PAD);
+    /// // The example from OpenID Connect Core 1.0, appendix A.4
+    /// let id_token = format!(
+    ///     "{}.{}.c2ln",
+    ///     encode(json!({ "alg": "RS256" })),
+    ///     encode(json!({ "nonce": "n-0S6_WzA2Mj", "c_hash": "LDktKdoQak3Pk0cnXxCltA" })),
+    /// );
+    /// let session = AuthorizationSession {
+    ///     state: "af0ifjsldkj".into(),
+    ///     nonce: Some("n-0S6_WzA2Mj".into()),
+    ///     code_verifier: None,
+    ///     max_age: None,
+    /// };
+    ///
+    /// let mut response = AuthorizationResponse {
+    ///     code: Some("Qcb0Orv1zh30vL1MPRsbm-diHiMwcLyZvn1arpZv-Jxf_11jnpEX3Tgfvk".into()),
+    ///     id_token: Some(id_token),
+    ///     state: "af0ifjsldkj".into(),
+    ///     session_state: None,
+    ///     client_info: None,
+    /// };
+    /// assert_eq!(response.verify_hybrid(&session), Ok(()));
+    ///
+    /// response.code = Some("another-code".into());
+    /// assert_eq!(response.verify_hybrid(&session), Err(CallbackError::CodeHash));
+    /// # }
+    /// ```
+    pub fn verify_hybrid(&self, session: &AuthorizationSession) -> Result<(), CallbackError> {
+        if self.state != session.state {
+            return Err(CallbackError::State);
+        }
+
+        let code = self.code.as_ref().ok_or(CallbackError::Missing("code"))?;
+        let id_token = self.id_token.as_ref().ok_or(CallbackError::Missing("id_token"))?;
+        let id_token = jwt::decode(id_token).ok_or(CallbackError::IdToken)?;
+
+        if id_token.claims["nonce"].as_str() != session.nonce.as_ref().map(|n| &n[..]) {
+            return Err(CallbackError::Nonce);
+        }
+
+        let alg = id_token.header["alg"].as_str().ok_or(CallbackError::IdToken)?;
+        let c_hash = id_token.claims["c_hash"].as_str().ok_or(CallbackError::CodeHash)?;
+        if jwt::left_hash(alg, code).as_ref().map(|h| &h[..]) != Some(c_hash) {
+            return Err(CallbackError::CodeHash);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for AuthorizationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.error)?;
+        if let Some(ref description) = self.error_description {
+            write!(f, ": {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for AuthorizationError {}
+
+impl From<AuthorizationError> for OAuth2Error {
+    fn from(err: AuthorizationError) -> Self {
+        OAuth2Error {
+            code: err.error.as_str().into(),
+            description: err.error_description,
+            uri: None,
+        }
+    }
+}
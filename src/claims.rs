@@ -0,0 +1,158 @@
+//! Claims requests.
+
+use std::fmt;
+
+use base64;
+use serde_json::{self, Map, Value};
+
+/// Builds the OpenID Connect `claims` parameter, which requests individual claims in the ID
+/// token or access token.
+///
+/// Azure AD uses it for optional claims, and to declare client capabilities such as
+/// continuous access evaluation (`xms_cc`).
+///
+/// See [OpenID Connect Core 1.0, section 5.5](https://openid.net/specs/openid-connect-core-1_0.html#ClaimsParameter).
+///
+/// ```rust
+/// # use inth_oauth2_azure::{Claim, ClaimsRequest};
+/// let claims = ClaimsRequest::new()
+///     .id_token("auth_time", Claim::essential())
+///     .id_token("acrs", Claim::essential().values(&["c1"]))
+///     .client_capabilities(&["cp1"]);
+///
+/// assert_eq!(
+///     claims.to_string(),
+///     r#"{"access_token":{"xms_cc":{"values":["cp1"]}},"id_token":{"acrs":{"essential":true,"values":["c1"]},"auth_time":{"essential":true}}}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClaimsRequest {
+    id_token: Map<String, Value>,
+    access_token: Map<String, Value>,
+}
+
+/// How a claim is requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Claim {
+    essential: bool,
+    value: Option<String>,
+    values: Vec<String>,
+}
+
+impl ClaimsRequest {
+    /// Creates an empty request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a claim in the ID token.
+    pub fn id_token(mut self, name: &str, claim: Claim) -> Self {
+        self.id_token.insert(name.to_owned(), claim.to_json());
+        self
+    }
+
+    /// Requests a claim in the access token.
+    pub fn access_token(mut self, name: &str, claim: Claim) -> Self {
+        self.access_token.insert(name.to_owned(), claim.to_json());
+        self
+    }
+
+    /// Declares the client's capabilities in the access token's `xms_cc` claim, eg. `cp1`
+    /// for clients that can handle claims challenges from continuous access evaluation.
+    pub fn client_capabilities<S: AsRef<str>>(self, capabilities: &[S]) -> Self {
+        self.access_token("xms_cc", Claim::voluntary().values(capabilities))
+    }
+
+    /// Returns the request as JSON.
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+
+        if !self.id_token.is_empty() {
+            json.insert("id_token".to_owned(), Value::Object(self.id_token.clone()));
+        }
+        if !self.access_token.is_empty() {
+            json.insert("access_token".to_owned(), Value::Object(self.access_token.clone()));
+        }
+
+        Value::Object(json)
+    }
+}
+
+/// Renders the JSON value of the `claims` parameter.
+impl fmt::Display for ClaimsRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+impl Claim {
+    /// A claim the client would like, but can do without.
+    pub fn voluntary() -> Self {
+        Self::default()
+    }
+
+    /// A claim the client needs.
+    pub fn essential() -> Self {
+        Claim { essential: true, ..Self::default() }
+    }
+
+    /// Requests the claim with a specific value.
+    pub fn value(mut self, value: &str) -> Self {
+        self.value = Some(value.to_owned());
+        self
+    }
+
+    /// Requests the claim with one of several values, in order of preference.
+    pub fn values<S: AsRef<str>>(mut self, values: &[S]) -> Self {
+        self.values = values.iter().map(|v| v.as_ref().to_owned()).collect();
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut json = Map::new();
+
+        if self.essential {
+            json.insert("essential".to_owned(), Value::Bool(true));
+        }
+        if let Some(ref value) = self.value {
+            json.insert("value".to_owned(), Value::String(value.clone()));
+        }
+        if !self.values.is_empty() {
+            json.insert("values".to_owned(), self.values.iter().cloned().map(Value::String).collect());
+        }
+
+        if json.is_empty() { Value::Null } else { Value::Object(json) }
+    }
+}
+
+/// Returns the claims a resource API demands in the `WWW-Authenticate` header of a 401
+/// response, eg. after continuous access evaluation revoked a session or Conditional Access
+/// requires MFA. Pass them to `AuthorizationRequest::claims` to acquire a token that
+/// satisfies them.
+///
+/// ```rust
+/// # use inth_oauth2_azure::claims_challenge;
+/// let header = r#"Bearer realm="", authorization_uri="https://login.microsoftonline.com/common/oauth2/authorize", error="insufficient_claims", claims="eyJhY2Nlc3NfdG9rZW4iOnsibmJmIjp7ImVzc2VudGlhbCI6dHJ1ZSwgInZhbHVlIjoiMTYwNDEwNjY1MSJ9fX0=""#;
+///
+/// assert_eq!(
+///     claims_challenge(header).unwrap(),
+///     r#"{"access_token":{"nbf":{"essential":true, "value":"1604106651"}}}"#
+/// );
+/// assert_eq!(claims_challenge(r#"Bearer error="invalid_token""#), None);
+/// ```
+pub fn claims_challenge(www_authenticate: &str) -> Option<String> {
+    let start = www_authenticate.find("claims=\"")? + "claims=\"".len();
+    let value = &www_authenticate[start..];
+    let value = &value[..value.find('"')?];
+
+    let decoded = base64::decode(value)
+        .or_else(|_| base64::decode_config(value, base64::URL_SAFE_NO_PAD))
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_else(|| value.to_owned());
+
+    match serde_json::from_str::<Value>(&decoded) {
+        Ok(ref json) if json.is_object() => Some(decoded),
+        _ => None,
+    }
+}
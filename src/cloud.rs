@@ -0,0 +1,322 @@
+//! Azure cloud instances.
+
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use url::{self, Url};
+
+use error::AuthorityError;
+
+/// The environment variable the Azure SDKs read the authority host from.
+pub const AZURE_AUTHORITY_HOST: &str = "AZURE_AUTHORITY_HOST";
+
+// Hosts Azure AD answers on, paired with the preferred host for each
+const AUTHORITY_ALIASES: &[(&str, &str)] = &[
+    ("login.microsoftonline.com", "login.microsoftonline.com"),
+    ("login.windows.net", "login.microsoftonline.com"),
+    ("login.microsoft.com", "login.microsoftonline.com"),
+    ("sts.windows.net", "login.microsoftonline.com"),
+    ("login.microsoftonline.us", "login.microsoftonline.us"),
+    ("login.usgovcloudapi.net", "login.microsoftonline.us"),
+    ("login.partner.microsoftonline.cn", "login.partner.microsoftonline.cn"),
+    ("login.chinacloudapi.cn", "login.partner.microsoftonline.cn"),
+    ("sts.chinacloudapi.cn", "login.partner.microsoftonline.cn"),
+    ("login.microsoftonline.de", "login.microsoftonline.de"),
+];
+
+/// Returns the preferred host for an Azure AD authority host or one of its aliases, eg.
+/// `login.microsoftonline.com` for `login.windows.net`.
+///
+/// Returns `None` for hosts that aren't Azure AD authorities.
+pub fn canonical_host(host: &str) -> Option<&'static str> {
+    AUTHORITY_ALIASES.iter()
+        .find(|&&(alias, _)| alias.eq_ignore_ascii_case(host))
+        .map(|&(_, preferred)| preferred)
+}
+
+/// Rewrites an authority URL to use its preferred host, leaving the path intact.
+///
+/// Trailing slashes are removed, and unknown hosts are returned unchanged.
+///
+/// ```rust
+/// # use inth_oauth2_azure::canonicalize_authority;
+/// assert_eq!(
+///     canonicalize_authority("https://sts.windows.net/contoso.onmicrosoft.com/").unwrap().as_str(),
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com"
+/// );
+/// ```
+pub fn canonicalize_authority(authority: &str) -> Result<Url, url::ParseError> {
+    let mut url = Url::parse(authority)?;
+
+    let preferred = url.host_str().and_then(canonical_host);
+    if let Some(host) = preferred {
+        url.set_host(Some(host))?;
+    }
+
+    let path = url.path().trim_end_matches('/').to_owned();
+    url.set_path(&path);
+
+    Ok(url)
+}
+
+/// Returns true if two authority URLs refer to the same authority once aliases are
+/// resolved. Tenant path segments are compared case-insensitively.
+///
+/// ```rust
+/// # use inth_oauth2_azure::same_authority;
+/// assert!(same_authority(
+///     "https://login.windows.net/Contoso.onmicrosoft.com",
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/"
+/// ));
+/// ```
+pub fn same_authority(a: &str, b: &str) -> bool {
+    match (canonicalize_authority(a), canonicalize_authority(b)) {
+        (Ok(a), Ok(b)) => {
+            a.scheme() == b.scheme()
+                && a.host_str() == b.host_str()
+                && a.port() == b.port()
+                && a.path().eq_ignore_ascii_case(b.path())
+        },
+        _ => false,
+    }
+}
+
+// Checks that `host` is a hostname, optionally with a port, and nothing else that would
+// change the URIs built from it
+pub(crate) fn validate_host(host: &str) -> Result<(), AuthorityError> {
+    let url = Url::parse( &format!("https://{}/", host) )?;
+
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some()
+        || !url.username().is_empty() || url.password().is_some()
+    {
+        return Err(AuthorityError::InvalidHost(host.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// The Azure cloud instance an authority lives in.
+///
+/// Each cloud has its own authority host, so a provider built for one cloud can't sign in
+/// users from another.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::{AzureCloud, AzureCommon, AzureTenant};
+/// let provider = AzureCommon::in_cloud(AzureCloud::UsGov);
+/// assert_eq!(
+///     provider.auth_uri().as_str(),
+///     "https://login.microsoftonline.us/common/oauth2/v2.0/authorize"
+/// );
+///
+/// let provider = AzureTenant::new_in_cloud("contoso.onmicrosoft.com", AzureCloud::China);
+/// assert_eq!(
+///     provider.token_uri().as_str(),
+///     "https://login.partner.microsoftonline.cn/contoso.onmicrosoft.com/oauth2/v2.0/token"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AzureCloud {
+    /// The global Azure public cloud, `login.microsoftonline.com`.
+    #[default]
+    Public,
+
+    /// Azure US Government (GCC High), `login.microsoftonline.us`.
+    UsGov,
+
+    /// Azure US Government DoD. Shares its authority host with `UsGov`.
+    UsGovDod,
+
+    /// Azure China, operated by 21Vianet, `login.partner.microsoftonline.cn`.
+    China,
+
+    /// Legacy Microsoft Cloud Deutschland, `login.microsoftonline.de`.
+    Germany,
+
+    /// Any other authority, eg. a private or emulated cloud.
+    Custom(Url),
+}
+
+impl AzureCloud {
+    /// Returns the cloud an authority host belongs to, eg. `https://login.microsoftonline.us`.
+    ///
+    /// The scheme may be omitted and aliases such as `login.windows.net` are recognised.
+    /// Hosts that aren't one of the well-known clouds are returned as `Custom`. `UsGov` and
+    /// `UsGovDod` can't be told apart by host, so `UsGov` is returned.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::AzureCloud;
+    /// assert_eq!(AzureCloud::from_authority_host("login.microsoftonline.us"), Ok(AzureCloud::UsGov));
+    /// assert_eq!(AzureCloud::from_authority_host("https://login.microsoftonline.com/"), Ok(AzureCloud::Public));
+    /// ```
+    pub fn from_authority_host(host: &str) -> Result<Self, url::ParseError> {
+        let url = if host.contains("://") {
+            Url::parse(host)?
+        } else {
+            Url::parse( &format!("https://{}", host) )?
+        };
+
+        let cloud = match url.host_str().and_then(canonical_host) {
+            Some("login.microsoftonline.com") => AzureCloud::Public,
+            Some("login.microsoftonline.us") => AzureCloud::UsGov,
+            Some("login.partner.microsoftonline.cn") => AzureCloud::China,
+            Some("login.microsoftonline.de") => AzureCloud::Germany,
+            _ => AzureCloud::Custom(url),
+        };
+
+        Ok(cloud)
+    }
+
+    /// Returns the cloud named by the `AZURE_AUTHORITY_HOST` environment variable, or
+    /// `Public` if it isn't set.
+    pub fn from_env() -> Result<Self, url::ParseError> {
+        match env::var(AZURE_AUTHORITY_HOST) {
+            Ok(ref host) if !host.is_empty() => Self::from_authority_host(host),
+            _ => Ok(AzureCloud::Public),
+        }
+    }
+
+    /// Returns the authority host URL for this cloud.
+    pub fn authority_host(&self) -> Url {
+        match *self {
+            AzureCloud::Public => Url::parse("https://login.microsoftonline.com/").unwrap(),
+            AzureCloud::UsGov | AzureCloud::UsGovDod => Url::parse("https://login.microsoftonline.us/").unwrap(),
+            AzureCloud::China => Url::parse("https://login.partner.microsoftonline.cn/").unwrap(),
+            AzureCloud::Germany => Url::parse("https://login.microsoftonline.de/").unwrap(),
+            AzureCloud::Custom(ref url) => url.clone(),
+        }
+    }
+
+    /// Returns the Microsoft Graph endpoint for this cloud, or `None` for custom clouds.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::AzureCloud;
+    /// assert_eq!(AzureCloud::UsGovDod.graph_endpoint().unwrap().as_str(), "https://dod-graph.microsoft.us/");
+    /// assert_eq!(AzureCloud::China.graph_default_scope().unwrap(), "https://microsoftgraph.chinacloudapi.cn/.default");
+    /// ```
+    pub fn graph_endpoint(&self) -> Option<Url> {
+        let endpoint = match *self {
+            AzureCloud::Public => "https://graph.microsoft.com/",
+            AzureCloud::UsGov => "https://graph.microsoft.us/",
+            AzureCloud::UsGovDod => "https://dod-graph.microsoft.us/",
+            AzureCloud::China => "https://microsoftgraph.chinacloudapi.cn/",
+            AzureCloud::Germany => "https://graph.microsoft.de/",
+            AzureCloud::Custom(_) => return None,
+        };
+
+        Some(Url::parse(endpoint).unwrap())
+    }
+
+    /// Returns the `.default` scope for Microsoft Graph in this cloud, which requests every
+    /// Graph permission the application has been granted.
+    pub fn graph_default_scope(&self) -> Option<String> {
+        self.graph_endpoint().map(|url| format!("{}.default", url))
+    }
+
+    pub(crate) fn endpoint(&self, tenant: &str, path: &str) -> Url {
+        join_endpoint(&self.authority_host(), tenant, path)
+    }
+
+    pub(crate) fn userinfo_endpoint(&self) -> Option<Url> {
+        self.graph_endpoint().map(|graph| graph.join("oidc/userinfo").unwrap())
+    }
+
+    pub(crate) fn issuer(&self, tenant: &str) -> String {
+        let host = self.authority_host();
+
+        format!("{}/{}/v2.0", host.as_str().trim_end_matches('/'), tenant)
+    }
+
+    pub(crate) fn issuer_v1(&self, tenant: &str) -> String {
+        // v1.0 tokens from the public and China clouds are issued by their legacy STS aliases
+        match *self {
+            AzureCloud::Public => format!("https://sts.windows.net/{}/", tenant),
+            AzureCloud::China => format!("https://sts.chinacloudapi.cn/{}/", tenant),
+            _ => format!("{}{}/", self.authority_host(), tenant),
+        }
+    }
+
+    pub(crate) fn regional_endpoint(&self, region: &str, tenant: &str, path: &str) -> Url {
+        // The public cloud's regional service lives under login.microsoft.com rather than
+        // the usual authority host; every other cloud, custom ones included, prefixes its
+        // own host and keeps its scheme, port and path
+        let mut host = match *self {
+            AzureCloud::Public => Url::parse("https://login.microsoft.com/").unwrap(),
+            _ => self.authority_host(),
+        };
+        let regional = format!("{}.{}", region.to_lowercase(), host.host_str().unwrap_or_default());
+
+        // IP addresses can't be prefixed, so those authorities have no regional service
+        if host.set_host(Some(&regional)).is_err() {
+            return self.endpoint(tenant, path);
+        }
+
+        join_endpoint(&host, tenant, path)
+    }
+}
+
+fn join_endpoint(host: &Url, tenant: &str, path: &str) -> Url {
+    let base = host.as_str().trim_end_matches('/');
+
+    Url::parse( &format!("{}/{}/{}", base, tenant, path) ).unwrap()
+}
+
+impl fmt::Display for AzureCloud {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            AzureCloud::Public => write!(f, "public"),
+            AzureCloud::UsGov => write!(f, "usgov"),
+            AzureCloud::UsGovDod => write!(f, "usgovdod"),
+            AzureCloud::China => write!(f, "china"),
+            AzureCloud::Germany => write!(f, "germany"),
+            AzureCloud::Custom(ref url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Parses a cloud name as written by `Display`, or the URL of a custom authority host.
+///
+/// ```rust
+/// # use inth_oauth2_azure::AzureCloud;
+/// assert_eq!("UsGov".parse(), Ok(AzureCloud::UsGov));
+/// assert!("http://localhost:8080/".parse::<AzureCloud>().is_ok());
+/// assert!("usgob".parse::<AzureCloud>().is_err());
+/// ```
+impl FromStr for AzureCloud {
+    type Err = AuthorityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "public" => Ok(AzureCloud::Public),
+            "usgov" => Ok(AzureCloud::UsGov),
+            "usgovdod" => Ok(AzureCloud::UsGovDod),
+            "china" => Ok(AzureCloud::China),
+            "germany" => Ok(AzureCloud::Germany),
+            _ if s.contains("://") => Ok(AzureCloud::from_authority_host(s)?),
+            _ => Err(AuthorityError::UnrecognizedHost(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::AzureCloud;
+
+    impl Serialize for AzureCloud {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for AzureCloud {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(de::Error::custom)
+        }
+    }
+}
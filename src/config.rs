@@ -0,0 +1,205 @@
+//! Client configuration.
+
+use std::env;
+
+use inth_oauth2::Client;
+
+#[cfg(feature = "serde")]
+use serde_json;
+
+use authority::AzureAuthority;
+use cloud::{AzureCloud, AZURE_AUTHORITY_HOST};
+use error::{AuthorityError, ConfigError};
+use public::PublicClient;
+use redirect::validate_redirect_uri;
+
+/// The environment variable holding the tenant identifier, as used by the Azure SDKs.
+pub const AZURE_TENANT_ID: &str = "AZURE_TENANT_ID";
+
+/// The environment variable holding the application (client) ID.
+pub const AZURE_CLIENT_ID: &str = "AZURE_CLIENT_ID";
+
+/// The environment variable holding the client secret.
+pub const AZURE_CLIENT_SECRET: &str = "AZURE_CLIENT_SECRET";
+
+/// The environment variable holding the redirect URI.
+pub const AZURE_REDIRECT_URI: &str = "AZURE_REDIRECT_URI";
+
+/// The environment variable holding the space-delimited scopes to request.
+pub const AZURE_SCOPES: &str = "AZURE_SCOPES";
+
+/// Everything needed to build a `Client` for an Azure AD application.
+///
+/// `authority` is an authority host or cloud name (`login.microsoftonline.us`, `usgov`) and
+/// `tenant` the tenant identifier, defaulting to the public cloud and `common`. For B2C,
+/// External ID or ADFS, `authority` can instead be a complete authority URL, in which case
+/// `tenant` must be left unset.
+///
+/// With the `serde` feature the configuration can be read from any serde format, such as a
+/// TOML or JSON file:
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureConfig;
+/// # #[cfg(feature = "serde")]
+/// # fn main() {
+/// let config = AzureConfig::from_json(r#"{
+///     "authority": "usgov",
+///     "tenant": "contoso.onmicrosoft.us",
+///     "client_id": "client-id",
+///     "client_secret": "client-secret",
+///     "redirect_uri": "https://localhost/callback",
+///     "scopes": ["openid", "offline_access"]
+/// }"#).unwrap();
+///
+/// let client = config.client().unwrap();
+/// assert_eq!(
+///     client.provider.token_uri().as_str(),
+///     "https://login.microsoftonline.us/contoso.onmicrosoft.us/oauth2/v2.0/token"
+/// );
+/// assert_eq!(config.scope().unwrap(), "openid offline_access");
+/// # }
+/// # #[cfg(not(feature = "serde"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AzureConfig {
+    /// The authority host, cloud name or authority URL.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub authority: Option<String>,
+
+    /// The tenant identifier, or `common`, `organizations` or `consumers`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub tenant: Option<String>,
+
+    /// The application (client) ID.
+    pub client_id: String,
+
+    /// The client secret.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub client_secret: Option<String>,
+
+    /// The redirect URI registered for the application.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub redirect_uri: Option<String>,
+
+    /// The scopes to request.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub scopes: Vec<String>
+}
+
+impl AzureConfig {
+    /// Reads the configuration from the environment variables used by the Azure SDKs:
+    /// `AZURE_AUTHORITY_HOST`, `AZURE_TENANT_ID`, `AZURE_CLIENT_ID`, `AZURE_CLIENT_SECRET`,
+    /// plus `AZURE_REDIRECT_URI` and `AZURE_SCOPES`.
+    ///
+    /// Only `AZURE_CLIENT_ID` is required; empty variables are treated as unset.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let client_id = var(AZURE_CLIENT_ID).ok_or(ConfigError::Missing(AZURE_CLIENT_ID))?;
+        let scopes = var(AZURE_SCOPES)
+            .map(|s| s.split_whitespace().map(str::to_owned).collect())
+            .unwrap_or_default();
+
+        let config = AzureConfig {
+            authority: var(AZURE_AUTHORITY_HOST),
+            tenant: var(AZURE_TENANT_ID),
+            client_id,
+            client_secret: var(AZURE_CLIENT_SECRET),
+            redirect_uri: var(AZURE_REDIRECT_URI),
+            scopes,
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses the configuration from JSON.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        let config: Self = serde_json::from_str(json)?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the authority, tenant and redirect URI are valid. Configuration loaded
+    /// with another serde format should be validated before use.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.provider()?;
+
+        if let Some(ref redirect_uri) = self.redirect_uri {
+            validate_redirect_uri(redirect_uri)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the provider described by `authority` and `tenant`.
+    pub fn provider(&self) -> Result<AzureAuthority, ConfigError> {
+        let authority = match self.authority {
+            Some(ref authority) => authority,
+            None => return Ok(self.tenant.as_ref().map(|t| &t[..]).unwrap_or("common").parse()?),
+        };
+
+        // A complete authority URL already names its tenant
+        if has_path(authority) {
+            if self.tenant.is_some() {
+                return Err(ConfigError::Conflict);
+            }
+            return Ok(AzureAuthority::from_authority_url(authority)?);
+        }
+
+        let cloud = match authority.parse() {
+            Ok(cloud) => cloud,
+            Err(_) => AzureCloud::from_authority_host(authority).map_err(AuthorityError::from)?,
+        };
+        let tenant = self.tenant.as_ref().map(|t| &t[..]).unwrap_or("common").parse()?;
+
+        Ok(AzureAuthority::in_cloud(tenant, cloud))
+    }
+
+    /// Returns the scopes as a space-delimited string, as expected by `Client::auth_uri`.
+    pub fn scope(&self) -> Option<String> {
+        if self.scopes.is_empty() {
+            None
+        } else {
+            Some(self.scopes.join(" "))
+        }
+    }
+
+    /// Builds a confidential `Client` from the configuration, failing with
+    /// `ConfigError::MissingSecret` if there's no client secret. Public clients should use
+    /// `public_client` instead.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureConfig, ConfigError};
+    /// let config = AzureConfig { client_id: "client-id".into(), ..AzureConfig::default() };
+    ///
+    /// assert!(match config.client() { Err(ConfigError::MissingSecret) => true, _ => false });
+    /// assert!(config.public_client().is_ok());
+    /// ```
+    pub fn client(&self) -> Result<Client<AzureAuthority>, ConfigError> {
+        let client_secret = self.client_secret.clone().filter(|s| !s.is_empty()).ok_or(ConfigError::MissingSecret)?;
+
+        Ok(Client::new(self.provider()?, self.client_id.clone(), client_secret, self.redirect_uri.clone()))
+    }
+
+    /// Builds a `PublicClient` from the configuration, ignoring any client secret.
+    pub fn public_client(&self) -> Result<PublicClient<AzureAuthority>, ConfigError> {
+        Ok(PublicClient::new(self.provider()?, self.client_id.clone(), self.redirect_uri.clone()))
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn has_path(authority: &str) -> bool {
+    match authority.find("://") {
+        Some(i) => authority[i + 3..].trim_end_matches('/').contains('/'),
+        None => false,
+    }
+}
@@ -0,0 +1,150 @@
+//! Admin consent.
+
+use url::Url;
+use inth_oauth2::client::response::ParseError;
+
+use callback::{parse_params, AuthorizationError};
+use provider::AzureProvider;
+use random::{random_token, DEFAULT_ENTROPY};
+
+/// Builds the URI to send a tenant administrator to, to grant a multi-tenant application's
+/// permissions for their whole organization.
+///
+/// See [Admin consent on the Microsoft identity platform](https://learn.microsoft.com/entra/identity-platform/v2-admin-consent).
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AdminConsentRequest, AzureOrganization, AzureConsumer};
+/// let (uri, state) = AdminConsentRequest::new(&AzureOrganization, "client-id")
+///     .unwrap()
+///     .redirect_uri("https://localhost/consent")
+///     .build();
+///
+/// assert_eq!(uri.path(), "/organizations/v2.0/adminconsent");
+/// assert!(uri.query().unwrap().contains(&format!("state={}", state)));
+///
+/// assert!(AdminConsentRequest::new(&AzureConsumer, "client-id").is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminConsentRequest {
+    admin_consent_uri: Url,
+    client_id: String,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+}
+
+impl AdminConsentRequest {
+    /// Starts a request to the provider's admin consent endpoint, or returns `None` if it
+    /// has none.
+    pub fn new<P: AzureProvider>(provider: &P, client_id: &str) -> Option<Self> {
+        Some(AdminConsentRequest {
+            admin_consent_uri: provider.admin_consent_uri()?,
+            client_id: client_id.to_owned(),
+            redirect_uri: None,
+            scope: None,
+            state: None,
+        })
+    }
+
+    /// Sets the redirect URI, exactly as registered for the application.
+    pub fn redirect_uri(mut self, redirect_uri: &str) -> Self {
+        self.redirect_uri = Some(redirect_uri.to_owned());
+        self
+    }
+
+    /// Sets the space-delimited scopes. Defaults to the permissions configured in the app
+    /// registration (`{resource}/.default`).
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_owned());
+        self
+    }
+
+    /// Sets the `state` instead of generating one.
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_owned());
+        self
+    }
+
+    /// Returns the admin consent URI and the `state` the callback must echo.
+    pub fn build(self) -> (Url, String) {
+        let state = self.state.unwrap_or_else(|| random_token(DEFAULT_ENTROPY));
+        let mut uri = self.admin_consent_uri;
+
+        {
+            let mut query = uri.query_pairs_mut();
+
+            query.append_pair("client_id", &self.client_id);
+
+            if let Some(ref redirect_uri) = self.redirect_uri {
+                query.append_pair("redirect_uri", redirect_uri);
+            }
+            if let Some(ref scope) = self.scope {
+                query.append_pair("scope", scope);
+            }
+            query.append_pair("state", &state);
+        }
+
+        (uri, state)
+    }
+}
+
+/// The parameters the admin consent endpoint returns to the redirect URI.
+///
+/// ```rust
+/// # use inth_oauth2_azure::AdminConsentCallback;
+/// let query = "admin_consent=True&tenant=fa15d692-e9c7-4460-a743-29f29522229&state=12345";
+///
+/// match AdminConsentCallback::from_query(query).unwrap() {
+///     AdminConsentCallback::Granted(consent) => {
+///         assert_eq!(consent.tenant, "fa15d692-e9c7-4460-a743-29f29522229");
+///         assert_eq!(consent.state.unwrap(), "12345");
+///     },
+///     AdminConsentCallback::Error(err) => panic!("{}", err),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminConsentCallback {
+    /// The administrator granted the permissions.
+    Granted(AdminConsent),
+
+    /// The administrator declined, or the request failed.
+    Error(AuthorizationError),
+}
+
+/// A successful admin consent response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminConsent {
+    /// The ID of the administrator's tenant, which the application can now request
+    /// app-only tokens from.
+    pub tenant: String,
+
+    /// The scopes that were granted.
+    pub scope: Option<String>,
+
+    /// The `state` sent with the request.
+    pub state: Option<String>,
+}
+
+impl AdminConsentCallback {
+    /// Parses the query string (without the `?`) of the request to the redirect URI.
+    pub fn from_query(query: &str) -> Result<Self, ParseError> {
+        let mut params = parse_params(query);
+
+        if let Some(err) = AuthorizationError::from_params(&mut params) {
+            return Ok(AdminConsentCallback::Error(err));
+        }
+
+        match params.remove("admin_consent") {
+            Some(ref consent) if consent.eq_ignore_ascii_case("true") => {},
+            Some(_) => return Err(ParseError::ExpectedFieldValue("admin_consent", "True")),
+            None => return Err(ParseError::ExpectedFieldType("admin_consent", "string")),
+        }
+
+        Ok(AdminConsentCallback::Granted(AdminConsent {
+            tenant: params.remove("tenant").ok_or(ParseError::ExpectedFieldType("tenant", "string"))?,
+            scope: params.remove("scope"),
+            state: params.remove("state"),
+        }))
+    }
+}
@@ -0,0 +1,61 @@
+//! Custom authorities.
+
+use url::{self, Url};
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use provider::AzureProvider;
+
+/// A provider with arbitrary authorize and token URIs but the same token semantics as
+/// the Azure AD providers.
+///
+/// This is intended for integration tests against a mock identity provider or emulator,
+/// where none of the Microsoft hosts are reachable.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::CustomAzureProvider;
+/// let provider = CustomAzureProvider::new(
+///     "http://localhost:8080/authorize",
+///     "http://localhost:8080/token"
+/// ).unwrap();
+///
+/// assert_eq!(provider.token_uri().as_str(), "http://localhost:8080/token");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CustomAzureProvider {
+    auth_uri: Url,
+    token_uri: Url
+}
+
+impl CustomAzureProvider {
+    pub fn new(auth_uri: &str, token_uri: &str) -> Result<Self, url::ParseError> {
+        let auth_uri = Url::parse(auth_uri)?;
+        let token_uri = Url::parse(token_uri)?;
+
+        Ok(Self { auth_uri, token_uri })
+    }
+
+    /// Creates a provider from already parsed URIs.
+    pub fn from_uris(auth_uri: Url, token_uri: Url) -> Self {
+        Self { auth_uri, token_uri }
+    }
+}
+
+impl Provider for CustomAzureProvider {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.auth_uri }
+    fn token_uri(&self) -> &Url { &self.token_uri }
+}
+
+impl AzureProvider for CustomAzureProvider {
+    fn device_authorization_uri(&self) -> Option<Url> { None }
+    fn end_session_uri(&self) -> Option<Url> { None }
+    fn jwks_uri(&self) -> Option<Url> { None }
+    fn userinfo_uri(&self) -> Option<Url> { None }
+    fn discovery_uri(&self) -> Option<Url> { None }
+}
@@ -0,0 +1,181 @@
+//! Device authorization grant.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+use inth_oauth2::error::OAuth2ErrorCode;
+
+/// The response to a device authorization request: the code the user enters, and where.
+///
+/// See [RFC 8628, section 3.2](https://tools.ietf.org/html/rfc8628#section-3.2).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAuthorization {
+    /// The code the client polls the token endpoint with.
+    pub device_code: String,
+
+    /// The code the user enters at the verification URI.
+    pub user_code: String,
+
+    /// Where the user signs in, eg. `https://microsoft.com/devicelogin`.
+    pub verification_uri: String,
+
+    /// Seconds until the codes expire.
+    pub expires_in: u64,
+
+    /// Seconds to wait between polls of the token endpoint.
+    pub interval: u64,
+
+    /// Instructions for the user, localized by Azure AD.
+    pub message: Option<String>,
+}
+
+/// The `grant_type` of token requests redeeming a device code.
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// The polling interval RFC 8628 defaults to when the response doesn't include one.
+const DEFAULT_INTERVAL: u64 = 5;
+
+impl FromResponse for DeviceAuthorization {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let obj = json.as_object().ok_or(ParseError::ExpectedType("object"))?;
+
+        let string = |key: &'static str| obj.get(key)
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or(ParseError::ExpectedFieldType(key, "string"));
+
+        // The v1.0 endpoint predates RFC 8628 and returns verification_url
+        let verification_uri = string("verification_uri").or_else(|_| string("verification_url"))?;
+
+        let expires_in = obj.get("expires_in")
+            .and_then(as_u64)
+            .ok_or(ParseError::ExpectedFieldType("expires_in", "number"))?;
+        let interval = obj.get("interval").and_then(as_u64).unwrap_or(DEFAULT_INTERVAL);
+
+        Ok(DeviceAuthorization {
+            device_code: string("device_code")?,
+            user_code: string("user_code")?,
+            verification_uri,
+            expires_in,
+            interval,
+            message: string("message").ok(),
+        })
+    }
+}
+
+/// How much longer to wait between polls each time the token endpoint asks to slow down.
+const SLOW_DOWN_INCREMENT: Duration = Duration::from_secs(5);
+
+/// The outcome of polling the token endpoint once with a device code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevicePoll<T> {
+    /// The user signed in, and the token was issued.
+    Complete(T),
+
+    /// The user hasn't signed in yet; poll again after the interval.
+    Pending,
+
+    /// Polling too often; poll again after the interval, which has been increased.
+    SlowDown,
+
+    /// The user declined to sign in.
+    Declined,
+
+    /// The device code expired before the user signed in.
+    Expired,
+}
+
+/// The state of polling for a device code's token: the interval between polls, lengthened
+/// by each `slow_down`, and when the code expires.
+///
+/// `ClientExt::poll_device_token` polls until the outcome is final. Poll step by step with
+/// `ClientExt::poll_device` instead to do other work between polls, or to cancel.
+///
+/// ```rust,no_run
+/// # extern crate reqwest;
+/// # extern crate inth_oauth2_azure;
+/// # use std::thread;
+/// # use inth_oauth2_azure::{AzureOrganization, ClientExt, DevicePoll, DevicePoller, PublicClient};
+/// # fn main() -> Result<(), inth_oauth2::ClientError> {
+/// let client = PublicClient::new(AzureOrganization, "client-id".into(), None);
+/// let http_client = reqwest::Client::new();
+///
+/// let authorization = client.start_device_authorization(&http_client, "openid offline_access")?;
+/// let mut poller = DevicePoller::new(&authorization);
+///
+/// let token = loop {
+///     thread::sleep(poller.interval());
+///     match client.poll_device(&http_client, &mut poller)? {
+///         DevicePoll::Complete(token) => break Some(token),
+///         DevicePoll::Pending | DevicePoll::SlowDown => continue,
+///         DevicePoll::Declined | DevicePoll::Expired => break None,
+///     }
+/// };
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DevicePoller {
+    device_code: String,
+    interval: Duration,
+    expires: Instant,
+}
+
+impl DevicePoller {
+    /// Starts polling for the token of a device authorization, which expires `expires_in`
+    /// seconds from now.
+    pub fn new(authorization: &DeviceAuthorization) -> Self {
+        DevicePoller {
+            device_code: authorization.device_code.clone(),
+            interval: Duration::from_secs(authorization.interval),
+            expires: Instant::now() + Duration::from_secs(authorization.expires_in),
+        }
+    }
+
+    /// Returns the device code polled with.
+    pub fn device_code(&self) -> &str { &self.device_code }
+
+    /// Returns how long to wait before the next poll.
+    pub fn interval(&self) -> Duration { self.interval }
+
+    /// Returns true if the device code has expired, so polling is pointless.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires
+    }
+
+    /// Interprets the token endpoint's response to a poll, slowing down if it asks to.
+    /// Errors other than those of RFC 8628 are returned as is.
+    pub(crate) fn outcome<T>(&mut self, response: Result<T, ClientError>) -> Result<DevicePoll<T>, ClientError> {
+        let error = match response {
+            Ok(token) => return Ok(DevicePoll::Complete(token)),
+            Err(ClientError::OAuth2(error)) => error,
+            Err(error) => return Err(error),
+        };
+
+        // Azure AD declines with authorization_declined and the v1.0 endpoint expires with
+        // code_expired, besides the codes of RFC 8628
+        let outcome = match error.code {
+            OAuth2ErrorCode::Unrecognized(ref code) => match code.as_str() {
+                "authorization_pending" => Some(DevicePoll::Pending),
+                "slow_down" => Some(DevicePoll::SlowDown),
+                "authorization_declined" | "access_denied" => Some(DevicePoll::Declined),
+                "expired_token" | "code_expired" => Some(DevicePoll::Expired),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(DevicePoll::SlowDown) = outcome {
+            self.interval += SLOW_DOWN_INCREMENT;
+        }
+
+        outcome.ok_or(ClientError::OAuth2(error))
+    }
+}
+
+// The v1.0 endpoint returns numbers as strings
+fn as_u64(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
@@ -0,0 +1,96 @@
+//! Directory roles.
+
+use std::fmt;
+
+macro_rules! directory_roles {
+    ($( $(#[$meta:meta])* $variant:ident = $template_id:expr, $name:expr; )+) => {
+        /// A built-in Azure AD directory role, as found by its template ID in the `wids` claim.
+        ///
+        /// Only a selection of the built-in roles is listed; `IdTokenClaims::wids` holds the
+        /// template IDs of every role assigned, including custom ones.
+        ///
+        /// See [Azure AD built-in roles](https://learn.microsoft.com/entra/identity/role-based-access-control/permissions-reference).
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub enum DirectoryRole {
+            $( $(#[$meta])* $variant ),+
+        }
+
+        impl DirectoryRole {
+            /// Returns the role with the template ID `template_id`, if it's listed.
+            pub fn from_template_id(template_id: &str) -> Option<Self> {
+                $( if template_id.eq_ignore_ascii_case($template_id) { return Some(DirectoryRole::$variant); } )+
+                None
+            }
+
+            /// Returns the role's template ID, the same in every tenant.
+            pub fn template_id(&self) -> &'static str {
+                match *self {
+                    $( DirectoryRole::$variant => $template_id ),+
+                }
+            }
+
+            /// Returns the role's display name.
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $( DirectoryRole::$variant => $name ),+
+                }
+            }
+        }
+    }
+}
+
+directory_roles! {
+    /// Manages every aspect of the tenant.
+    GlobalAdministrator = "62e90394-69f5-4237-9190-012177145e10", "Global Administrator";
+    /// Reads everything a Global Administrator can, but can't change anything.
+    GlobalReader = "f2ef992c-3afb-46b9-b7cf-a126ee74c451", "Global Reader";
+    /// Manages role assignments and Privileged Identity Management.
+    PrivilegedRoleAdministrator = "e8611ab8-c189-46e8-94e1-60213ab1f814", "Privileged Role Administrator";
+    /// Manages users and groups, and resets passwords of non-admins.
+    UserAdministrator = "fe930be7-5e62-47db-91af-98c3a49a38b1", "User Administrator";
+    /// Manages groups and their settings.
+    GroupsAdministrator = "fdd7a751-b60b-444a-984c-02652fe8fa1c", "Groups Administrator";
+    /// Resets passwords of non-admins and Helpdesk Administrators.
+    HelpdeskAdministrator = "729827e3-9c14-49f7-bb1b-9608f156bbb8", "Helpdesk Administrator";
+    /// Manages the authentication methods of non-admins.
+    AuthenticationAdministrator = "c4e39bd9-1100-46d3-8c65-fb160da0071f", "Authentication Administrator";
+    /// Manages the authentication methods of every user, admins included.
+    PrivilegedAuthenticationAdministrator = "7be44c8a-adaf-4e2a-84d6-ab2649e08a13", "Privileged Authentication Administrator";
+    /// Manages app registrations, enterprise apps and application proxy.
+    ApplicationAdministrator = "9b895d92-2cd3-44c7-9d02-a6ac2d5ea5c3", "Application Administrator";
+    /// Manages app registrations and enterprise apps, except application proxy.
+    CloudApplicationAdministrator = "158c047a-c907-4556-b7ef-446551a6b5f7", "Cloud Application Administrator";
+    /// Manages Conditional Access policies.
+    ConditionalAccessAdministrator = "b1be1c3e-b65d-4f19-8427-f6fa0d97feb9", "Conditional Access Administrator";
+    /// Manages security features and reads security reports.
+    SecurityAdministrator = "194ae4cb-b126-40b2-bd5b-6091b380977d", "Security Administrator";
+    /// Reads security information and reports.
+    SecurityReader = "5d6b6bb7-de71-4623-b4af-96380a352509", "Security Reader";
+    /// Manages compliance configuration and reports.
+    ComplianceAdministrator = "17315797-102d-40b4-93e0-432062caca18", "Compliance Administrator";
+    /// Makes purchases, manages subscriptions and support tickets.
+    BillingAdministrator = "b0f54661-2d74-4c50-afa3-1ec803f12efe", "Billing Administrator";
+    /// Reads usage reports.
+    ReportsReader = "4a5d8f65-41da-4de4-8968-e035b65339cf", "Reports Reader";
+    /// Manages Exchange Online.
+    ExchangeAdministrator = "29232cdf-9323-42fd-ade2-1d097af3e4de", "Exchange Administrator";
+    /// Manages SharePoint Online.
+    SharePointAdministrator = "f28a1f50-f6e7-4571-818b-6a12f2af6b6c", "SharePoint Administrator";
+    /// Manages Microsoft Teams.
+    TeamsAdministrator = "69091246-20e8-4a56-aa4d-066075b2a7a8", "Teams Administrator";
+    /// Manages Microsoft Intune.
+    IntuneAdministrator = "3a2c62db-5318-420d-8d74-23affee5d9d5", "Intune Administrator";
+    /// Invites guest users regardless of the tenant's guest settings.
+    GuestInviter = "95e79109-95c0-4d8e-aee3-d01accf2d47b", "Guest Inviter";
+    /// Reads basic directory information.
+    DirectoryReaders = "88d8e3e3-8f55-4a1e-953a-9b9898b8876b", "Directory Readers";
+    /// Reads and writes basic directory information, for applications.
+    DirectoryWriters = "9360feb5-f418-4baa-8175-e2a00bac4301", "Directory Writers";
+}
+
+impl fmt::Display for DirectoryRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,173 @@
+//! OpenID Connect discovery.
+
+use std::time::Duration;
+
+use reqwest;
+use serde_json::Value;
+use url::Url;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cache::MetadataCache;
+use provider::AzureProvider;
+use request;
+
+/// An authority's OpenID Connect discovery document, as published at its
+/// `/.well-known/openid-configuration`.
+///
+/// The endpoints of the other providers are derived from the authority URI, which is enough
+/// for most apps, but Microsoft may publish different ones, eg. for B2C custom domains or
+/// sovereign clouds. The configuration is itself a provider of the endpoints it lists.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::client::response::FromResponse;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::{AzureProvider, IdTokenValidator, OpenIdConfiguration};
+/// # fn main() {
+/// let configuration = OpenIdConfiguration::from_response(&json!({
+///     "issuer": "https://login.microsoftonline.com/{tenantid}/v2.0",
+///     "authorization_endpoint": "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+///     "token_endpoint": "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+///     "jwks_uri": "https://login.microsoftonline.com/common/discovery/v2.0/keys",
+///     "end_session_endpoint": "https://login.microsoftonline.com/common/oauth2/v2.0/logout",
+///     "id_token_signing_alg_values_supported": ["RS256"],
+///     "cloud_instance_name": "microsoftonline.com",
+/// })).unwrap();
+///
+/// assert_eq!(configuration.token_uri().as_str(), "https://login.microsoftonline.com/common/oauth2/v2.0/token");
+/// assert_eq!(configuration.device_authorization_uri(), None);
+///
+/// let validator = IdTokenValidator::from_configuration(&configuration, "6cb04018-a3f5-46a7-b995-940c78f5aef3");
+/// assert_eq!(validator.issuers(), ["https://login.microsoftonline.com/{tenantid}/v2.0"]);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenIdConfiguration {
+    /// The issuer of the authority's tokens, with a `{tenantid}` template for multi-tenant
+    /// authorities.
+    pub issuer: String,
+
+    /// The authorization endpoint URI.
+    pub authorization_endpoint: Url,
+
+    /// The token endpoint URI.
+    pub token_endpoint: Url,
+
+    /// The URI of the JSON Web Key Set tokens are signed with.
+    pub jwks_uri: Url,
+
+    /// The end session (logout) endpoint URI.
+    pub end_session_endpoint: Option<Url>,
+
+    /// The OpenID Connect userinfo endpoint URI.
+    pub userinfo_endpoint: Option<Url>,
+
+    /// The device authorization endpoint URI.
+    pub device_authorization_endpoint: Option<Url>,
+
+    /// The algorithms ID tokens are signed with.
+    pub id_token_signing_alg_values_supported: Vec<String>,
+
+    /// The scopes the authority supports.
+    pub scopes_supported: Vec<String>,
+
+    /// The claims the authority may issue.
+    pub claims_supported: Vec<String>,
+
+    /// The Azure cloud the authority belongs to, eg. `microsoftonline.us`.
+    pub cloud_instance_name: Option<String>,
+}
+
+impl OpenIdConfiguration {
+    /// Fetches the discovery document at `uri`, see `AzureProvider::discovery_uri`.
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureB2C, AzureProvider, OpenIdConfiguration};
+    /// # fn main() -> Result<(), inth_oauth2::ClientError> {
+    /// let provider = AzureB2C::new("contoso", "B2C_1_susi");
+    /// let uri = provider.discovery_uri().unwrap();
+    ///
+    /// let configuration = OpenIdConfiguration::fetch(&reqwest::Client::new(), &uri)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fetch(http_client: &reqwest::Client, uri: &Url) -> Result<Self, ClientError> {
+        let json = request::get_json(http_client, uri)?;
+
+        Ok(Self::from_response(&json)?)
+    }
+
+    /// Like `fetch`, but through `cache`, where the document is kept for `ttl`.
+    pub fn fetch_cached(
+        http_client: &reqwest::Client,
+        uri: &Url,
+        cache: &dyn MetadataCache,
+        ttl: Duration,
+    ) -> Result<Self, ClientError> {
+        if let Some(configuration) = cache.get(uri.as_str()).and_then(|json| Self::from_response(&json).ok()) {
+            return Ok(configuration);
+        }
+
+        let json = request::get_json(http_client, uri)?;
+        let configuration = Self::from_response(&json)?;
+        cache.put(uri.as_str(), &json, ttl);
+
+        Ok(configuration)
+    }
+}
+
+impl FromResponse for OpenIdConfiguration {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let obj = json.as_object().ok_or(ParseError::ExpectedType("object"))?;
+
+        let uri = |key: &'static str| obj.get(key)
+            .and_then(Value::as_str)
+            .and_then(|uri| Url::parse(uri).ok())
+            .ok_or(ParseError::ExpectedFieldType(key, "URI"));
+
+        let strings = |key: &'static str| obj.get(key)
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+            .unwrap_or_default();
+
+        let issuer = obj.get("issuer")
+            .and_then(Value::as_str)
+            .ok_or(ParseError::ExpectedFieldType("issuer", "string"))?;
+
+        Ok(OpenIdConfiguration {
+            issuer: issuer.to_owned(),
+            authorization_endpoint: uri("authorization_endpoint")?,
+            token_endpoint: uri("token_endpoint")?,
+            jwks_uri: uri("jwks_uri")?,
+            end_session_endpoint: uri("end_session_endpoint").ok(),
+            userinfo_endpoint: uri("userinfo_endpoint").ok(),
+            device_authorization_endpoint: uri("device_authorization_endpoint").ok(),
+            id_token_signing_alg_values_supported: strings("id_token_signing_alg_values_supported"),
+            scopes_supported: strings("scopes_supported"),
+            claims_supported: strings("claims_supported"),
+            cloud_instance_name: obj.get("cloud_instance_name").and_then(Value::as_str).map(str::to_owned),
+        })
+    }
+}
+
+impl Provider for OpenIdConfiguration {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.authorization_endpoint }
+    fn token_uri(&self) -> &Url { &self.token_endpoint }
+}
+
+impl AzureProvider for OpenIdConfiguration {
+    fn device_authorization_uri(&self) -> Option<Url> { self.device_authorization_endpoint.clone() }
+    fn end_session_uri(&self) -> Option<Url> { self.end_session_endpoint.clone() }
+    fn jwks_uri(&self) -> Option<Url> { Some(self.jwks_uri.clone()) }
+    fn userinfo_uri(&self) -> Option<Url> { self.userinfo_endpoint.clone() }
+}
@@ -0,0 +1,56 @@
+//! Endpoints of the multi-tenant audiences in each cloud.
+//!
+//! The unit struct providers have to return `&Url`, so their endpoints are parsed once into a
+//! single table. Supporting another cloud means adding it to `CLOUDS`.
+
+use std::sync::LazyLock;
+
+use url::Url;
+
+use cloud::AzureCloud;
+
+/// The clouds with unit struct providers, in table order.
+const CLOUDS: [AzureCloud; 4] = [AzureCloud::Public, AzureCloud::UsGov, AzureCloud::China, AzureCloud::Germany];
+
+/// The audiences with unit struct providers, in table order.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Audience {
+    Common,
+    Organizations,
+    Consumers,
+}
+
+const AUDIENCES: [Audience; 3] = [Audience::Common, Audience::Organizations, Audience::Consumers];
+
+impl Audience {
+    fn tenant(self) -> &'static str {
+        match self {
+            Audience::Common => "common",
+            Audience::Organizations => "organizations",
+            Audience::Consumers => "consumers",
+        }
+    }
+}
+
+pub(crate) struct Endpoints {
+    pub auth_uri: Url,
+    pub token_uri: Url
+}
+
+static ENDPOINTS: LazyLock<Vec<Endpoints>> = LazyLock::new(|| {
+    CLOUDS.iter()
+        .flat_map(|cloud| AUDIENCES.iter().map(move |audience| Endpoints {
+            auth_uri: cloud.endpoint(audience.tenant(), "oauth2/v2.0/authorize"),
+            token_uri: cloud.endpoint(audience.tenant(), "oauth2/v2.0/token"),
+        }))
+        .collect()
+});
+
+/// Returns the endpoints of an audience in one of `CLOUDS`.
+pub(crate) fn well_known(cloud: &AzureCloud, audience: Audience) -> &'static Endpoints {
+    let index = CLOUDS.iter()
+        .position(|c| c == cloud)
+        .expect("no well-known endpoints for cloud");
+
+    &ENDPOINTS[index * AUDIENCES.len() + audience as usize]
+}
@@ -0,0 +1,576 @@
+//! Errors.
+
+use std::error::Error;
+use std::fmt;
+
+use url;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::ParseError;
+use inth_oauth2::error::OAuth2Error;
+use openssl::error::ErrorStack;
+
+#[cfg(feature = "serde")]
+use serde_json;
+
+/// Errors that can occur when interpreting an authority URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthorityError {
+    /// The authority isn't a valid URL.
+    Url(url::ParseError),
+
+    /// The host isn't a known Azure AD, B2C, External ID or ADFS authority.
+    UnrecognizedHost(String),
+
+    /// The host isn't a bare hostname, eg. because it contains a path.
+    InvalidHost(String),
+
+    /// The URL doesn't contain the path segments expected for its host.
+    MissingSegment(&'static str),
+
+    /// The tenant identifier isn't valid.
+    Tenant(AzureTenantError),
+
+    /// The B2C user flow (policy) isn't a single path segment of letters, digits, `_` and `-`.
+    InvalidPolicy(String),
+}
+
+impl fmt::Display for AuthorityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            AuthorityError::Url(ref err) => write!(f, "{}", err),
+            AuthorityError::UnrecognizedHost(ref host) => write!(f, "Unrecognized authority host {}", host),
+            AuthorityError::InvalidHost(ref host) => write!(f, "Invalid authority host {}", host),
+            AuthorityError::MissingSegment(segment) => write!(f, "Authority URL is missing the {}", segment),
+            AuthorityError::Tenant(ref err) => write!(f, "{}", err),
+            AuthorityError::InvalidPolicy(ref policy) => write!(f, "Invalid B2C user flow {:?}", policy),
+        }
+    }
+}
+
+impl Error for AuthorityError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            AuthorityError::Url(ref err) => Some(err),
+            AuthorityError::Tenant(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for AuthorityError {
+    fn from(err: url::ParseError) -> Self {
+        AuthorityError::Url(err)
+    }
+}
+
+impl From<AzureTenantError> for AuthorityError {
+    fn from(err: AzureTenantError) -> Self {
+        AuthorityError::Tenant(err)
+    }
+}
+
+/// Errors that can occur when validating a tenant identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AzureTenantError {
+    /// The tenant identifier is empty.
+    Empty,
+
+    /// The tenant identifier contains a character that can't appear in a GUID or domain name.
+    InvalidCharacter(char),
+
+    /// The tenant identifier starts or ends with a `.` or `-`.
+    InvalidBoundary,
+
+    /// The tenant identifier is neither a GUID nor a fully qualified domain name.
+    InvalidDomain,
+}
+
+impl fmt::Display for AzureTenantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            AzureTenantError::Empty => write!(f, "Tenant identifier is empty"),
+            AzureTenantError::InvalidCharacter(c) => write!(f, "Tenant identifier contains invalid character {:?}", c),
+            AzureTenantError::InvalidBoundary => write!(f, "Tenant identifier can't start or end with '.' or '-'"),
+            AzureTenantError::InvalidDomain => write!(f, "Tenant identifier is neither a GUID nor a domain name"),
+        }
+    }
+}
+
+impl Error for AzureTenantError {}
+
+/// Errors that can occur when validating a redirect URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectUriError {
+    /// The redirect URI isn't an absolute URL.
+    Url(url::ParseError),
+
+    /// The redirect URI is longer than 256 characters.
+    TooLong,
+
+    /// The redirect URI contains a wildcard.
+    Wildcard,
+
+    /// The redirect URI contains a fragment.
+    Fragment,
+
+    /// The redirect URI uses `http` for a host other than the loopback interface.
+    InsecureScheme,
+}
+
+impl fmt::Display for RedirectUriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RedirectUriError::Url(ref err) => write!(f, "{}", err),
+            RedirectUriError::TooLong => write!(f, "Redirect URI is longer than 256 characters"),
+            RedirectUriError::Wildcard => write!(f, "Redirect URI can't contain wildcards"),
+            RedirectUriError::Fragment => write!(f, "Redirect URI can't contain a fragment"),
+            RedirectUriError::InsecureScheme => write!(f, "Redirect URI must use https unless it's localhost"),
+        }
+    }
+}
+
+impl Error for RedirectUriError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RedirectUriError::Url(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for RedirectUriError {
+    fn from(err: url::ParseError) -> Self {
+        RedirectUriError::Url(err)
+    }
+}
+
+/// Errors that can occur when validating a `ScopeSet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeError {
+    /// No scopes were given.
+    Empty,
+
+    /// A scope is empty or contains whitespace.
+    Invalid(String),
+
+    /// The scopes are for more than one resource.
+    MultipleResources(String, String),
+
+    /// A resource's `.default` scope was combined with its other scopes.
+    MixedDefault(String),
+
+    /// A refresh token was expected, but `offline_access` wasn't requested.
+    MissingOfflineAccess,
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ScopeError::Empty => write!(f, "No scopes requested"),
+            ScopeError::Invalid(ref scope) => write!(f, "Invalid scope {:?}", scope),
+            ScopeError::MultipleResources(ref a, ref b) => write!(f, "Scopes are for more than one resource: {} and {}", a, b),
+            ScopeError::MixedDefault(ref resource) => write!(f, "{}/.default can't be combined with other scopes for {}", resource, resource),
+            ScopeError::MissingOfflineAccess => write!(f, "offline_access is required for a refresh token"),
+        }
+    }
+}
+
+impl Error for ScopeError {}
+
+/// Errors that can occur when encoding or decoding a `StatePayload`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The key can't be used for HMAC, because it's empty.
+    Key,
+
+    /// The state wasn't produced by `StatePayload::encode`.
+    Malformed,
+
+    /// The signature doesn't match, so the state was forged or signed with another key.
+    Signature,
+
+    /// The state has expired.
+    Expired,
+
+    /// The state is bound to a different session.
+    Binding,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            StateError::Key => write!(f, "State key is empty"),
+            StateError::Malformed => write!(f, "State is malformed"),
+            StateError::Signature => write!(f, "State signature is invalid"),
+            StateError::Expired => write!(f, "State has expired"),
+            StateError::Binding => write!(f, "State belongs to another session"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+/// Errors that can occur when checking an authorization response against its request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackError {
+    /// The `state` doesn't match the request's, so the response wasn't solicited.
+    State,
+
+    /// The response is missing a parameter the request's `response_type` requires.
+    Missing(&'static str),
+
+    /// The ID token can't be decoded.
+    IdToken,
+
+    /// The ID token's `nonce` doesn't match the request's.
+    Nonce,
+
+    /// The ID token's `c_hash` doesn't match the authorization code.
+    CodeHash,
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CallbackError::State => write!(f, "State doesn't match the authorization request"),
+            CallbackError::Missing(param) => write!(f, "Authorization response is missing the {}", param),
+            CallbackError::IdToken => write!(f, "ID token is malformed"),
+            CallbackError::Nonce => write!(f, "ID token nonce doesn't match the authorization request"),
+            CallbackError::CodeHash => write!(f, "ID token c_hash doesn't match the authorization code"),
+        }
+    }
+}
+
+impl Error for CallbackError {}
+
+/// Errors that can occur when redeeming the authorization code of a callback.
+#[derive(Debug)]
+pub enum RedeemError {
+    /// The callback doesn't belong to the authorization request, or carries no code.
+    Callback(CallbackError),
+
+    /// The token request failed.
+    Client(ClientError),
+}
+
+impl fmt::Display for RedeemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RedeemError::Callback(ref err) => write!(f, "{}", err),
+            RedeemError::Client(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RedeemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RedeemError::Callback(ref err) => Some(err),
+            RedeemError::Client(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<CallbackError> for RedeemError {
+    fn from(err: CallbackError) -> Self {
+        RedeemError::Callback(err)
+    }
+}
+
+impl From<ClientError> for RedeemError {
+    fn from(err: ClientError) -> Self {
+        RedeemError::Client(err)
+    }
+}
+
+/// Errors that can occur when validating a token.
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The token isn't a signed JWT.
+    Malformed,
+
+    /// The token is signed with an algorithm that isn't accepted.
+    Algorithm(String),
+
+    /// The token's `kid` isn't in the key set.
+    UnknownKey(Option<String>),
+
+    /// The signing key can't be used.
+    Key(ErrorStack),
+
+    /// The signing key's certificate isn't one of the pinned certificates.
+    Unpinned,
+
+    /// The signature is invalid.
+    Signature,
+
+    /// The encrypted token can't be decrypted, or there's no key to decrypt it with.
+    Decryption,
+
+    /// The claims are missing or of the wrong type.
+    Claims(ParseError),
+
+    /// The token has expired.
+    Expired,
+
+    /// The token isn't valid yet.
+    NotYetValid,
+
+    /// The token was issued by another issuer.
+    Issuer(String),
+
+    /// The token was issued for another audience.
+    Audience(String),
+
+    /// The token's `nonce` is missing or doesn't match the authorization request's.
+    Nonce,
+
+    /// The token was issued by another tenant, or carries no `tid`.
+    Tenant(Option<String>),
+
+    /// The user didn't authenticate with multiple factors.
+    Mfa,
+
+    /// The user authenticated longer ago than `max_age` allows, or the token has no
+    /// `auth_time`.
+    AuthTime(Option<u64>),
+
+    /// The token was requested by an application that isn't accepted, or doesn't say which.
+    AuthorizedParty(Option<String>),
+
+    /// The application that requested the token didn't authenticate with a certificate.
+    ClientAuthentication(Option<String>),
+
+    /// The access token wasn't granted a required scope.
+    Scope(String),
+
+    /// The access token's caller wasn't assigned a required app role.
+    Role(String),
+
+    /// The key set couldn't be fetched.
+    Jwks(ClientError),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ValidationError::Malformed => write!(f, "Token is not a signed JWT"),
+            ValidationError::Algorithm(ref alg) => write!(f, "Token signing algorithm {} is not accepted", alg),
+            ValidationError::UnknownKey(Some(ref kid)) => write!(f, "Token signing key {} is unknown", kid),
+            ValidationError::UnknownKey(None) => write!(f, "Token doesn't identify its signing key"),
+            ValidationError::Key(ref err) => write!(f, "{}", err),
+            ValidationError::Unpinned => write!(f, "Token signing certificate is not pinned"),
+            ValidationError::Signature => write!(f, "Token signature is invalid"),
+            ValidationError::Decryption => write!(f, "Token can't be decrypted"),
+            ValidationError::Claims(ref err) => write!(f, "{}", err),
+            ValidationError::Expired => write!(f, "Token has expired"),
+            ValidationError::NotYetValid => write!(f, "Token is not valid yet"),
+            ValidationError::Issuer(ref iss) => write!(f, "Token issuer {} is not accepted", iss),
+            ValidationError::Audience(ref aud) => write!(f, "Token audience {} is not accepted", aud),
+            ValidationError::Nonce => write!(f, "Token nonce doesn't match the authorization request"),
+            ValidationError::Tenant(Some(ref tid)) => write!(f, "Token tenant {} is not accepted", tid),
+            ValidationError::Tenant(None) => write!(f, "Token doesn't identify its tenant"),
+            ValidationError::Mfa => write!(f, "Token wasn't issued with multi-factor authentication"),
+            ValidationError::AuthTime(Some(_)) => write!(f, "Authentication is older than the request's max_age"),
+            ValidationError::AuthTime(None) => write!(f, "Token doesn't say when the user authenticated"),
+            ValidationError::AuthorizedParty(Some(ref azp)) => write!(f, "Token client application {} is not accepted", azp),
+            ValidationError::AuthorizedParty(None) => write!(f, "Token doesn't identify its client application"),
+            ValidationError::ClientAuthentication(_) => write!(f, "Token client application didn't authenticate with a certificate"),
+            ValidationError::Scope(ref scope) => write!(f, "Token wasn't granted the scope {}", scope),
+            ValidationError::Role(ref role) => write!(f, "Token's caller doesn't have the role {}", role),
+            ValidationError::Jwks(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ValidationError::Key(ref err) => Some(err),
+            ValidationError::Claims(ref err) => Some(err),
+            ValidationError::Jwks(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can occur when requesting a token with a user's username and password.
+///
+/// Azure AD explains why it refused the credentials with an `AADSTS` code at the start of
+/// the error description; the reasons a test account can't sign in non-interactively are
+/// told apart from other errors.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::ClientError;
+/// # use oauth::error::{OAuth2Error, OAuth2ErrorCode};
+/// # use inth_oauth2_azure::PasswordGrantError;
+/// # fn main() {
+/// let error = ClientError::OAuth2(OAuth2Error {
+///     code: OAuth2ErrorCode::InvalidGrant,
+///     description: Some("AADSTS50076: Due to a configuration change made by your administrator, you must use multi-factor authentication.".into()),
+///     uri: None,
+/// });
+///
+/// match PasswordGrantError::from(error) {
+///     PasswordGrantError::InteractionRequired(_) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// let error = ClientError::OAuth2(OAuth2Error {
+///     code: OAuth2ErrorCode::InvalidGrant,
+///     description: Some("AADSTS50107: The requested federation realm object 'urn:federation:contoso' does not exist.".into()),
+///     uri: None,
+/// });
+///
+/// match PasswordGrantError::from(error) {
+///     PasswordGrantError::Federated(_) => {},
+///     other => panic!("{:?}", other),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum PasswordGrantError {
+    /// The user must sign in interactively, to complete multi-factor authentication or
+    /// another challenge (`AADSTS50076`, `AADSTS50079`, `AADSTS50158`).
+    InteractionRequired(OAuth2Error),
+
+    /// The username or password is wrong (`AADSTS50126`).
+    InvalidCredentials(OAuth2Error),
+
+    /// The user's password has expired and must be changed (`AADSTS50055`).
+    PasswordExpired(OAuth2Error),
+
+    /// The user or an administrator hasn't consented to the scopes (`AADSTS65001`).
+    ConsentRequired(OAuth2Error),
+
+    /// The account's domain is federated with another identity provider, so Azure AD can't
+    /// check its password (`AADSTS50008`, `AADSTS50107`).
+    Federated(OAuth2Error),
+
+    /// The request failed otherwise.
+    Client(ClientError),
+}
+
+impl fmt::Display for PasswordGrantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            PasswordGrantError::InteractionRequired(ref err) => write!(f, "User must sign in interactively: {}", err),
+            PasswordGrantError::InvalidCredentials(ref err) => write!(f, "Username or password is invalid: {}", err),
+            PasswordGrantError::PasswordExpired(ref err) => write!(f, "Password has expired: {}", err),
+            PasswordGrantError::ConsentRequired(ref err) => write!(f, "Consent is required: {}", err),
+            PasswordGrantError::Federated(ref err) => write!(f, "Account is federated, so its password can't be checked: {}", err),
+            PasswordGrantError::Client(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for PasswordGrantError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PasswordGrantError::InteractionRequired(ref err)
+            | PasswordGrantError::InvalidCredentials(ref err)
+            | PasswordGrantError::PasswordExpired(ref err)
+            | PasswordGrantError::ConsentRequired(ref err)
+            | PasswordGrantError::Federated(ref err) => Some(err),
+            PasswordGrantError::Client(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<ClientError> for PasswordGrantError {
+    fn from(err: ClientError) -> Self {
+        let error = match err {
+            ClientError::OAuth2(error) => error,
+            err => return PasswordGrantError::Client(err),
+        };
+
+        let code = error.description.as_ref()
+            .and_then(|description| description.trim_start().strip_prefix("AADSTS"))
+            .map(|rest| rest.chars().take_while(char::is_ascii_digit).collect::<String>());
+
+        match code.as_deref() {
+            Some("50076") | Some("50079") | Some("50158") => PasswordGrantError::InteractionRequired(error),
+            Some("50126") => PasswordGrantError::InvalidCredentials(error),
+            Some("50055") => PasswordGrantError::PasswordExpired(error),
+            Some("65001") => PasswordGrantError::ConsentRequired(error),
+            Some("50008") | Some("50107") => PasswordGrantError::Federated(error),
+            _ => PasswordGrantError::Client(ClientError::OAuth2(error)),
+        }
+    }
+}
+
+/// Errors that can occur when loading an `AzureConfig`.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A required environment variable isn't set.
+    Missing(&'static str),
+
+    /// Both a complete authority URL and a tenant were given.
+    Conflict,
+
+    /// A confidential client was requested but no client secret is configured.
+    MissingSecret,
+
+    /// The authority or tenant isn't valid.
+    Authority(AuthorityError),
+
+    /// The redirect URI isn't valid.
+    RedirectUri(RedirectUriError),
+
+    /// The configuration isn't valid JSON.
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ConfigError::Missing(name) => write!(f, "Environment variable {} is not set", name),
+            ConfigError::Conflict => write!(f, "Tenant can't be set when the authority is a URL with a tenant"),
+            ConfigError::MissingSecret => write!(f, "Client secret is not set; public clients should use public_client()"),
+            ConfigError::Authority(ref err) => write!(f, "{}", err),
+            ConfigError::RedirectUri(ref err) => write!(f, "{}", err),
+            #[cfg(feature = "serde")]
+            ConfigError::Json(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ConfigError::Authority(ref err) => Some(err),
+            ConfigError::RedirectUri(ref err) => Some(err),
+            #[cfg(feature = "serde")]
+            ConfigError::Json(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<AuthorityError> for ConfigError {
+    fn from(err: AuthorityError) -> Self {
+        ConfigError::Authority(err)
+    }
+}
+
+impl From<RedirectUriError> for ConfigError {
+    fn from(err: RedirectUriError) -> Self {
+        ConfigError::RedirectUri(err)
+    }
+}
+
+impl From<AzureTenantError> for ConfigError {
+    fn from(err: AzureTenantError) -> Self {
+        ConfigError::Authority(AuthorityError::Tenant(err))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        ConfigError::Json(err)
+    }
+}
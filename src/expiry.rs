@@ -0,0 +1,225 @@
+//! Token expiry.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+use inth_oauth2::token::{Bearer, Expiring, Lifetime, Refresh, Token};
+
+use id_token::IdTokenClaims;
+
+/// When a token expires, so it can be refreshed ahead of time rather than after a request
+/// has failed with it.
+///
+/// Implemented by the expiring lifetimes, the bearer tokens carrying them and the claims of
+/// validated tokens.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use std::time::Duration;
+/// # use oauth::client::response::FromResponse;
+/// # use oauth::token::{Bearer, Refresh};
+/// # use inth_oauth2_azure::Expiry;
+/// # fn main() {
+/// let token = Bearer::<Refresh>::from_response(&json!({
+///     "token_type": "Bearer",
+///     "access_token": "eyJ0eXAi",
+///     "refresh_token": "OAQABAAAAAAAGV_bv21oQQ4ROqh0_1-tA",
+///     "expires_in": 600,
+/// })).unwrap();
+///
+/// assert!(!token.is_expired());
+/// assert!(!token.needs_refresh(Duration::from_secs(5 * 60)));
+/// assert!(token.needs_refresh(Duration::from_secs(15 * 60)));
+/// # assert!(token.needs_refresh(Duration::from_secs(u64::max_value())));
+/// # }
+/// ```
+pub trait Expiry {
+    /// Returns when the token expires.
+    fn expires_at(&self) -> SystemTime;
+
+    /// Returns true if the token has expired.
+    fn is_expired(&self) -> bool {
+        self.expires_at() <= SystemTime::now()
+    }
+
+    /// Returns true if the token expires within `margin`, so should be refreshed now.
+    fn needs_refresh(&self, margin: Duration) -> bool {
+        match SystemTime::now().checked_add(margin) {
+            Some(deadline) => self.expires_at() <= deadline,
+            None => true,
+        }
+    }
+
+    /// Returns when the issuer suggested refreshing the token, if it did.
+    fn refresh_hint(&self) -> Option<SystemTime> { None }
+}
+
+/// The refresh-ahead window used by default, five minutes.
+pub const DEFAULT_REFRESH_AHEAD: Duration = Duration::from_secs(5 * 60);
+
+/// How long before expiry tokens are refreshed, so a token handed out is never about to
+/// expire during the request it's used for.
+///
+/// A token is refreshed once less than `margin` of its lifetime remains, or once the
+/// `refresh_in` Azure AD sent with it has passed, whichever is earlier. Azure AD sends
+/// `refresh_in` with long-lived tokens, to have them refreshed well before they expire.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use std::time::{Duration, SystemTime};
+/// # use oauth::client::response::FromResponse;
+/// # use oauth::token::{Bearer, Refresh};
+/// # use inth_oauth2_azure::{Issued, RefreshAhead};
+/// # fn main() {
+/// let token = Issued::<Bearer<Refresh>>::from_response(&json!({
+///     "token_type": "Bearer",
+///     "access_token": "eyJ0eXAi",
+///     "refresh_token": "OAQABAAAAAAAGV_bv21oQQ4ROqh0_1-tA",
+///     "expires_in": 86399,
+///     "refresh_in": 43199,
+/// })).unwrap();
+///
+/// let ahead = RefreshAhead::default();
+/// assert!(!ahead.needs_refresh(&token));
+/// assert!(ahead.refresh_at(&token) <= SystemTime::now() + Duration::from_secs(43199));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RefreshAhead {
+    margin: Duration,
+}
+
+impl RefreshAhead {
+    /// Refreshes tokens once less than `margin` of their lifetime remains.
+    pub fn new(margin: Duration) -> Self {
+        RefreshAhead { margin }
+    }
+
+    /// Returns the window before expiry in which tokens are refreshed.
+    pub fn margin(&self) -> Duration { self.margin }
+
+    /// Returns when `token` should be refreshed.
+    pub fn refresh_at<T: Expiry + ?Sized>(&self, token: &T) -> SystemTime {
+        let ahead = token.expires_at().checked_sub(self.margin).unwrap_or(UNIX_EPOCH);
+        match token.refresh_hint() {
+            Some(hint) if hint < ahead => hint,
+            _ => ahead,
+        }
+    }
+
+    /// Returns true if `token` should be refreshed before it's used.
+    pub fn needs_refresh<T: Expiry + ?Sized>(&self, token: &T) -> bool {
+        self.refresh_at(token) <= SystemTime::now()
+    }
+}
+
+impl Default for RefreshAhead {
+    fn default() -> Self { Self::new(DEFAULT_REFRESH_AHEAD) }
+}
+
+/// A token along with the `refresh_in` of the response it was issued in, which the
+/// lifetimes of `inth_oauth2` don't keep.
+///
+/// Parse token responses as `Issued<Bearer<Refresh>>` rather than `Bearer<Refresh>` for
+/// `RefreshAhead` to honor `refresh_in`, or wrap existing tokens with `Issued::from`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issued<T> {
+    token: T,
+    refresh_hint: Option<SystemTime>,
+}
+
+impl<T> Issued<T> {
+    /// Returns the token.
+    pub fn token(&self) -> &T { &self.token }
+
+    /// Unwraps the token.
+    pub fn into_inner(self) -> T { self.token }
+}
+
+impl<T> From<T> for Issued<T> {
+    fn from(token: T) -> Self {
+        Issued { token, refresh_hint: None }
+    }
+}
+
+impl<T: FromResponse> FromResponse for Issued<T> {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        Ok(Issued { token: T::from_response(json)?, refresh_hint: refresh_hint(json)? })
+    }
+
+    fn from_response_inherit(json: &Value, prev: &Self) -> Result<Self, ParseError> {
+        let token = T::from_response_inherit(json, &prev.token)?;
+        Ok(Issued { token, refresh_hint: refresh_hint(json)? })
+    }
+}
+
+fn refresh_hint(json: &Value) -> Result<Option<SystemTime>, ParseError> {
+    // Like expires_in, sent as a number or, by the v1 endpoints, a string
+    let secs = match json.get("refresh_in") {
+        None | Some(Value::Null) => return Ok(None),
+        Some(Value::String(s)) => s.parse().ok(),
+        Some(n) => n.as_u64(),
+    };
+    let secs = secs.ok_or(ParseError::ExpectedFieldType("refresh_in", "u64"))?;
+    Ok(Some(SystemTime::now().checked_add(Duration::from_secs(secs)).unwrap_or_else(far_future)))
+}
+
+impl<T: Expiry> Expiry for Issued<T> {
+    fn expires_at(&self) -> SystemTime { self.token.expires_at() }
+    fn refresh_hint(&self) -> Option<SystemTime> { self.refresh_hint.or_else(|| self.token.refresh_hint()) }
+}
+
+// The lifetimes store their expiry as a chrono timestamp
+fn from_timestamp(secs: i64) -> SystemTime {
+    from_unix(secs.max(0) as u64)
+}
+
+// Claims come from tokens, which may claim to expire further ahead than `SystemTime` reaches
+fn from_unix(secs: u64) -> SystemTime {
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs)).unwrap_or_else(far_future)
+}
+
+// Some 35,000 years ahead, within the range of `SystemTime` on every platform
+fn far_future() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1 << 40)
+}
+
+impl Expiry for Refresh {
+    fn expires_at(&self) -> SystemTime { from_timestamp(self.expires().timestamp()) }
+}
+
+impl Expiry for Expiring {
+    fn expires_at(&self) -> SystemTime { from_timestamp(self.expires().timestamp()) }
+}
+
+impl<L: Lifetime + Expiry> Expiry for Bearer<L> {
+    fn expires_at(&self) -> SystemTime { self.lifetime().expires_at() }
+}
+
+/// Tokens may claim to expire further ahead than `SystemTime` can represent, such as those
+/// read with `dangerous_unverified_claims`; they're taken to expire in some 35,000 years.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{Expiry, IdTokenClaims};
+/// # fn main() {
+/// let claims = IdTokenClaims::from_json(&json!({
+///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "exp": u64::max_value(),
+///     "iat": 1536274711,
+/// })).unwrap();
+///
+/// assert!(!claims.is_expired());
+/// # }
+/// ```
+impl Expiry for IdTokenClaims {
+    fn expires_at(&self) -> SystemTime { from_unix(self.exp) }
+}
@@ -0,0 +1,724 @@
+//! Azure-specific client helpers.
+
+use std::thread;
+
+use reqwest;
+use serde_json::Value;
+use url::Url;
+use url::form_urlencoded::Serializer;
+use inth_oauth2::{Client, ClientError};
+use inth_oauth2::client::response::FromResponse;
+use inth_oauth2::error::{OAuth2Error, OAuth2ErrorCode};
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Expiring, Lifetime, Refresh, Token};
+
+use authorize::{AuthorizationRequest, AuthorizationSession, Prompt};
+use callback::AuthorizationResponse;
+use consent::AdminConsentRequest;
+use device::{DeviceAuthorization, DevicePoll, DevicePoller, DEVICE_CODE_GRANT_TYPE};
+use error::{CallbackError, PasswordGrantError, RedeemError};
+use expiry::{Expiry, Issued, RefreshAhead};
+use provider::AzureProvider;
+use public::PublicClient;
+use request::{post_token, post_token_from};
+use saml::{SamlAssertion, SamlVersion};
+
+/// Azure-specific helpers for `inth_oauth2::Client` and `PublicClient`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureOrganization, ClientExt, Prompt};
+/// let client = oauth::Client::new(
+///     AzureOrganization,
+///     "client-id".into(),
+///     "client-secret".into(),
+///     Some("https://localhost/callback".into())
+/// );
+///
+/// let uri = client.auth_uri_with_hint(Some("openid"), None, Some(Prompt::Login), Some("user@contoso.com"));
+/// assert!(uri.query().unwrap().ends_with("&prompt=login&login_hint=user%40contoso.com"));
+///
+/// let uri = client.admin_consent_uri(None, Some("state")).unwrap();
+/// assert_eq!(uri.path(), "/organizations/v2.0/adminconsent");
+/// ```
+pub trait ClientExt {
+    /// The type of token issued by the client's provider.
+    type Token;
+
+    /// Starts an `AuthorizationRequest` with the client's ID and redirect URI.
+    fn authorization_request(&self) -> AuthorizationRequest;
+
+    /// Starts an `AdminConsentRequest` with the client's ID and redirect URI, or returns
+    /// `None` if the provider has no admin consent endpoint.
+    fn admin_consent_request(&self) -> Option<AdminConsentRequest>;
+
+    /// Returns an authorization endpoint URI with a `prompt` and `login_hint`.
+    fn auth_uri_with_hint(
+        &self,
+        scope: Option<&str>,
+        state: Option<&str>,
+        prompt: Option<Prompt>,
+        login_hint: Option<&str>,
+    ) -> Url;
+
+    /// Returns the URI to direct a tenant administrator to, to grant the application's
+    /// permissions for their organization. Defaults to the scopes configured in the app
+    /// registration (`{resource}/.default`) when `scope` is `None`.
+    ///
+    /// Returns `None` if the provider has no admin consent endpoint.
+    fn admin_consent_uri(&self, scope: Option<&str>, state: Option<&str>) -> Option<Url>;
+
+    /// Requests an access token using an authorization code protected with PKCE.
+    fn request_token_with_verifier(
+        &self,
+        http_client: &reqwest::Client,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<Self::Token, ClientError>;
+
+    /// Redeems the authorization code of a callback, after checking its `state` against the
+    /// session of the request. The session's PKCE `code_verifier` is sent if it has one, as
+    /// Azure AD requires of public clients and single-page applications.
+    ///
+    /// Fails with `RedeemError::Callback` if the state doesn't match or there's no code,
+    /// without sending the code anywhere.
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureCommon, Callback, ClientExt, Pkce, PublicClient, parse_callback};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let callback_uri = "";
+    /// let client = PublicClient::new(AzureCommon, "client-id".into(), Some("http://localhost/callback".into()));
+    ///
+    /// let (uri, session) = client.authorization_request().scope("openid offline_access").pkce(Pkce::new()).build();
+    /// // ...redirect the user to `uri`, keeping the session until the callback
+    ///
+    /// if let Callback::Success(response) = parse_callback(callback_uri)? {
+    ///     let token = client.redeem_code(&reqwest::Client::new(), &response, &session)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn redeem_code(
+        &self,
+        http_client: &reqwest::Client,
+        response: &AuthorizationResponse,
+        session: &AuthorizationSession,
+    ) -> Result<Self::Token, RedeemError>;
+
+    /// Starts the device authorization grant, returning the code the user should enter.
+    ///
+    /// See [RFC 8628, section 3.1](https://tools.ietf.org/html/rfc8628#section-3.1).
+    fn start_device_authorization(
+        &self,
+        http_client: &reqwest::Client,
+        scope: &str,
+    ) -> Result<DeviceAuthorization, ClientError>;
+
+    /// Polls the token endpoint at the authorization's interval until the user has signed in
+    /// and returns the token, blocking the thread meanwhile.
+    ///
+    /// Waits longer between polls each time the endpoint asks to slow down. Fails with
+    /// `expired_token` if the user doesn't sign in before the codes expire, or
+    /// `authorization_declined` if they decline.
+    ///
+    /// See [RFC 8628, section 3.4](https://tools.ietf.org/html/rfc8628#section-3.4).
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureOrganization, ClientExt, PublicClient};
+    /// # fn main() -> Result<(), inth_oauth2::ClientError> {
+    /// let client = PublicClient::new(AzureOrganization, "client-id".into(), None);
+    /// let http_client = reqwest::Client::new();
+    ///
+    /// let authorization = client.start_device_authorization(&http_client, "openid offline_access")?;
+    /// println!("{}", authorization.message.as_ref().unwrap_or(&authorization.verification_uri));
+    ///
+    /// let token = client.poll_device_token(&http_client, &authorization)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn poll_device_token(
+        &self,
+        http_client: &reqwest::Client,
+        authorization: &DeviceAuthorization,
+    ) -> Result<Self::Token, ClientError>;
+
+    /// Requests a token with a user's username and password, with the resource owner
+    /// password credentials (ROPC) grant.
+    ///
+    /// **ROPC is insecure and legacy**: the app handles the password, and accounts that
+    /// require MFA, Conditional Access challenges or a federated identity provider can't sign
+    /// in. Reserve it for test automation with dedicated accounts; these cases fail with the
+    /// variants of `PasswordGrantError`. Only work or school accounts are supported, so use
+    /// the organizations or a tenant's endpoints.
+    ///
+    /// See [OAuth 2.0 ROPC](https://learn.microsoft.com/entra/identity-platform/v2-oauth-ropc).
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureOrganization, ClientExt, PasswordGrantError, PublicClient};
+    /// # fn main() {
+    /// let client = PublicClient::new(AzureOrganization, "client-id".into(), None);
+    ///
+    /// match client.request_token_with_password(&reqwest::Client::new(), "test@contoso.com", "password", "User.Read offline_access") {
+    ///     Ok(token) => {},
+    ///     Err(PasswordGrantError::InteractionRequired(_)) => panic!("exclude the test account from MFA"),
+    ///     Err(err) => panic!("{}", err),
+    /// }
+    /// # }
+    /// ```
+    fn request_token_with_password(
+        &self,
+        http_client: &reqwest::Client,
+        username: &str,
+        password: &str,
+        scope: &str,
+    ) -> Result<Self::Token, PasswordGrantError>;
+
+    /// Polls the token endpoint once for the token of a device code, without waiting for the
+    /// interval first, and returns the outcome. See `DevicePoller`.
+    fn poll_device(
+        &self,
+        http_client: &reqwest::Client,
+        poller: &mut DevicePoller,
+    ) -> Result<DevicePoll<Self::Token>, ClientError>;
+
+    /// Refreshes an access token, and calls `persist` with the new token if Azure AD rotated
+    /// its refresh token.
+    ///
+    /// Azure AD may issue a new refresh token with each refresh, and always does for
+    /// single-page applications, whose refresh tokens can't be reused. Once it has, the
+    /// old one may stop working, so the new one must be stored before the token is used.
+    ///
+    /// ```rust,no_run
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::token::{Bearer, Refresh};
+    /// # use inth_oauth2_azure::{AzureCommon, ClientExt, PublicClient};
+    /// # fn save(token: &Bearer<Refresh>) {}
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// # let token: Bearer<Refresh> = unimplemented!();
+    /// let client = PublicClient::new(AzureCommon, "client-id".into(), None);
+    ///
+    /// let token = client.refresh_token_rotating(&reqwest::Client::new(), token, None, |rotated| save(rotated))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn refresh_token_rotating<F: FnOnce(&Self::Token)>(
+        &self,
+        http_client: &reqwest::Client,
+        token: Self::Token,
+        scope: Option<&str>,
+        persist: F,
+    ) -> Result<Self::Token, ClientError>
+    where
+        Self::Token: Token<Refresh>;
+
+    /// Returns `token` as is, unless it's within the refresh-ahead window of `ahead`, in
+    /// which case it's refreshed first, calling `persist` like `refresh_token_rotating` does
+    /// if Azure AD rotated its refresh token.
+    ///
+    /// The refreshed token keeps the `refresh_in` of its response, so the next call honors
+    /// it. Calls for the same account from several threads at once should go through a
+    /// `RefreshGroup`.
+    ///
+    /// ```rust,no_run
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::token::{Bearer, Refresh};
+    /// # use inth_oauth2_azure::{AzureCommon, ClientExt, Issued, PublicClient, RefreshAhead};
+    /// # fn save(token: &Bearer<Refresh>) {}
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// # let token: Bearer<Refresh> = unimplemented!();
+    /// let client = PublicClient::new(AzureCommon, "client-id".into(), None);
+    /// let http = reqwest::Client::new();
+    ///
+    /// let mut token = Issued::from(token);
+    /// for _ in 0..3 {
+    ///     token = client.refresh_token_ahead(&http, token, None, &RefreshAhead::default(), |rotated| save(rotated))?;
+    ///     // use token.token().access_token()
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn refresh_token_ahead<F: FnOnce(&Self::Token)>(
+        &self,
+        http_client: &reqwest::Client,
+        token: Issued<Self::Token>,
+        scope: Option<&str>,
+        ahead: &RefreshAhead,
+        persist: F,
+    ) -> Result<Issued<Self::Token>, ClientError>
+    where
+        Self::Token: Token<Refresh> + Expiry;
+}
+
+macro_rules! impl_client_ext {
+    ($client:ident) => {
+        impl<P: AzureProvider> ClientExt for $client<P> {
+            type Token = P::Token;
+
+            fn authorization_request(&self) -> AuthorizationRequest {
+                let request = AuthorizationRequest::new(&self.provider, &self.client_id);
+
+                match self.redirect_uri {
+                    Some(ref redirect_uri) => request.redirect_uri(redirect_uri),
+                    None => request,
+                }
+            }
+
+            fn admin_consent_request(&self) -> Option<AdminConsentRequest> {
+                let request = AdminConsentRequest::new(&self.provider, &self.client_id)?;
+
+                match self.redirect_uri {
+                    Some(ref redirect_uri) => Some(request.redirect_uri(redirect_uri)),
+                    None => Some(request),
+                }
+            }
+
+            fn auth_uri_with_hint(
+                &self,
+                scope: Option<&str>,
+                state: Option<&str>,
+                prompt: Option<Prompt>,
+                login_hint: Option<&str>,
+            ) -> Url {
+                let mut uri = self.auth_uri(scope, state);
+
+                {
+                    let mut query = uri.query_pairs_mut();
+
+                    if let Some(prompt) = prompt {
+                        query.append_pair("prompt", prompt.as_str());
+                    }
+                    if let Some(login_hint) = login_hint {
+                        query.append_pair("login_hint", login_hint);
+                    }
+                }
+
+                uri
+            }
+
+            fn admin_consent_uri(&self, scope: Option<&str>, state: Option<&str>) -> Option<Url> {
+                let mut uri = self.provider.admin_consent_uri()?;
+
+                {
+                    let mut query = uri.query_pairs_mut();
+
+                    query.append_pair("client_id", &self.client_id);
+
+                    if let Some(ref redirect_uri) = self.redirect_uri {
+                        query.append_pair("redirect_uri", redirect_uri);
+                    }
+                    if let Some(scope) = scope {
+                        query.append_pair("scope", scope);
+                    }
+                    if let Some(state) = state {
+                        query.append_pair("state", state);
+                    }
+                }
+
+                Some(uri)
+            }
+
+            fn request_token_with_verifier(
+                &self,
+                http_client: &reqwest::Client,
+                code: &str,
+                code_verifier: &str,
+            ) -> Result<P::Token, ClientError> {
+                let json = self.redeem_authorization_code(http_client, code, Some(code_verifier))?;
+                let token = P::Token::from_response(&json)?;
+                Ok(token)
+            }
+
+            fn redeem_code(
+                &self,
+                http_client: &reqwest::Client,
+                response: &AuthorizationResponse,
+                session: &AuthorizationSession,
+            ) -> Result<P::Token, RedeemError> {
+                if response.state != session.state {
+                    return Err(RedeemError::Callback(CallbackError::State));
+                }
+
+                let code = response.code.as_ref().ok_or(CallbackError::Missing("code"))?;
+
+                let json = self.redeem_authorization_code(http_client, code, session.code_verifier.as_deref())?;
+                let token = P::Token::from_response(&json).map_err(ClientError::from)?;
+                Ok(token)
+            }
+
+            fn start_device_authorization(
+                &self,
+                http_client: &reqwest::Client,
+                scope: &str,
+            ) -> Result<DeviceAuthorization, ClientError> {
+                let uri = self.provider.device_authorization_uri().ok_or_else(unsupported_device_authorization)?;
+
+                let mut body = Serializer::new(String::new());
+                body.append_pair("scope", scope);
+
+                // The device authorization endpoint only identifies the client, never authenticates it
+                let json = post_token(http_client, &uri, &self.client_id, None, body)?;
+                let authorization = DeviceAuthorization::from_response(&json)?;
+                Ok(authorization)
+            }
+
+            fn request_token_with_password(
+                &self,
+                http_client: &reqwest::Client,
+                username: &str,
+                password: &str,
+                scope: &str,
+            ) -> Result<P::Token, PasswordGrantError> {
+                let mut body = Serializer::new(String::new());
+                body.append_pair("grant_type", "password");
+                body.append_pair("username", username);
+                body.append_pair("password", password);
+                body.append_pair("scope", scope);
+
+                let json = post_token(http_client, self.provider.token_uri(), &self.client_id, self.secret(), body)?;
+                let token = P::Token::from_response(&json).map_err(ClientError::from)?;
+                Ok(token)
+            }
+
+            fn poll_device(
+                &self,
+                http_client: &reqwest::Client,
+                poller: &mut DevicePoller,
+            ) -> Result<DevicePoll<P::Token>, ClientError> {
+                if poller.is_expired() {
+                    return Ok(DevicePoll::Expired);
+                }
+
+                let mut body = Serializer::new(String::new());
+                body.append_pair("grant_type", DEVICE_CODE_GRANT_TYPE);
+                body.append_pair("device_code", poller.device_code());
+
+                let response = post_token(http_client, self.provider.token_uri(), &self.client_id, self.secret(), body)
+                    .and_then(|json| Ok(P::Token::from_response(&json)?));
+                poller.outcome(response)
+            }
+
+            fn refresh_token_rotating<F: FnOnce(&P::Token)>(
+                &self,
+                http_client: &reqwest::Client,
+                token: P::Token,
+                scope: Option<&str>,
+                persist: F,
+            ) -> Result<P::Token, ClientError>
+            where
+                P::Token: Token<Refresh>,
+            {
+                let json = self.redeem_refresh_token(http_client, token.lifetime().refresh_token(), scope)?;
+                let refreshed = P::Token::from_response_inherit(&json, &token)?;
+                persist_rotated(&token, &refreshed, persist);
+                Ok(refreshed)
+            }
+
+            fn refresh_token_ahead<F: FnOnce(&P::Token)>(
+                &self,
+                http_client: &reqwest::Client,
+                token: Issued<P::Token>,
+                scope: Option<&str>,
+                ahead: &RefreshAhead,
+                persist: F,
+            ) -> Result<Issued<P::Token>, ClientError>
+            where
+                P::Token: Token<Refresh> + Expiry,
+            {
+                if !ahead.needs_refresh(&token) {
+                    return Ok(token);
+                }
+
+                let json = self.redeem_refresh_token(http_client, token.token().lifetime().refresh_token(), scope)?;
+                let refreshed = Issued::from_response_inherit(&json, &token)?;
+                persist_rotated(token.token(), refreshed.token(), persist);
+                Ok(refreshed)
+            }
+
+            fn poll_device_token(
+                &self,
+                http_client: &reqwest::Client,
+                authorization: &DeviceAuthorization,
+            ) -> Result<P::Token, ClientError> {
+                let mut poller = DevicePoller::new(authorization);
+
+                loop {
+                    thread::sleep(poller.interval());
+
+                    match self.poll_device(http_client, &mut poller)? {
+                        DevicePoll::Complete(token) => return Ok(token),
+                        DevicePoll::Pending | DevicePoll::SlowDown => {},
+                        DevicePoll::Declined => return Err(device_error("authorization_declined", "The user declined to sign in")),
+                        DevicePoll::Expired => return Err(device_error("expired_token", "The device code expired before the user signed in")),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl_client_ext!(Client);
+impl_client_ext!(PublicClient);
+
+/// The `grant_type` of on-behalf-of requests, whose assertion is a JWT access token.
+const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// Azure-specific helpers for confidential clients, which authenticate with a secret, so only
+/// for `inth_oauth2::Client`.
+pub trait ConfidentialClientExt {
+    /// Requests an app-only token with the client credentials grant, for daemons and
+    /// background jobs acting as themselves rather than for a user.
+    ///
+    /// `scope` must be the `.default` scope of the resource, see `scopes::default_for`: the
+    /// token carries the application permissions granted to the app on it. The token has no
+    /// refresh token, so it's an `Expiring` one whatever the provider's lifetime; request a
+    /// new one once it expires.
+    ///
+    /// See [OAuth 2.0 client credentials flow](https://learn.microsoft.com/entra/identity-platform/v2-oauth2-client-creds-grant-flow).
+    ///
+    /// ```rust,no_run
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::token::Token;
+    /// # use inth_oauth2_azure::{AzureTenant, ConfidentialClientExt, scopes};
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// let client = oauth::Client::new(
+    ///     AzureTenant::new("contoso.onmicrosoft.com"),
+    ///     "client-id".into(),
+    ///     "client-secret".into(),
+    ///     None
+    /// );
+    ///
+    /// let token = client.request_app_token(&reqwest::Client::new(), scopes::GRAPH_DEFAULT)?;
+    /// println!("{}", token.access_token());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn request_app_token(&self, http_client: &reqwest::Client, scope: &str) -> Result<Bearer<Expiring>, ClientError>;
+
+    /// Exchanges the access token a middle-tier API was called with, `assertion`, for a token
+    /// to call a downstream API such as Graph for the same user, with the on-behalf-of flow.
+    ///
+    /// The downstream token only has a refresh token when `scope` includes `offline_access`,
+    /// so the lifetime is chosen by the caller: `Expiring` unless it's requested.
+    ///
+    /// See [OAuth 2.0 on-behalf-of flow](https://learn.microsoft.com/entra/identity-platform/v2-oauth2-on-behalf-of-flow).
+    ///
+    /// ```rust,no_run
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::token::{Bearer, Expiring};
+    /// # use inth_oauth2_azure::{AzureTenant, ConfidentialClientExt, bearer_token};
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// # let authorization = "";
+    /// let client = oauth::Client::new(
+    ///     AzureTenant::new("contoso.onmicrosoft.com"),
+    ///     "client-id".into(),
+    ///     "client-secret".into(),
+    ///     None
+    /// );
+    ///
+    /// let incoming = bearer_token(authorization).unwrap();
+    /// let graph: Bearer<Expiring> = client.request_on_behalf_of(&reqwest::Client::new(), incoming, "User.Read")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn request_on_behalf_of<L: Lifetime>(
+        &self,
+        http_client: &reqwest::Client,
+        assertion: &str,
+        scope: &str,
+    ) -> Result<Bearer<L>, ClientError>;
+
+    /// Like `request_on_behalf_of`, but requests a SAML assertion for a downstream API that
+    /// only accepts SAML, such as a legacy gateway.
+    ///
+    /// ```rust,no_run
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureTenant, ConfidentialClientExt, SamlVersion};
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// # let incoming = "";
+    /// let client = oauth::Client::new(
+    ///     AzureTenant::new("contoso.onmicrosoft.com"),
+    ///     "client-id".into(),
+    ///     "client-secret".into(),
+    ///     None
+    /// );
+    ///
+    /// let saml = client.request_saml_on_behalf_of(
+    ///     &reqwest::Client::new(),
+    ///     incoming,
+    ///     "https://gateway.contoso.com/.default",
+    ///     SamlVersion::Saml2,
+    /// )?;
+    /// println!("{}", saml.xml().unwrap());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn request_saml_on_behalf_of(
+        &self,
+        http_client: &reqwest::Client,
+        assertion: &str,
+        scope: &str,
+        version: SamlVersion,
+    ) -> Result<SamlAssertion, ClientError>;
+}
+
+impl<P: AzureProvider> ConfidentialClientExt for Client<P> {
+    fn request_app_token(&self, http_client: &reqwest::Client, scope: &str) -> Result<Bearer<Expiring>, ClientError> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", "client_credentials");
+        body.append_pair("scope", scope);
+
+        let json = post_token(http_client, self.provider.token_uri(), &self.client_id, Some(&self.client_secret), body)?;
+        let token = Bearer::<Expiring>::from_response(&json)?;
+        Ok(token)
+    }
+
+    fn request_on_behalf_of<L: Lifetime>(
+        &self,
+        http_client: &reqwest::Client,
+        assertion: &str,
+        scope: &str,
+    ) -> Result<Bearer<L>, ClientError> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", JWT_BEARER_GRANT_TYPE);
+        body.append_pair("assertion", assertion);
+        body.append_pair("scope", scope);
+        body.append_pair("requested_token_use", "on_behalf_of");
+
+        let json = post_token(http_client, self.provider.token_uri(), &self.client_id, Some(&self.client_secret), body)?;
+        let token = Bearer::<L>::from_response(&json)?;
+        Ok(token)
+    }
+
+    fn request_saml_on_behalf_of(
+        &self,
+        http_client: &reqwest::Client,
+        assertion: &str,
+        scope: &str,
+        version: SamlVersion,
+    ) -> Result<SamlAssertion, ClientError> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", JWT_BEARER_GRANT_TYPE);
+        body.append_pair("assertion", assertion);
+        body.append_pair("scope", scope);
+        body.append_pair("requested_token_use", "on_behalf_of");
+        body.append_pair("requested_token_type", version.as_str());
+
+        let json = post_token(http_client, self.provider.token_uri(), &self.client_id, Some(&self.client_secret), body)?;
+        let assertion = SamlAssertion::from_response(&json)?;
+        Ok(assertion)
+    }
+}
+
+/// How a client identifies itself in token requests.
+trait Credentials {
+    /// The client's token endpoint.
+    fn token_uri(&self) -> &Url;
+
+    /// The client's application ID.
+    fn client_id(&self) -> &str;
+
+    /// The redirect URI authorization codes are sent to.
+    fn redirect_uri(&self) -> Option<&str>;
+
+    /// The secret a client authenticates with, if it has one.
+    fn secret(&self) -> Option<&str>;
+
+    /// The origin a single-page application sends requests from.
+    fn origin(&self) -> Option<&str>;
+
+    /// Redeems an authorization code, with its PKCE verifier if the request had a challenge.
+    fn redeem_authorization_code(
+        &self,
+        http_client: &reqwest::Client,
+        code: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", "authorization_code");
+        body.append_pair("code", code);
+
+        if let Some(redirect_uri) = self.redirect_uri() {
+            body.append_pair("redirect_uri", redirect_uri);
+        }
+        if let Some(code_verifier) = code_verifier {
+            body.append_pair("code_verifier", code_verifier);
+        }
+
+        post_token_from(http_client, self.token_uri(), self.client_id(), self.secret(), self.origin(), body)
+    }
+
+    /// Redeems a refresh token, optionally for a different `scope`.
+    fn redeem_refresh_token(
+        &self,
+        http_client: &reqwest::Client,
+        refresh_token: &str,
+        scope: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", "refresh_token");
+        body.append_pair("refresh_token", refresh_token);
+
+        if let Some(scope) = scope {
+            body.append_pair("scope", scope);
+        }
+
+        post_token_from(http_client, self.token_uri(), self.client_id(), self.secret(), self.origin(), body)
+    }
+}
+
+impl<P: Provider> Credentials for Client<P> {
+    fn token_uri(&self) -> &Url { self.provider.token_uri() }
+    fn client_id(&self) -> &str { &self.client_id }
+    fn redirect_uri(&self) -> Option<&str> { self.redirect_uri.as_deref() }
+    fn secret(&self) -> Option<&str> { Some(&self.client_secret) }
+    fn origin(&self) -> Option<&str> { None }
+}
+
+impl<P: Provider> Credentials for PublicClient<P> {
+    fn token_uri(&self) -> &Url { self.provider.token_uri() }
+    fn client_id(&self) -> &str { &self.client_id }
+    fn redirect_uri(&self) -> Option<&str> { self.redirect_uri.as_deref() }
+    fn secret(&self) -> Option<&str> { None }
+    fn origin(&self) -> Option<&str> { self.origin.as_deref() }
+}
+
+/// Calls `persist` with `refreshed` if its refresh token isn't the one of `previous`.
+fn persist_rotated<T: Token<Refresh>, F: FnOnce(&T)>(previous: &T, refreshed: &T, persist: F) {
+    // Responses without a refresh token inherit the previous one
+    if refreshed.lifetime().refresh_token() != previous.lifetime().refresh_token() {
+        persist(refreshed);
+    }
+}
+
+fn device_error(code: &str, description: &str) -> ClientError {
+    ClientError::OAuth2(OAuth2Error {
+        code: OAuth2ErrorCode::from(code),
+        description: Some(description.to_owned()),
+        uri: None,
+    })
+}
+
+fn unsupported_device_authorization() -> ClientError {
+    ClientError::OAuth2(OAuth2Error {
+        code: OAuth2ErrorCode::UnsupportedGrantType,
+        description: Some("The provider has no device authorization endpoint".to_owned()),
+        uri: None,
+    })
+}
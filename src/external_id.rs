@@ -0,0 +1,118 @@
+//! Microsoft Entra External ID.
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cloud::AzureCloud;
+use error::{AuthorityError, AzureTenantError};
+use provider::AzureProvider;
+use short_name;
+
+/// Customers sign in through a Microsoft Entra External ID (CIAM) tenant.
+///
+/// The tenant can be given as its short name or its `onmicrosoft.com` domain. To address the
+/// tenant by its GUID, use `with_tenant_id`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureExternalId;
+/// let provider = AzureExternalId::new("contoso");
+///
+/// assert_eq!(
+///     provider.auth_uri().as_str(),
+///     "https://contoso.ciamlogin.com/contoso.onmicrosoft.com/oauth2/v2.0/authorize"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureExternalId {
+    tenant: String,
+    auth_uri: Url,
+    token_uri: Url
+}
+
+impl AzureExternalId {
+    /// Creates a provider for an External ID tenant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tenant isn't a valid name; use `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(tenant: &str) -> Self {
+        Self::try_new(tenant).expect("invalid External ID tenant")
+    }
+
+    /// Creates a provider for an External ID tenant, checking that the tenant is a valid name.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::AzureExternalId;
+    /// assert!(AzureExternalId::try_new("contoso").is_ok());
+    /// assert!(AzureExternalId::try_new("Contoso.OnMicrosoft.com").is_ok());
+    /// assert!(AzureExternalId::try_new("contoso.evil.com/").is_err());
+    /// ```
+    pub fn try_new(tenant: &str) -> Result<Self, AuthorityError> {
+        let tenant = short_name(tenant);
+        let segment = format!("{}.onmicrosoft.com", tenant);
+
+        Self::build(&tenant, tenant.clone(), &segment)
+    }
+
+    /// Creates a provider addressing the tenant by its GUID in the path, as shown on the
+    /// app registration's endpoints page.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the subdomain or tenant ID isn't valid; use `try_with_tenant_id` when they
+    /// come from configuration or user input.
+    pub fn with_tenant_id(subdomain: &str, tenant_id: &str) -> Self {
+        Self::try_with_tenant_id(subdomain, tenant_id).expect("invalid External ID tenant")
+    }
+
+    /// Creates a provider addressing the tenant by its GUID, checking both the subdomain
+    /// and the tenant ID.
+    pub fn try_with_tenant_id(subdomain: &str, tenant_id: &str) -> Result<Self, AuthorityError> {
+        let subdomain = short_name(subdomain);
+        let tenant = tenant_id.to_lowercase();
+
+        Self::build(&subdomain, tenant.clone(), &tenant)
+    }
+
+    fn build(subdomain: &str, tenant: String, segment: &str) -> Result<Self, AuthorityError> {
+        // Both the subdomain and the tenant are DNS labels
+        for label in &[subdomain, &tenant[..]] {
+            if label.is_empty() {
+                return Err(AuthorityError::Tenant(AzureTenantError::Empty));
+            }
+            if let Some(c) = label.chars().find(|&c| !(c.is_ascii_alphanumeric() || c == '-')) {
+                return Err(AuthorityError::Tenant(AzureTenantError::InvalidCharacter(c)));
+            }
+        }
+
+        let base = format!("https://{}.ciamlogin.com/{}/oauth2/v2.0", subdomain, segment);
+        let auth_uri = Url::parse( &format!("{}/authorize", base) )?;
+        let token_uri = Url::parse( &format!("{}/token", base) )?;
+
+        Ok(Self { tenant, auth_uri, token_uri })
+    }
+
+    /// Returns the tenant name or GUID this provider was built for.
+    pub fn tenant(&self) -> &str { &self.tenant }
+}
+
+impl Provider for AzureExternalId {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.auth_uri }
+    fn token_uri(&self) -> &Url { &self.token_uri }
+}
+
+impl AzureProvider for AzureExternalId {
+    fn device_authorization_uri(&self) -> Option<Url> { self.auth_uri.join("devicecode").ok() }
+    fn end_session_uri(&self) -> Option<Url> { self.auth_uri.join("logout").ok() }
+    fn jwks_uri(&self) -> Option<Url> { self.auth_uri.join("../../discovery/v2.0/keys").ok() }
+    fn userinfo_uri(&self) -> Option<Url> { AzureCloud::Public.userinfo_endpoint() }
+    fn discovery_uri(&self) -> Option<Url> { self.auth_uri.join("../../v2.0/.well-known/openid-configuration").ok() }
+}
@@ -0,0 +1,75 @@
+//! Microsoft Graph lookups.
+
+use reqwest;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde_json::{self, Value};
+use url::Url;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::ParseError;
+use inth_oauth2::error::{OAuth2Error, OAuth2ErrorCode};
+
+/// Fetches the object IDs of the groups the signed-in user is a member of, directly or
+/// through nested groups, from Graph's `transitiveMemberOf` endpoint, for tokens with a
+/// groups overage. Like the `groups` claim, this includes nested groups.
+///
+/// `graph_endpoint` is that of the user's cloud, see `AzureCloud::graph_endpoint`, and
+/// `access_token` must be a Graph token for the user with the `GroupMember.Read.All` or
+/// `Directory.Read.All` permission. Requires the `graph` feature.
+///
+/// ```rust,no_run
+/// # extern crate reqwest;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureCloud, IdTokenClaims, fetch_groups};
+/// # fn main() -> Result<(), inth_oauth2::ClientError> {
+/// # let claims: IdTokenClaims = unimplemented!();
+/// # let graph_token = "";
+/// let groups = if claims.has_groups_overage() {
+///     let graph = AzureCloud::Public.graph_endpoint().unwrap();
+///     fetch_groups(&reqwest::Client::new(), &graph, graph_token)?
+/// } else {
+///     claims.groups.clone()
+/// };
+/// # Ok(())
+/// # }
+/// ```
+pub fn fetch_groups(
+    http_client: &reqwest::Client,
+    graph_endpoint: &Url,
+    access_token: &str,
+) -> Result<Vec<String>, ClientError> {
+    let mut next = Some(graph_endpoint.join("v1.0/me/transitiveMemberOf?$select=id&$top=999")?);
+    let mut groups = Vec::new();
+
+    // Graph pages large memberships
+    while let Some(uri) = next.take() {
+        let mut response = http_client.get(uri)
+            .header(AUTHORIZATION, format!("Bearer {}", access_token))
+            .header(ACCEPT, "application/json")
+            .send()?;
+
+        let json: Value = serde_json::from_reader(&mut response)?;
+
+        if let Some(error) = json.get("error") {
+            return Err(ClientError::from(OAuth2Error {
+                code: OAuth2ErrorCode::from(error["code"].as_str().unwrap_or_default()),
+                description: error["message"].as_str().map(str::to_owned),
+                uri: None,
+            }));
+        }
+
+        let members = json["value"].as_array().ok_or(ParseError::ExpectedFieldType("value", "array"))?;
+
+        // transitiveMemberOf also lists directory roles and administrative units
+        groups.extend(members.iter()
+            .filter(|member| member["@odata.type"] == "#microsoft.graph.group")
+            .filter_map(|member| member["id"].as_str())
+            .map(str::to_owned));
+
+        next = match json["@odata.nextLink"].as_str() {
+            Some(link) => Some(Url::parse(link)?),
+            None => None,
+        };
+    }
+
+    Ok(groups)
+}
@@ -0,0 +1,483 @@
+//! ID token claims.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+use inth_oauth2::client::response::ParseError;
+
+use directory::DirectoryRole;
+use roles::AppRole;
+use scopes::ScopeSet;
+use tenant::EndpointVersion;
+
+/// Who an access token was issued to act for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CallerKind {
+    /// The application itself, with the client credentials flow. No user is present.
+    App,
+
+    /// A signed-in user, on whose behalf the application calls.
+    User,
+}
+
+/// The claims of an Azure AD ID token.
+///
+/// See [ID token claims reference](https://learn.microsoft.com/entra/identity-platform/id-token-claims-reference).
+///
+/// v1.0 and v2.0 tokens name some claims differently; v1.0 claims are read into their v2.0
+/// counterparts, so `preferred_username` falls back to `upn` or `unique_name`, `azp` to
+/// `appid` and `azpacr` to `appidacr`.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{EndpointVersion, IdTokenClaims};
+/// # fn main() {
+/// let claims = IdTokenClaims::from_json(&json!({
+///     "ver": "2.0",
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "exp": 1536361411,
+///     "iat": 1536274711,
+///     "nbf": 1536274711,
+///     "name": "Abe Lincoln",
+///     "preferred_username": "AbeLi@microsoft.com",
+///     "oid": "00000000-0000-0000-66f3-3332eca7ea81",
+///     "tid": "9188040d-6c67-4c5b-b112-36a304b66dad",
+///     "nonce": "123523",
+///     "aio": "Df2UVXL1ix!lMCWMSOJBcFatzcGfvFGhjKv8q5g0x732dR5MB5BisvGQO7YWByjd8iQDLq!eGbIDakyp5mnOrcdqHeYSnltepQmRp6AIZ8jY",
+/// })).unwrap();
+///
+/// assert_eq!(claims.oid.unwrap(), "00000000-0000-0000-66f3-3332eca7ea81");
+/// assert!(claims.roles.is_empty());
+/// assert!(claims.additional.contains_key("aio"));
+///
+/// let v1 = IdTokenClaims::from_json(&json!({
+///     "ver": "1.0",
+///     "iss": "https://sts.windows.net/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/",
+///     "sub": "l3_roISQU222bULS9yi2k0XpqpOiMz5H3ZACo1GeXA",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "exp": 1536361411,
+///     "iat": 1536274711,
+///     "appid": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "upn": "abeli@contoso.onmicrosoft.com",
+/// })).unwrap();
+///
+/// assert_eq!(v1.version(), EndpointVersion::V1);
+/// assert_eq!(v1.preferred_username.unwrap(), "abeli@contoso.onmicrosoft.com");
+/// assert_eq!(v1.azp.unwrap(), "6cb04018-a3f5-46a7-b995-940c78f5aef3");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IdTokenClaims {
+    /// The issuer, `https://login.microsoftonline.com/{tid}/v2.0` for v2.0 tokens.
+    pub iss: String,
+
+    /// The audience: the client ID of the application the token was issued to.
+    pub aud: String,
+
+    /// A pairwise identifier of the user, unique to the application.
+    pub sub: String,
+
+    /// When the token expires, in seconds since the Unix epoch.
+    pub exp: u64,
+
+    /// When the token was issued.
+    pub iat: u64,
+
+    /// When the token becomes valid.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nbf: Option<u64>,
+
+    /// The version of the token, `1.0` or `2.0`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ver: Option<String>,
+
+    /// The user's object ID, the same across applications. Requires the `profile` scope.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub oid: Option<String>,
+
+    /// The tenant the user signed in to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tid: Option<String>,
+
+    /// Whether the user is a member (`0`) or guest (`1`) of the tenant, when configured as
+    /// an optional claim.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub acct: Option<u64>,
+
+    /// The identity provider that authenticated the user, when it isn't the issuer: their
+    /// home tenant's issuer for B2B guests, or eg. `live.com` or `google.com` for external
+    /// identities.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub idp: Option<String>,
+
+    /// The user's sign-in name. It's mutable, so mustn't be used to authorize.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub preferred_username: Option<String>,
+
+    /// The client ID of the application that requested the token, `appid` in v1.0 tokens.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub azp: Option<String>,
+
+    /// How the application that requested the token authenticated: `0` for public clients,
+    /// `1` with a client secret and `2` with a certificate. `appidacr` in v1.0 tokens.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub azpacr: Option<String>,
+
+    /// The user's display name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+
+    /// The user's email address, if the `email` scope was requested and they have one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub email: Option<String>,
+
+    /// The user principal name, in v1.0 tokens and as an optional claim.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub upn: Option<String>,
+
+    /// The `nonce` sent with the authorization request.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nonce: Option<String>,
+
+    /// When the user last authenticated.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub auth_time: Option<u64>,
+
+    /// The authentication context class: `0` if the user's authentication doesn't meet
+    /// ISO/IEC 29115 level 1, `1` otherwise, in v1.0 tokens.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub acr: Option<String>,
+
+    /// How the user authenticated, eg. `pwd` and `mfa`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub amr: Vec<String>,
+
+    /// The app roles assigned to the user.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub roles: Vec<String>,
+
+    /// The object IDs of the user's groups, when configured as an optional claim.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub groups: Vec<String>,
+
+    /// The template IDs of the directory roles assigned to the user, when configured as an
+    /// optional claim. See `DirectoryRole`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wids: Vec<String>,
+
+    /// The scopes granted, in access tokens validated with the same claims.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scp: Option<String>,
+
+    /// Whether an access token is for an `app` or a `user`, when configured as an optional
+    /// claim.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub idtyp: Option<String>,
+
+    /// Every other claim.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub additional: Map<String, Value>,
+}
+
+impl IdTokenClaims {
+    /// Returns the version of the token's format, v2.0 unless its `ver` is `1.0`.
+    pub fn version(&self) -> EndpointVersion {
+        match self.ver.as_deref() {
+            Some("1.0") => EndpointVersion::V1,
+            _ => EndpointVersion::V2,
+        }
+    }
+
+    /// Returns true if the user was assigned the app role with the value `role`.
+    ///
+    /// Prefer `has_app_role` with roles declared by `app_roles!`, which can't be misspelled.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Returns true if the user was assigned `role`.
+    pub fn has_app_role<R: AppRole>(&self, role: R) -> bool {
+        self.has_role(role.value())
+    }
+
+    /// Returns the declared app roles assigned to the user, ignoring undeclared ones.
+    pub fn app_roles<R: AppRole>(&self) -> HashSet<R> {
+        self.roles.iter().filter_map(|role| R::from_value(role)).collect()
+    }
+
+    /// Returns the listed directory roles assigned to the user, ignoring others.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{DirectoryRole, IdTokenClaims};
+    /// # fn main() {
+    /// let claims = IdTokenClaims::from_json(&json!({
+    ///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+    ///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    ///     "exp": 1536361411,
+    ///     "iat": 1536274711,
+    ///     "wids": ["fe930be7-5e62-47db-91af-98c3a49a38b1", "b79fbf4d-3ef9-4689-8143-76b194e85509"],
+    /// })).unwrap();
+    ///
+    /// let roles = claims.directory_roles();
+    /// assert!(roles.contains(&DirectoryRole::UserAdministrator));
+    /// assert_eq!(roles.len(), 1);
+    /// assert!(!claims.has_directory_role(DirectoryRole::GlobalAdministrator));
+    /// # }
+    /// ```
+    pub fn directory_roles(&self) -> HashSet<DirectoryRole> {
+        self.wids.iter().filter_map(|id| DirectoryRole::from_template_id(id)).collect()
+    }
+
+    /// Returns true if the user was assigned the directory role `role`.
+    pub fn has_directory_role(&self, role: DirectoryRole) -> bool {
+        self.wids.iter().any(|id| id.eq_ignore_ascii_case(role.template_id()))
+    }
+
+    /// Returns true if the user is a guest of the tenant rather than one of its members.
+    ///
+    /// The optional `acct` claim decides when present. Otherwise users authenticated by
+    /// another identity provider than the issuer, according to `idp`, are guests.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::IdTokenClaims;
+    /// # fn main() {
+    /// let claims = |extra: serde_json::Value| {
+    ///     let mut json = json!({
+    ///         "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///         "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+    ///         "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    ///         "exp": 1536361411,
+    ///         "iat": 1536274711,
+    ///     });
+    ///     json.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    ///     IdTokenClaims::from_json(&json).unwrap()
+    /// };
+    ///
+    /// assert!(!claims(json!({})).is_guest());
+    /// assert!(claims(json!({ "acct": 1 })).is_guest());
+    ///
+    /// let guest = claims(json!({ "idp": "https://sts.windows.net/9188040d-6c67-4c5b-b112-36a304b66dad/" }));
+    /// assert!(guest.is_guest());
+    /// assert!(claims(json!({ "idp": "live.com" })).is_guest());
+    /// # }
+    /// ```
+    pub fn is_guest(&self) -> bool {
+        match self.acct {
+            Some(acct) => acct == 1,
+            None => self.idp.as_ref().is_some_and(|idp| *idp != self.iss),
+        }
+    }
+
+    /// Returns true if the user authenticated with multiple factors, according to `amr`.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::IdTokenClaims;
+    /// # fn main() {
+    /// let claims = IdTokenClaims::from_json(&json!({
+    ///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+    ///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    ///     "exp": 1536361411,
+    ///     "iat": 1536274711,
+    ///     "amr": ["pwd", "mfa"],
+    /// })).unwrap();
+    ///
+    /// assert!(claims.has_mfa());
+    /// # }
+    /// ```
+    pub fn has_mfa(&self) -> bool {
+        // `ngcmfa` is the deprecated Windows Hello reference, which implies `mfa`
+        self.amr.iter().any(|method| method == "mfa" || method == "ngcmfa")
+    }
+
+    /// Returns the delegated permissions granted, from the `scp` claim of access tokens.
+    /// The set is empty for ID tokens and app-only tokens.
+    pub fn scopes(&self) -> ScopeSet {
+        ScopeSet::parse(self.scp.as_deref().unwrap_or_default())
+    }
+
+    /// Returns whether an access token was issued to an application acting for itself, or
+    /// for a signed-in user, so the two can't be confused by APIs that accept both.
+    ///
+    /// The optional `idtyp` claim decides when present. Otherwise only tokens with delegated
+    /// permissions in `scp` are taken to have a user, as app-only tokens carry `roles` alone.
+    /// ID tokens always identify a user, so this is meant for access tokens.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{CallerKind, IdTokenClaims};
+    /// # fn main() {
+    /// let claims = |extra: serde_json::Value| {
+    ///     let mut json = json!({
+    ///         "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///         "sub": "00000000-0000-0000-66f3-3332eca7ea81",
+    ///         "aud": "api://6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    ///         "exp": 1536361411,
+    ///         "iat": 1536274711,
+    ///     });
+    ///     json.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    ///     IdTokenClaims::from_json(&json).unwrap()
+    /// };
+    ///
+    /// assert_eq!(claims(json!({ "idtyp": "app", "roles": ["Tasks.Read.All"] })).caller_kind(), CallerKind::App);
+    /// assert_eq!(claims(json!({ "roles": ["Tasks.Read.All"] })).caller_kind(), CallerKind::App);
+    /// assert_eq!(claims(json!({ "scp": "Tasks.Read" })).caller_kind(), CallerKind::User);
+    /// # }
+    /// ```
+    pub fn caller_kind(&self) -> CallerKind {
+        match self.idtyp.as_deref() {
+            Some("app") => CallerKind::App,
+            Some("user") => CallerKind::User,
+            _ if self.scp.is_some() => CallerKind::User,
+            _ => CallerKind::App,
+        }
+    }
+
+    /// Returns true if the user is a member of more groups than fit in the token, so `groups`
+    /// is empty and they must be fetched from Graph instead.
+    ///
+    /// Azure AD then names a distributed `groups` claim in `_claim_names`, or sets `hasgroups`
+    /// in tokens from the implicit flow.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::IdTokenClaims;
+    /// # fn main() {
+    /// let claims = IdTokenClaims::from_json(&json!({
+    ///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+    ///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    ///     "exp": 1536361411,
+    ///     "iat": 1536274711,
+    ///     "_claim_names": { "groups": "src1" },
+    ///     "_claim_sources": {
+    ///         "src1": { "endpoint": "https://graph.windows.net/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/users/00000000-0000-0000-66f3-3332eca7ea81/getMemberObjects" },
+    ///     },
+    /// })).unwrap();
+    ///
+    /// assert!(claims.has_groups_overage());
+    /// assert!(claims.groups.is_empty());
+    /// # }
+    /// ```
+    pub fn has_groups_overage(&self) -> bool {
+        let distributed = self.additional.get("_claim_names")
+            .and_then(|names| names.get("groups"))
+            .is_some();
+
+        distributed || self.additional.get("hasgroups").and_then(Value::as_bool).unwrap_or(false)
+    }
+
+    /// Reads the claims from the decoded payload of an ID token.
+    pub fn from_json(json: &Value) -> Result<Self, ParseError> {
+        let mut obj = json.as_object().ok_or(ParseError::ExpectedType("object"))?.clone();
+
+        let mut string = |key: &'static str| match obj.remove(key) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            None | Some(Value::Null) => Ok(None),
+            Some(_) => Err(ParseError::ExpectedFieldType(key, "string")),
+        };
+
+        let iss = string("iss")?.ok_or(ParseError::ExpectedFieldType("iss", "string"))?;
+        let sub = string("sub")?.ok_or(ParseError::ExpectedFieldType("sub", "string"))?;
+        let ver = string("ver")?;
+        let oid = string("oid")?;
+        let tid = string("tid")?;
+        let idp = string("idp")?;
+        let upn = string("upn")?;
+        let unique_name = string("unique_name")?;
+        let preferred_username = string("preferred_username")?
+            .or_else(|| upn.clone())
+            .or(unique_name);
+        let appid = string("appid")?;
+        let azp = string("azp")?.or(appid);
+        let appidacr = string("appidacr")?;
+        let azpacr = string("azpacr")?.or(appidacr);
+        let name = string("name")?;
+        let email = string("email")?;
+        let nonce = string("nonce")?;
+        let scp = string("scp")?;
+        let idtyp = string("idtyp")?;
+        let acr = string("acr")?;
+
+        // Azure AD only issues tokens for one audience, but OpenID Connect allows an array
+        let aud = match obj.remove("aud") {
+            Some(Value::String(aud)) => aud,
+            Some(Value::Array(ref auds)) if auds.len() == 1 && auds[0].is_string() => {
+                auds[0].as_str().unwrap_or_default().to_owned()
+            },
+            _ => return Err(ParseError::ExpectedFieldType("aud", "string")),
+        };
+
+        let mut number = |key: &'static str| match obj.remove(key) {
+            Some(Value::Number(n)) => n.as_u64().map(Some).ok_or(ParseError::ExpectedFieldType(key, "number")),
+            None | Some(Value::Null) => Ok(None),
+            Some(_) => Err(ParseError::ExpectedFieldType(key, "number")),
+        };
+
+        let exp = number("exp")?.ok_or(ParseError::ExpectedFieldType("exp", "number"))?;
+        let iat = number("iat")?.ok_or(ParseError::ExpectedFieldType("iat", "number"))?;
+        let nbf = number("nbf")?;
+        let auth_time = number("auth_time")?;
+        let acct = number("acct")?;
+
+        let mut strings = |key: &'static str| match obj.remove(key) {
+            Some(Value::Array(values)) => values.into_iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s),
+                    _ => Err(ParseError::ExpectedFieldType(key, "array of strings")),
+                })
+                .collect(),
+            None | Some(Value::Null) => Ok(Vec::new()),
+            Some(_) => Err(ParseError::ExpectedFieldType(key, "array of strings")),
+        };
+
+        let roles = strings("roles")?;
+        let groups = strings("groups")?;
+        let wids = strings("wids")?;
+        let amr = strings("amr")?;
+
+        Ok(IdTokenClaims {
+            iss,
+            aud,
+            sub,
+            exp,
+            iat,
+            nbf,
+            ver,
+            oid,
+            tid,
+            acct,
+            idp,
+            preferred_username,
+            azp,
+            azpacr,
+            name,
+            email,
+            upn,
+            nonce,
+            auth_time,
+            acr,
+            amr,
+            roles,
+            groups,
+            wids,
+            scp,
+            idtyp,
+            additional: obj,
+        })
+    }
+}
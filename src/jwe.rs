@@ -0,0 +1,69 @@
+//! JSON Web Encryption.
+
+use base64;
+use openssl::encrypt::Decrypter;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Padding;
+use openssl::symm::{self, Cipher};
+use serde_json::{self, Value};
+
+use error::ValidationError;
+
+/// Returns true if `token` is a JWE compact serialization, which has five parts.
+pub(crate) fn is_encrypted(token: &str) -> bool {
+    token.split('.').count() == 5
+}
+
+/// Decrypts a JWE encrypted with `RSA-OAEP` or `RSA-OAEP-256` and `A256GCM`, returning the
+/// nested token.
+///
+/// See [RFC 7516](https://tools.ietf.org/html/rfc7516).
+pub(crate) fn decrypt(token: &str, key: &PKey<Private>) -> Result<String, ValidationError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 5 {
+        return Err(ValidationError::Malformed);
+    }
+
+    let decode = |part: &str| base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| ValidationError::Malformed);
+
+    let header: Value = serde_json::from_slice(&decode(parts[0])?).map_err(|_| ValidationError::Malformed)?;
+    let encrypted_key = decode(parts[1])?;
+    let iv = decode(parts[2])?;
+    let ciphertext = decode(parts[3])?;
+    let tag = decode(parts[4])?;
+
+    let oaep_digest = match header["alg"].as_str().unwrap_or_default() {
+        "RSA-OAEP" => MessageDigest::sha1(),
+        "RSA-OAEP-256" => MessageDigest::sha256(),
+        alg => return Err(ValidationError::Algorithm(alg.to_owned())),
+    };
+    match header["enc"].as_str().unwrap_or_default() {
+        "A256GCM" => {},
+        enc => return Err(ValidationError::Algorithm(enc.to_owned())),
+    }
+    // Compressed plaintexts aren't supported
+    if let Some(zip) = header.get("zip") {
+        return Err(ValidationError::Algorithm(zip.as_str().unwrap_or_default().to_owned()));
+    }
+
+    let mut decrypter = Decrypter::new(key).map_err(ValidationError::Key)?;
+    decrypter.set_rsa_padding(Padding::PKCS1_OAEP).map_err(ValidationError::Key)?;
+    decrypter.set_rsa_oaep_md(oaep_digest).map_err(ValidationError::Key)?;
+
+    let mut cek = vec![0; decrypter.decrypt_len(&encrypted_key).map_err(ValidationError::Key)?];
+    let len = decrypter.decrypt(&encrypted_key, &mut cek).map_err(|_| ValidationError::Decryption)?;
+    cek.truncate(len);
+
+    // OpenSSL would accept a truncated tag, weakening the authentication to as little as a
+    // byte, so only full 128-bit tags are
+    if cek.len() != 32 || iv.len() != 12 || tag.len() != 16 {
+        return Err(ValidationError::Decryption);
+    }
+
+    // The protected header, as encoded, is the additional authenticated data
+    let plaintext = symm::decrypt_aead(Cipher::aes_256_gcm(), &cek, Some(&iv), parts[0].as_bytes(), &ciphertext, &tag)
+        .map_err(|_| ValidationError::Decryption)?;
+
+    String::from_utf8(plaintext).map_err(|_| ValidationError::Malformed)
+}
@@ -0,0 +1,373 @@
+//! JSON Web Key Sets.
+
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest;
+use serde_json::Value;
+use url::Url;
+use base64;
+use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::rsa::Rsa;
+use openssl::x509::X509;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+
+use assertion::Thumbprint;
+use cache::{MemoryCache, MetadataCache};
+use refresh::{self, BackgroundRefresh};
+use request::{self, Validated};
+
+/// A public key tokens are signed with.
+///
+/// See [RFC 7517](https://tools.ietf.org/html/rfc7517).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jwk {
+    /// The key type, `RSA` for Azure AD.
+    pub kty: String,
+
+    /// The key ID, which tokens reference in their `kid` header.
+    pub kid: Option<String>,
+
+    /// What the key is for, `sig` for Azure AD.
+    pub use_: Option<String>,
+
+    /// The RSA modulus, base64url encoded.
+    pub n: Option<String>,
+
+    /// The RSA exponent, base64url encoded.
+    pub e: Option<String>,
+
+    /// The SHA-1 thumbprint of the key's certificate.
+    pub x5t: Option<String>,
+
+    /// The SHA-256 thumbprint of the key's certificate, `x5t#S256`.
+    pub x5t_s256: Option<String>,
+
+    /// The key's certificate chain, base64 DER encoded.
+    pub x5c: Vec<String>,
+
+    /// The issuer the key signs tokens for, which Azure AD includes for multi-tenant keys.
+    pub issuer: Option<String>,
+}
+
+/// The key set published at a provider's `jwks_uri`.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2::client::response::FromResponse;
+/// # use inth_oauth2_azure::Jwks;
+/// # fn main() {
+/// let jwks = Jwks::from_response(&json!({
+///     "keys": [{
+///         "kty": "RSA",
+///         "use": "sig",
+///         "kid": "nOo3ZDrODXEK1jKWhXslHR_KXEg",
+///         "n": "oaLLT9hkcSj2tGfZsjbu7Xz1Krs0qEicXPmEsJKOBQHauZ_kRM1HdEkgOJbUznUspE6xOuOSXjlzErqBxXAu4SCvcvVOCYG2v9G3-uIrLF5dstD0sYHBo1VomtKxzF90Vslrkn6rNQgUGIWgvuQTxm1uRklYFPEcTIRw0LnYknzJ06GC9ljKR617wABVrZNkBuDgQKj37qcyxoaxIGdxEcmVFZXJyrxDgdXh9owRmZn6LIJlGjZ9m59emfuwnBnsIQG7DirJwe9SXrLB6gPVzgj8GRsvURbhCAvnUSdDr2e_dtscCwHIU6RKOllqXImB5iNUqVQ0ykHhsXeVgCGyTQ",
+///         "e": "AQAB",
+///     }]
+/// })).unwrap();
+///
+/// let key = jwks.find("nOo3ZDrODXEK1jKWhXslHR_KXEg").unwrap();
+/// assert!(key.public_key().is_ok());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Jwks {
+    /// The keys.
+    pub keys: Vec<Jwk>,
+}
+
+impl Jwk {
+    /// Returns the RSA public key, from its modulus and exponent or its certificate.
+    pub fn public_key(&self) -> Result<PKey<Public>, ErrorStack> {
+        if let (Some(n), Some(e)) = (self.n.as_ref(), self.e.as_ref()) {
+            if let (Some(n), Some(e)) = (decode_uint(n), decode_uint(e)) {
+                let rsa = Rsa::from_public_components(BigNum::from_slice(&n)?, BigNum::from_slice(&e)?)?;
+                return PKey::from_rsa(rsa);
+            }
+        }
+
+        self.certificate_key()
+    }
+
+    /// Returns the public key of the key's certificate, the first of `x5c`, ignoring `n` and
+    /// `e`.
+    pub fn certificate_key(&self) -> Result<PKey<Public>, ErrorStack> {
+        let der = self.x5c.first()
+            .and_then(|cert| base64::decode(cert).ok())
+            .unwrap_or_default();
+        X509::from_der(&der)?.public_key()
+    }
+
+    /// Computes the base64url thumbprint of the key's certificate, rather than trusting its
+    /// `x5t` or `x5t#S256`. Returns `None` if the key has no certificate.
+    pub fn certificate_thumbprint(&self, thumbprint: Thumbprint) -> Option<String> {
+        let digest = match thumbprint {
+            Thumbprint::Sha1 => MessageDigest::sha1(),
+            Thumbprint::Sha256 => MessageDigest::sha256(),
+        };
+
+        let der = base64::decode(self.x5c.first()?).ok()?;
+        let hash = X509::from_der(&der).ok()?.digest(digest).ok()?;
+        Some(base64::encode_config(&hash, base64::URL_SAFE_NO_PAD))
+    }
+
+    /// Returns true if the key's certificate has the base64url `thumbprint`, computed from
+    /// `x5c`. The published `x5t` and `x5t#S256` aren't trusted.
+    pub fn has_thumbprint(&self, thumbprint: Thumbprint, value: &str) -> bool {
+        self.certificate_thumbprint(thumbprint).is_some_and(|computed| computed == value)
+    }
+}
+
+// Some providers pad base64url integers, which RFC 7518 forbids
+fn decode_uint(value: &str) -> Option<Vec<u8>> {
+    base64::decode_config(value.trim_end_matches('='), base64::URL_SAFE_NO_PAD).ok()
+}
+
+impl Jwks {
+    /// Fetches the key set from a `jwks_uri`.
+    pub fn fetch(http_client: &reqwest::Client, jwks_uri: &Url) -> Result<Self, ClientError> {
+        let json = request::get_json(http_client, jwks_uri)?;
+        Ok(Self::from_response(&json)?)
+    }
+
+    /// Returns the key with the ID `kid`.
+    pub fn find(&self, kid: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.kid.as_ref().is_some_and(|k| k == kid))
+    }
+
+    /// Returns the key whose certificate has the base64url `thumbprint`, as tokens reference
+    /// in their `x5t` or `x5t#S256` header.
+    ///
+    /// ```rust
+    /// # extern crate base64;
+    /// # extern crate openssl;
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2;
+    /// # extern crate inth_oauth2_azure;
+    /// # use openssl::asn1::Asn1Time;
+    /// # use openssl::hash::MessageDigest;
+    /// # use openssl::pkey::PKey;
+    /// # use openssl::rsa::Rsa;
+    /// # use openssl::x509::X509;
+    /// # use inth_oauth2::client::response::FromResponse;
+    /// # use inth_oauth2_azure::{Jwks, Thumbprint};
+    /// # fn main() {
+    /// # let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    /// # let mut builder = X509::builder().unwrap();
+    /// # builder.set_pubkey(&key).unwrap();
+    /// # builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    /// # builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+    /// # builder.sign(&key, MessageDigest::sha256()).unwrap();
+    /// # let certificate = builder.build();
+    /// let jwks = Jwks::from_response(&json!({
+    ///     "keys": [{ "kty": "RSA", "kid": "key-1", "x5c": [base64::encode(&certificate.to_der().unwrap())] }],
+    /// })).unwrap();
+    ///
+    /// let sha256 = base64::encode_config(&certificate.digest(MessageDigest::sha256()).unwrap(), base64::URL_SAFE_NO_PAD);
+    /// let key = jwks.find_by_thumbprint(Thumbprint::Sha256, &sha256).unwrap();
+    /// assert_eq!(key.kid.as_ref().unwrap(), "key-1");
+    /// assert_eq!(key.certificate_thumbprint(Thumbprint::Sha256).unwrap(), sha256);
+    /// # }
+    /// ```
+    pub fn find_by_thumbprint(&self, thumbprint: Thumbprint, value: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.has_thumbprint(thumbprint, value))
+    }
+}
+
+impl FromResponse for Jwks {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let keys = json.get("keys")
+            .and_then(Value::as_array)
+            .ok_or(ParseError::ExpectedFieldType("keys", "array"))?;
+
+        let keys = keys.iter()
+            .map(|key| {
+                let obj = key.as_object().ok_or(ParseError::ExpectedType("object"))?;
+                let string = |key: &str| obj.get(key).and_then(Value::as_str).map(str::to_owned);
+
+                Ok(Jwk {
+                    kty: string("kty").ok_or(ParseError::ExpectedFieldType("kty", "string"))?,
+                    kid: string("kid"),
+                    use_: string("use"),
+                    n: string("n"),
+                    e: string("e"),
+                    x5t: string("x5t"),
+                    x5t_s256: string("x5t#S256"),
+                    x5c: obj.get("x5c")
+                        .and_then(Value::as_array)
+                        .map(|certs| certs.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+                        .unwrap_or_default(),
+                    issuer: string("issuer"),
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        Ok(Jwks { keys })
+    }
+}
+
+/// How long `JwksCache` keeps keys by default. Microsoft recommends checking for new keys
+/// daily.
+pub const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The least time between two fetches of the keys to find an unknown `kid`, so tokens with
+/// made-up key IDs can't make every validation fetch them.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Caches the key set of a `jwks_uri`, refetching it once it's older than its TTL.
+///
+/// Azure AD rolls its signing keys over regularly and without notice, so a token may be
+/// signed with a key that was published after the keys were cached. `find` refetches the
+/// keys once before giving up on an unknown `kid`.
+///
+/// The keys are cached in memory unless another `MetadataCache` is given. The cache is safe
+/// to share between threads.
+pub struct JwksCache {
+    jwks_uri: Url,
+    ttl: Duration,
+    cache: Arc<dyn MetadataCache>,
+    refreshed: Mutex<Option<Instant>>,
+    fetched: Mutex<Option<Validated>>,
+}
+
+impl JwksCache {
+    /// Caches the keys at `jwks_uri` in memory for `DEFAULT_JWKS_TTL`.
+    pub fn new(jwks_uri: Url) -> Self {
+        JwksCache {
+            jwks_uri,
+            ttl: DEFAULT_JWKS_TTL,
+            cache: Arc::new(MemoryCache::new()),
+            refreshed: Mutex::new(None),
+            fetched: Mutex::new(None),
+        }
+    }
+
+    /// Sets how long the keys are cached.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Caches the keys in `cache`, keyed by the `jwks_uri`, eg. to share them between the
+    /// instances of a service.
+    pub fn with_cache(mut self, cache: Arc<dyn MetadataCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Returns the URI the keys are fetched from.
+    pub fn jwks_uri(&self) -> &Url { &self.jwks_uri }
+
+    /// Returns the key set, fetching it if it isn't cached or has expired.
+    pub fn get(&self, http_client: &reqwest::Client) -> Result<Arc<Jwks>, ClientError> {
+        // A shared cache may hold anything, so refetch what doesn't parse
+        let cached = self.cache.get(self.jwks_uri.as_str())
+            .and_then(|json| Jwks::from_response(&json).ok());
+
+        match cached {
+            Some(jwks) => Ok(Arc::new(jwks)),
+            None => self.refresh(http_client),
+        }
+    }
+
+    /// Fetches the key set, replacing the cached one.
+    ///
+    /// Once fetched, the keys are revalidated with their `ETag` and `Last-Modified`, so
+    /// they're only downloaded again when they've changed.
+    pub fn refresh(&self, http_client: &reqwest::Client) -> Result<Arc<Jwks>, ClientError> {
+        let mut fetched = self.fetched.lock().unwrap_or_else(|e| e.into_inner());
+        let validated = request::get_json_if_modified(http_client, &self.jwks_uri, fetched.as_ref())?;
+        let jwks = Jwks::from_response(&validated.json)?;
+
+        self.cache.put(self.jwks_uri.as_str(), &validated.json, self.ttl);
+        *fetched = Some(validated);
+        *self.refreshed.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        Ok(Arc::new(jwks))
+    }
+
+    /// Refreshes the key set on a background thread ahead of its expiry, so validations
+    /// needn't wait for it to be fetched. The keys are refetched after a jittered half to
+    /// three quarters of the TTL, and failed fetches are retried with a backoff.
+    ///
+    /// The thread shares this cache's store, and stops when the returned handle is dropped.
+    /// It's a plain thread rather than a tokio task, since the HTTP client blocks, so it works
+    /// with or without a runtime.
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate url;
+    /// # extern crate inth_oauth2_azure;
+    /// # use url::Url;
+    /// # use inth_oauth2_azure::JwksCache;
+    /// # fn main() -> std::io::Result<()> {
+    /// let cache = JwksCache::new(Url::parse("https://login.microsoftonline.com/common/discovery/v2.0/keys").unwrap());
+    /// let refresh = cache.refresh_in_background(reqwest::Client::new())?;
+    ///
+    /// // ... validate tokens, then on shutdown
+    /// refresh.stop();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn refresh_in_background(&self, http_client: reqwest::Client) -> io::Result<BackgroundRefresh> {
+        let cache = JwksCache {
+            jwks_uri: self.jwks_uri.clone(),
+            ttl: self.ttl,
+            cache: self.cache.clone(),
+            refreshed: Mutex::new(None),
+            fetched: Mutex::new(self.fetched.lock().unwrap_or_else(|e| e.into_inner()).clone()),
+        };
+
+        refresh::spawn(cache, http_client, self.ttl)
+    }
+
+    /// Returns the key with the ID `kid`, refetching the keys once if it isn't among the
+    /// cached ones, in case it was rolled over since.
+    pub fn find(&self, http_client: &reqwest::Client, kid: &str) -> Result<Option<Jwk>, ClientError> {
+        self.find_with(http_client, |jwks| jwks.find(kid))
+    }
+
+    /// Returns the key whose certificate has the base64url `thumbprint`, refetching the keys
+    /// once if it isn't among the cached ones.
+    pub fn find_by_thumbprint(
+        &self,
+        http_client: &reqwest::Client,
+        thumbprint: Thumbprint,
+        value: &str,
+    ) -> Result<Option<Jwk>, ClientError> {
+        self.find_with(http_client, |jwks| jwks.find_by_thumbprint(thumbprint, value))
+    }
+
+    fn find_with<F: Fn(&Jwks) -> Option<&Jwk>>(&self, http_client: &reqwest::Client, find: F) -> Result<Option<Jwk>, ClientError> {
+        let jwks = self.get(http_client)?;
+        if let Some(key) = find(&jwks) {
+            return Ok(Some(key.clone()));
+        }
+
+        let recent = self.refreshed.lock().unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|refreshed| refreshed.elapsed() < MIN_REFRESH_INTERVAL);
+        if recent {
+            return Ok(None);
+        }
+
+        let jwks = self.refresh(http_client)?;
+        Ok(find(&jwks).cloned())
+    }
+}
+
+impl fmt::Debug for JwksCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("JwksCache")
+            .field("jwks_uri", &self.jwks_uri)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
@@ -0,0 +1,63 @@
+//! JSON Web Tokens.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64;
+use openssl::sha::{sha256, sha384, sha512};
+use serde_json::{self, Value};
+
+/// A decoded, but not verified, JWS compact serialization.
+#[derive(Debug, Clone)]
+pub(crate) struct Jwt {
+    pub header: Value,
+    pub claims: Value,
+    pub signing_input: String,
+    pub signature: Vec<u8>,
+}
+
+/// Splits and decodes a JWT, returning `None` unless it has three parts and a JSON object
+/// header and claims.
+pub(crate) fn decode(token: &str) -> Option<Jwt> {
+    let mut parts = token.split('.');
+    let header = parts.next()?;
+    let claims = parts.next()?;
+    let signature = parts.next()?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Jwt {
+        header: decode_json(header)?,
+        claims: decode_json(claims)?,
+        signing_input: format!("{}.{}", header, claims),
+        signature: base64::decode_config(signature, base64::URL_SAFE_NO_PAD).ok()?,
+    })
+}
+
+fn decode_json(part: &str) -> Option<Value> {
+    let bytes = base64::decode_config(part, base64::URL_SAFE_NO_PAD).ok()?;
+    let json: Value = serde_json::from_slice(&bytes).ok()?;
+
+    if json.is_object() { Some(json) } else { None }
+}
+
+/// Computes a `c_hash` or `at_hash`: the base64url left half of the hash of `value`, using
+/// the hash function of the token's `alg`.
+///
+/// See [OpenID Connect Core 1.0, section 3.3.2.11](https://openid.net/specs/openid-connect-core-1_0.html#HybridIDToken).
+pub(crate) fn left_hash(alg: &str, value: &str) -> Option<String> {
+    let hash = match alg.get(2..) {
+        Some("256") => sha256(value.as_bytes()).to_vec(),
+        Some("384") => sha384(value.as_bytes()).to_vec(),
+        Some("512") => sha512(value.as_bytes()).to_vec(),
+        _ => return None,
+    };
+
+    Some(base64::encode_config(&hash[..hash.len() / 2], base64::URL_SAFE_NO_PAD))
+}
+
+/// Returns the current time as a `NumericDate`: seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
@@ -18,65 +18,261 @@
 //! 
 //! Azure provides multiple endpoints which can be used depending on the type of end user
 //! you're wishing to authenticate. [More info...](https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-protocols-oidc#fetch-the-openid-connect-metadata-document)
+//!
+//! # Features
+//!
+//! - `browser`: `open_in_browser` and `interactive_signin`, which signs the user in with the
+//!   system browser. Implies `loopback`.
+//! - `graph`: `fetch_groups`, which fetches the user's groups from Microsoft Graph when
+//!   they don't fit in the token.
+//! - `loopback`: `LoopbackListener`, which receives the authorization callback of native
+//!   apps on a localhost port.
+//! - `rand`: generate `state`, `nonce` and PKCE values with `rand` rather than OpenSSL.
+//! - `serde`: `Serialize` and `Deserialize` for the provider types and `AzureConfig`, so
+//!   client configuration can be loaded from a file.
+//! - `userinfo`: `fetch_userinfo`, which fetches the signed-in user's profile from the
+//!   OpenID Connect userinfo endpoint.
 
+extern crate base64;
 extern crate inth_oauth2;
+extern crate openssl;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_json;
 extern crate url;
+extern crate uuid;
 
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
 #[macro_use]
-extern crate lazy_static;
+extern crate serde_derive;
+
+mod account;
+mod adfs;
+mod assertion;
+mod authority;
+mod authorize;
+mod b2c;
+#[cfg(feature = "browser")]
+mod browser;
+mod cache;
+mod callback;
+mod claims;
+mod cloud;
+mod config;
+mod consent;
+mod custom;
+mod device;
+mod directory;
+mod discovery;
+mod endpoints;
+mod error;
+mod expiry;
+mod ext;
+mod external_id;
+#[cfg(feature = "graph")]
+mod graph;
+mod id_token;
+mod jwe;
+mod jwks;
+mod jwt;
+mod lifetime;
+mod logout;
+#[cfg(feature = "loopback")]
+mod loopback;
+mod nonce;
+mod pkce;
+mod provider;
+mod public;
+mod random;
+mod redirect;
+mod refresh;
+mod request;
+mod roles;
+mod saml;
+pub mod scopes;
+mod shared;
+mod singleflight;
+mod state;
+mod tenant;
+#[cfg(feature = "userinfo")]
+mod userinfo;
+mod v1;
+mod validator;
+
+pub use account::{Account, ClientInfo, find_account};
+pub use adfs::Adfs;
+pub use assertion::{ClientAssertion, CLIENT_ASSERTION_TYPE, Thumbprint};
+pub use authority::AzureAuthority;
+pub use authorize::{AuthorizationRequest, AuthorizationSession, DomainHint, Prompt, ResponseMode};
+pub use b2c::{AzureB2C, B2C_PASSWORD_RESET_ERROR, B2C_ROPC_POLICY, is_password_reset_error, password_reset_auth_uri};
+#[cfg(feature = "browser")]
+pub use browser::{interactive_signin, open_in_browser};
+pub use cache::{MemoryCache, MetadataCache};
+pub use callback::{AuthorizationError, AuthorizationResponse, Callback, SilentCallback, parse_callback};
+pub use claims::{Claim, ClaimsRequest, claims_challenge};
+pub use cloud::{AzureCloud, AZURE_AUTHORITY_HOST, canonical_host, canonicalize_authority, same_authority};
+pub use config::{AzureConfig, AZURE_CLIENT_ID, AZURE_CLIENT_SECRET, AZURE_REDIRECT_URI, AZURE_SCOPES, AZURE_TENANT_ID};
+pub use consent::{AdminConsent, AdminConsentCallback, AdminConsentRequest};
+pub use custom::CustomAzureProvider;
+pub use device::{DeviceAuthorization, DevicePoll, DevicePoller, DEVICE_CODE_GRANT_TYPE};
+pub use directory::DirectoryRole;
+pub use discovery::OpenIdConfiguration;
+pub use error::{AuthorityError, AzureTenantError, CallbackError, ConfigError, PasswordGrantError, RedeemError, RedirectUriError, ScopeError, StateError, ValidationError};
+pub use expiry::{DEFAULT_REFRESH_AHEAD, Expiry, Issued, RefreshAhead};
+pub use ext::{ClientExt, ConfidentialClientExt};
+pub use external_id::AzureExternalId;
+#[cfg(feature = "graph")]
+pub use graph::fetch_groups;
+pub use id_token::{CallerKind, IdTokenClaims};
+pub use jwks::{DEFAULT_JWKS_TTL, Jwk, Jwks, JwksCache};
+pub use lifetime::{AppOnly, WithLifetime};
+pub use logout::LogoutRequest;
+#[cfg(feature = "loopback")]
+pub use loopback::{DEFAULT_SIGNIN_TIMEOUT, LoopbackListener};
+pub use nonce::{MemoryNonceStore, NonceStore};
+pub use pkce::{Pkce, PKCE_METHOD};
+pub use provider::AzureProvider;
+pub use public::{PublicClient, SPA_REFRESH_TOKEN_LIFETIME};
+pub use request::TokenRequest;
+pub use random::{random_token, DEFAULT_ENTROPY, MIN_ENTROPY};
+pub use redirect::validate_redirect_uri;
+pub use refresh::BackgroundRefresh;
+pub use roles::AppRole;
+pub use saml::{SamlAssertion, SamlVersion};
+pub use shared::Shared;
+pub use singleflight::RefreshGroup;
+pub use state::StatePayload;
+pub use tenant::{AzureTenantBuilder, EndpointVersion, TenantId};
+#[cfg(feature = "userinfo")]
+pub use userinfo::{UserInfo, fetch_userinfo};
+pub use v1::AzureV1;
+pub use validator::{AccessTokenValidator, Algorithm, DEFAULT_CLOCK_SKEW, IdTokenValidator, bearer_token, dangerous_unverified_claims};
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 use url::Url;
 use inth_oauth2::provider::Provider;
 use inth_oauth2::token::{Bearer, Refresh};
 
-lazy_static! {
-    // Users with either a personal or organisation MSFT account can sign in with these
-    static ref COMMON_AUTH_URL: Url = Url::parse("https://login.microsoftonline.com/common/oauth2/v2.0/authorize").unwrap();
-    static ref COMMON_TOKEN_URL: Url = Url::parse("https://login.microsoftonline.com/common/oauth2/v2.0/token").unwrap();
+use endpoints::{Audience, well_known};
+use tenant::validate_region;
+
+/// The placeholder multi-tenant issuers use in place of the tenant ID.
+pub const TENANT_ID_TEMPLATE: &str = "{tenantid}";
+
+/// The tenant ID personal Microsoft accounts are issued tokens from.
+pub const CONSUMER_TENANT_ID: &str = "9188040d-6c67-4c5b-b112-36a304b66dad";
+
+/// Returns true if a `tid` claim identifies a personal Microsoft account rather than a
+/// work or school account.
+///
+/// ```rust
+/// # use inth_oauth2_azure::is_consumer_tenant;
+/// assert!(is_consumer_tenant("9188040D-6C67-4C5B-B112-36A304B66DAD"));
+/// assert!(!is_consumer_tenant("8eaef023-2b34-4da1-9baa-8bc8c9d6a490"));
+/// ```
+pub fn is_consumer_tenant(tid: &str) -> bool {
+    tid.eq_ignore_ascii_case(CONSUMER_TENANT_ID)
+}
 
-    // Only users with an organisation (work/school) account can sign in with these
-    static ref ORGANIZATIONS_AUTH_URL: Url = Url::parse("https://login.microsoftonline.com/organizations/oauth2/v2.0/authorize").unwrap();
-    static ref ORGANIZATIONS_TOKEN_URL: Url = Url::parse("https://login.microsoftonline.com/organizations/oauth2/v2.0/token").unwrap();
+fn is_guid(s: &str) -> bool {
+    s.len() == 36 && s.chars().enumerate().all(|(i, c)| match i {
+        8 | 13 | 18 | 23 => c == '-',
+        _ => c.is_ascii_hexdigit(),
+    })
+}
 
-    // Only users wtih a personal account can sign in with these
-    static ref CONSUMERS_AUTH_URL: Url = Url::parse("https://login.microsoftonline.com/consumers/oauth2/v2.0/authorize").unwrap();
-    static ref CONSUMERS_TOKEN_URL: Url = Url::parse("https://login.microsoftonline.com/consumers/oauth2/v2.0/token").unwrap();
+// Lowercased first, so that differently cased `onmicrosoft.com` domains are trimmed too
+fn short_name(tenant: &str) -> String {
+    let tenant = tenant.to_lowercase();
+    tenant.trim_end_matches(".onmicrosoft.com").to_owned()
 }
 
 /// Users with both a personal Microsoft account and a work or school account from Azure Active Directory (Azure AD) can sign in to the application.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AzureCommon;
 
+impl AzureCommon {
+    /// Returns a provider for the `common` endpoint of another Azure cloud.
+    pub fn in_cloud(cloud: AzureCloud) -> AzureTenant {
+        AzureTenant::new_in_cloud("common", cloud)
+    }
+
+    /// Returns the issuer of tokens from this endpoint. This is the template
+    /// `https://login.microsoftonline.com/{tenantid}/v2.0`, where `{tenantid}` stands for
+    /// the `tid` claim of each token.
+    pub fn issuer(&self) -> String {
+        AzureCloud::Public.issuer(TENANT_ID_TEMPLATE)
+    }
+}
+
 impl Provider for AzureCommon {
     type Lifetime = Refresh;
     type Token = Bearer<Self::Lifetime>;
 
-    fn auth_uri(&self) -> &Url { &COMMON_AUTH_URL }
-    fn token_uri(&self) -> &Url { &COMMON_TOKEN_URL }
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Common).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Common).token_uri }
 }
 
 /// Only users with work or school accounts from Azure AD can sign in to the application.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AzureOrganization;
 
+impl AzureOrganization {
+    /// Returns a provider for the `organizations` endpoint of another Azure cloud.
+    pub fn in_cloud(cloud: AzureCloud) -> AzureTenant {
+        AzureTenant::new_in_cloud("organizations", cloud)
+    }
+
+    /// Returns the issuer of tokens from this endpoint. This is the template
+    /// `https://login.microsoftonline.com/{tenantid}/v2.0`, where `{tenantid}` stands for
+    /// the `tid` claim of each token.
+    pub fn issuer(&self) -> String {
+        AzureCloud::Public.issuer(TENANT_ID_TEMPLATE)
+    }
+}
+
 impl Provider for AzureOrganization {
     type Lifetime = Refresh;
     type Token = Bearer<Self::Lifetime>;
 
-    fn auth_uri(&self) -> &Url { &ORGANIZATIONS_AUTH_URL }
-    fn token_uri(&self) -> &Url { &ORGANIZATIONS_TOKEN_URL }
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Organizations).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Organizations).token_uri }
 }
 
 /// Only users with a personal Microsoft account can sign in to the application.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AzureConsumer;
 
+impl AzureConsumer {
+    /// Returns a provider for the `consumers` endpoint of another Azure cloud.
+    pub fn in_cloud(cloud: AzureCloud) -> AzureTenant {
+        AzureTenant::new_in_cloud("consumers", cloud)
+    }
+
+    /// Returns the issuer of tokens from this endpoint, which is always that of the personal
+    /// account tenant.
+    pub fn issuer(&self) -> String {
+        AzureCloud::Public.issuer(CONSUMER_TENANT_ID)
+    }
+}
+
 impl Provider for AzureConsumer {
     type Lifetime = Refresh;
     type Token = Bearer<Self::Lifetime>;
 
-    fn auth_uri(&self) -> &Url { &CONSUMERS_AUTH_URL }
-    fn token_uri(&self) -> &Url { &CONSUMERS_TOKEN_URL }
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Consumers).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::Public, Audience::Consumers).token_uri }
 }
 
 /// Only users with a work or school account from a specific Azure AD tenant can
@@ -86,25 +282,319 @@ impl Provider for AzureConsumer {
 /// Eg `8eaef023-2b34-4da1-9baa-8bc8c9d6a490` or `contoso.onmicrosoft.com`
 /// 
 /// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::AzureTenant;
 /// let client = oauth::Client::new(
 ///     AzureTenant::new("8eaef023-2b34-4da1-9baa-8bc8c9d6a490"),
 ///     "client-id".into(),
 ///     "client-secret".into(),
-///     "redirect-uri".into()
+///     Some("redirect-uri".into())
 /// );
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// With the `serde` feature, providers serialize as their tenant, cloud and region rather
+/// than as URIs:
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # extern crate serde_json;
+/// # use inth_oauth2_azure::AzureTenant;
+/// # #[cfg(feature = "serde")] {
+/// let provider = AzureTenant::new("contoso.onmicrosoft.com");
+/// let json = serde_json::to_string(&provider).unwrap();
+/// assert_eq!(json, r#"{"tenant":"contoso.onmicrosoft.com"}"#);
+///
+/// let provider: AzureTenant = serde_json::from_str(r#"{"tenant":"contoso.onmicrosoft.us","cloud":"usgov"}"#).unwrap();
+///
+/// let invalid = r#"{"tenant":"contoso.onmicrosoft.com","region":"west us"}"#;
+/// assert!(serde_json::from_str::<AzureTenant>(invalid).is_err());
+/// # }
+/// ```
+///
+/// Construction is cheap enough to do per request: the endpoint URIs aren't built until
+/// they're first used, and GUID tenant identifiers don't allocate at all.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(into = "TenantRepr", try_from = "TenantRepr"))]
 pub struct AzureTenant {
-    auth_uri: Url,
-    token_uri: Url
+    tenant: TenantId,
+    cloud: AzureCloud,
+    version: EndpointVersion,
+    region: Option<String>,
+    auth_uri: OnceLock<Url>,
+    token_uri: OnceLock<Url>
 }
 
 impl AzureTenant {
+    /// Creates a provider for a tenant in the public cloud.
+    ///
+    /// The tenant identifier isn't validated, so prefer `try_new` when it comes from
+    /// configuration or user input.
     pub fn new(id: &str) -> Self {
-        let auth_uri = Url::parse( &format!("https://login.microsoftonline.com/{}/oauth2/v2.0/authorize", id) ).unwrap();
-        let token_uri = Url::parse( &format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", id) ).unwrap();
+        Self::new_in_cloud(id, AzureCloud::Public)
+    }
+
+    /// Creates a provider for a tenant in the public cloud, checking that the tenant
+    /// identifier is a plausible GUID or domain name.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenant, AzureTenantError};
+    /// assert!(AzureTenant::try_new("contoso.onmicrosoft.com").is_ok());
+    /// assert_eq!(AzureTenant::try_new("contoso/evil"), Err(AzureTenantError::InvalidCharacter('/')));
+    /// assert_eq!(AzureTenant::try_new(""), Err(AzureTenantError::Empty));
+    /// ```
+    pub fn try_new(id: &str) -> Result<Self, AzureTenantError> {
+        Self::try_new_in_cloud(id, AzureCloud::Public)
+    }
+
+    /// Creates a provider for a tenant in the given Azure cloud, checking the tenant
+    /// identifier like `try_new`.
+    pub fn try_new_in_cloud(id: &str, cloud: AzureCloud) -> Result<Self, AzureTenantError> {
+        Ok(Self::from_tenant_id(id.parse()?, cloud))
+    }
+
+    /// Creates a provider for a tenant in the given Azure cloud.
+    pub fn new_in_cloud(id: &str, cloud: AzureCloud) -> Self {
+        let tenant = TenantId::from_str(id).unwrap_or_else(|_| TenantId::Domain(id.to_owned()));
+
+        Self::from_tenant_id(tenant, cloud)
+    }
+
+    /// Creates a provider for an already validated tenant identifier.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::provider::Provider;
+    /// # use inth_oauth2_azure::{AzureCloud, AzureTenant, TenantId};
+    /// let provider = AzureTenant::from_tenant_id(TenantId::Organizations, AzureCloud::UsGov);
+    ///
+    /// assert_eq!(
+    ///     provider.auth_uri().as_str(),
+    ///     "https://login.microsoftonline.us/organizations/oauth2/v2.0/authorize"
+    /// );
+    /// ```
+    pub fn from_tenant_id(tenant: TenantId, cloud: AzureCloud) -> Self {
+        Self::build(tenant, cloud, EndpointVersion::V2, None)
+    }
+
+    /// Returns a builder for providers that need more than a tenant and cloud, such as
+    /// the v1.0 endpoints or a regional token service.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::provider::Provider;
+    /// # use inth_oauth2_azure::{AzureCloud, AzureTenant, EndpointVersion};
+    /// let provider = AzureTenant::builder()
+    ///     .tenant("contoso.onmicrosoft.us")
+    ///     .cloud(AzureCloud::UsGov)
+    ///     .version(EndpointVersion::V1)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     provider.token_uri().as_str(),
+    ///     "https://login.microsoftonline.us/contoso.onmicrosoft.us/oauth2/token"
+    /// );
+    /// ```
+    pub fn builder() -> AzureTenantBuilder {
+        AzureTenantBuilder::default()
+    }
+
+    fn build(tenant: TenantId, cloud: AzureCloud, version: EndpointVersion, region: Option<String>) -> Self {
+        Self { tenant, cloud, version, region, auth_uri: OnceLock::new(), token_uri: OnceLock::new() }
+    }
 
-        Self { auth_uri, token_uri }
+    // The URIs are derived from these, so they're all that equality and ordering need
+    fn key(&self) -> (&TenantId, &AzureCloud, EndpointVersion, &Option<String>) {
+        (&self.tenant, &self.cloud, self.version, &self.region)
+    }
+
+    /// Sends token requests to a regional token service, eg. `westus2`.
+    ///
+    /// Only the token URI is regionalised; users are still sent to the global authorize
+    /// endpoint. Regional endpoints are only available to confidential clients. The region
+    /// must be a single alphanumeric label.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::provider::Provider;
+    /// # use inth_oauth2_azure::{AzureCloud, AzureTenant, AzureTenantError};
+    /// let provider = AzureTenant::new("contoso.onmicrosoft.com").with_region("westus2").unwrap();
+    ///
+    /// assert_eq!(
+    ///     provider.auth_uri().as_str(),
+    ///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/v2.0/authorize"
+    /// );
+    /// assert_eq!(
+    ///     provider.token_uri().as_str(),
+    ///     "https://westus2.login.microsoft.com/contoso.onmicrosoft.com/oauth2/v2.0/token"
+    /// );
+    ///
+    /// // Custom clouds keep their scheme, port and path
+    /// let cloud: AzureCloud = "http://localhost:8080/aad/".parse().unwrap();
+    /// let provider = AzureTenant::new_in_cloud("contoso.onmicrosoft.com", cloud).with_region("westus2").unwrap();
+    /// assert_eq!(
+    ///     provider.token_uri().as_str(),
+    ///     "http://westus2.localhost:8080/aad/contoso.onmicrosoft.com/oauth2/v2.0/token"
+    /// );
+    ///
+    /// assert_eq!(
+    ///     AzureTenant::new("contoso.onmicrosoft.com").with_region("west us"),
+    ///     Err(AzureTenantError::InvalidCharacter(' '))
+    /// );
+    /// ```
+    pub fn with_region(self, region: &str) -> Result<Self, AzureTenantError> {
+        validate_region(region)?;
+
+        Ok(Self::build(self.tenant, self.cloud, self.version, Some(region.to_owned())))
+    }
+
+    /// Returns the tenant this provider was built for.
+    pub fn tenant_id(&self) -> &TenantId { &self.tenant }
+
+    /// Returns the issuer tokens from this tenant carry in their `iss` claim.
+    ///
+    /// Tenants identified by a domain name have a GUID issuer that can only be found through
+    /// discovery, so `None` is returned. For the `common` and `organizations` endpoints the
+    /// issuer is a template containing `{tenantid}`, as in Azure AD's discovery document.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureCloud, AzureTenant, EndpointVersion};
+    /// let provider = AzureTenant::new("8eaef023-2b34-4da1-9baa-8bc8c9d6a490");
+    /// assert_eq!(
+    ///     provider.issuer().unwrap(),
+    ///     "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0"
+    /// );
+    ///
+    /// assert_eq!(AzureTenant::new("contoso.onmicrosoft.com").issuer(), None);
+    ///
+    /// // v1.0 tokens are issued by the cloud's legacy STS host
+    /// let provider = AzureTenant::builder()
+    ///     .tenant("8eaef023-2b34-4da1-9baa-8bc8c9d6a490")
+    ///     .cloud(AzureCloud::China)
+    ///     .version(EndpointVersion::V1)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(provider.issuer().unwrap(), "https://sts.chinacloudapi.cn/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/");
+    /// assert_eq!(AzureTenant::from_issuer(&provider.issuer().unwrap()).unwrap().cloud(), &AzureCloud::China);
+    /// ```
+    pub fn issuer(&self) -> Option<String> {
+        let tenant = match self.tenant {
+            TenantId::Guid(_) => self.tenant.to_string(),
+            TenantId::Domain(_) => return None,
+            TenantId::Common | TenantId::Organizations => TENANT_ID_TEMPLATE.to_owned(),
+            TenantId::Consumers => CONSUMER_TENANT_ID.to_owned(),
+        };
+
+        match self.version {
+            EndpointVersion::V1 => Some(self.cloud.issuer_v1(&tenant)),
+            EndpointVersion::V2 => Some(self.cloud.issuer(&tenant)),
+        }
+    }
+
+    /// Returns whether the provider uses the v1.0 or v2.0 endpoints.
+    pub fn version(&self) -> EndpointVersion { self.version }
+
+    /// Returns the Azure cloud the tenant lives in.
+    pub fn cloud(&self) -> &AzureCloud { &self.cloud }
+
+    /// Returns the Azure region token requests are sent to, if one was set.
+    pub fn region(&self) -> Option<&str> { self.region.as_ref().map(|r| &r[..]) }
+
+    /// Creates a provider for the tenant that issued a token, from its `iss` claim.
+    ///
+    /// Both v2.0 (`https://login.microsoftonline.com/{tid}/v2.0`) and v1.0
+    /// (`https://sts.windows.net/{tid}/`) issuers are accepted, in any cloud.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::provider::Provider;
+    /// # use inth_oauth2_azure::AzureTenant;
+    /// let provider = AzureTenant::from_issuer(
+    ///     "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(
+    ///     provider.auth_uri().as_str(),
+    ///     "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/oauth2/v2.0/authorize"
+    /// );
+    /// assert!(AzureTenant::from_issuer("https://example.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0").is_err());
+    /// ```
+    pub fn from_issuer(issuer: &str) -> Result<Self, AuthorityError> {
+        let url = Url::parse(issuer)?;
+        let host = url.host_str().unwrap_or_default();
+
+        let cloud = match AzureCloud::from_authority_host(host)? {
+            AzureCloud::Custom(_) => return Err(AuthorityError::UnrecognizedHost(host.to_owned())),
+            cloud => cloud,
+        };
+        let tenant = url.path_segments()
+            .and_then(|mut s| s.next())
+            .and_then(|t| TenantId::from_str(t).ok())
+            .filter(TenantId::is_specific)
+            .ok_or(AuthorityError::MissingSegment("tenant"))?;
+
+        Ok(Self::from_tenant_id(tenant, cloud))
+    }
+
+    /// Creates a provider for a tenant in the cloud named by the `AZURE_AUTHORITY_HOST`
+    /// environment variable, falling back to the public cloud when it isn't set.
+    ///
+    /// This matches the behaviour of the Azure identity SDKs, so the same binary can be
+    /// pointed at a sovereign cloud without code changes.
+    pub fn from_env_authority(id: &str) -> Result<Self, url::ParseError> {
+        Ok(Self::new_in_cloud(id, AzureCloud::from_env()?))
+    }
+}
+
+/// The serialized form of `AzureTenant`, which omits the derived URIs.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct TenantRepr {
+    tenant: TenantId,
+    #[serde(default, skip_serializing_if = "is_default")]
+    cloud: AzureCloud,
+    #[serde(default, skip_serializing_if = "is_default")]
+    version: EndpointVersion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+fn is_default<T: Default + PartialEq>(value: &T) -> bool { *value == T::default() }
+
+#[cfg(feature = "serde")]
+impl From<AzureTenant> for TenantRepr {
+    fn from(provider: AzureTenant) -> Self {
+        TenantRepr {
+            tenant: provider.tenant,
+            cloud: provider.cloud,
+            version: provider.version,
+            region: provider.region,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::std::convert::TryFrom<TenantRepr> for AzureTenant {
+    type Error = AzureTenantError;
+
+    fn try_from(repr: TenantRepr) -> Result<Self, Self::Error> {
+        if let Some(ref region) = repr.region {
+            validate_region(region)?;
+        }
+
+        Ok(AzureTenant::build(repr.tenant, repr.cloud, repr.version, repr.region))
+    }
+}
+
+impl From<TenantId> for AzureTenant {
+    fn from(tenant: TenantId) -> Self {
+        Self::from_tenant_id(tenant, AzureCloud::Public)
     }
 }
 
@@ -112,6 +602,269 @@ impl Provider for AzureTenant {
     type Lifetime = Refresh;
     type Token = Bearer<Self::Lifetime>;
 
-    fn auth_uri(&self) -> &Url { &self.auth_uri }
-    fn token_uri(&self) -> &Url { &self.token_uri }
-}
\ No newline at end of file
+    fn auth_uri(&self) -> &Url {
+        self.auth_uri.get_or_init(|| {
+            self.cloud.endpoint(&self.tenant.to_string(), &self.version.oauth2_path("authorize"))
+        })
+    }
+
+    fn token_uri(&self) -> &Url {
+        self.token_uri.get_or_init(|| {
+            let path = self.version.oauth2_path("token");
+            match self.region {
+                Some(ref region) => self.cloud.regional_endpoint(region, &self.tenant.to_string(), &path),
+                None => self.cloud.endpoint(&self.tenant.to_string(), &path),
+            }
+        })
+    }
+}
+
+impl PartialEq for AzureTenant {
+    fn eq(&self, other: &Self) -> bool { self.key() == other.key() }
+}
+
+impl Eq for AzureTenant {}
+
+impl Hash for AzureTenant {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.key().hash(state) }
+}
+
+impl PartialOrd for AzureTenant {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for AzureTenant {
+    fn cmp(&self, other: &Self) -> Ordering { self.key().cmp(&other.key()) }
+}
+
+/// Users with a work or school account from any Azure AD tenant in the US Government
+/// cloud can sign in to the application.
+///
+/// This covers both GCC High and DoD, which share the `login.microsoftonline.us` authority host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGovCommon;
+
+impl Provider for AzureGovCommon {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::UsGov, Audience::Common).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::UsGov, Audience::Common).token_uri }
+}
+
+/// Only users with work or school accounts from US Government cloud Azure AD tenants
+/// can sign in to the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGovOrganization;
+
+impl Provider for AzureGovOrganization {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::UsGov, Audience::Organizations).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::UsGov, Audience::Organizations).token_uri }
+}
+
+/// Only users from a specific US Government cloud (GCC High or DoD) tenant can sign in
+/// to the application. Accepts the same tenant identifiers as `AzureTenant`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureGovTenant;
+/// let provider = AzureGovTenant::new("contoso.onmicrosoft.us");
+///
+/// assert_eq!(
+///     provider.token_uri().as_str(),
+///     "https://login.microsoftonline.us/contoso.onmicrosoft.us/oauth2/v2.0/token"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGovTenant {
+    tenant: AzureTenant
+}
+
+impl AzureGovTenant {
+    /// Creates a provider for a US Government cloud tenant.
+    ///
+    /// The tenant identifier isn't validated, so prefer `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(id: &str) -> Self {
+        Self { tenant: AzureTenant::new_in_cloud(id, AzureCloud::UsGov) }
+    }
+
+    /// Creates a provider for a US Government cloud tenant, checking the tenant identifier
+    /// like `AzureTenant::try_new`.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenantError, AzureGovTenant};
+    /// assert!(AzureGovTenant::try_new("contoso.onmicrosoft.us").is_ok());
+    /// assert_eq!(AzureGovTenant::try_new("x/../../evil?a="), Err(AzureTenantError::InvalidCharacter('/')));
+    /// ```
+    pub fn try_new(id: &str) -> Result<Self, AzureTenantError> {
+        Ok(Self { tenant: AzureTenant::try_new_in_cloud(id, AzureCloud::UsGov)? })
+    }
+}
+
+impl Provider for AzureGovTenant {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { self.tenant.auth_uri() }
+    fn token_uri(&self) -> &Url { self.tenant.token_uri() }
+}
+
+/// Users with either a personal or organisation account can sign in to the application
+/// through the Azure China (21Vianet) cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureChinaCommon;
+
+impl Provider for AzureChinaCommon {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Common).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Common).token_uri }
+}
+
+/// Only users with work or school accounts from Azure China tenants can sign in to
+/// the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureChinaOrganization;
+
+impl Provider for AzureChinaOrganization {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Organizations).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Organizations).token_uri }
+}
+
+/// Only users with a personal account can sign in to the application through the
+/// Azure China cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureChinaConsumer;
+
+impl Provider for AzureChinaConsumer {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Consumers).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::China, Audience::Consumers).token_uri }
+}
+
+/// Only users from a specific Azure China tenant can sign in to the application.
+/// Accepts the same tenant identifiers as `AzureTenant`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureChinaTenant;
+/// let provider = AzureChinaTenant::new("contoso.partner.onmschina.cn");
+///
+/// assert_eq!(
+///     provider.auth_uri().as_str(),
+///     "https://login.partner.microsoftonline.cn/contoso.partner.onmschina.cn/oauth2/v2.0/authorize"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureChinaTenant {
+    tenant: AzureTenant
+}
+
+impl AzureChinaTenant {
+    /// Creates a provider for an Azure China tenant.
+    ///
+    /// The tenant identifier isn't validated, so prefer `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(id: &str) -> Self {
+        Self { tenant: AzureTenant::new_in_cloud(id, AzureCloud::China) }
+    }
+
+    /// Creates a provider for an Azure China tenant, checking the tenant identifier like
+    /// `AzureTenant::try_new`.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenantError, AzureChinaTenant};
+    /// assert!(AzureChinaTenant::try_new("contoso.partner.onmschina.cn").is_ok());
+    /// assert_eq!(AzureChinaTenant::try_new("x/../../evil?a="), Err(AzureTenantError::InvalidCharacter('/')));
+    /// ```
+    pub fn try_new(id: &str) -> Result<Self, AzureTenantError> {
+        Ok(Self { tenant: AzureTenant::try_new_in_cloud(id, AzureCloud::China)? })
+    }
+}
+
+impl Provider for AzureChinaTenant {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { self.tenant.auth_uri() }
+    fn token_uri(&self) -> &Url { self.tenant.token_uri() }
+}
+
+/// Users with a work or school account from any Microsoft Cloud Deutschland tenant can
+/// sign in to the application.
+///
+/// This is the legacy German sovereign cloud; new deployments should use the public cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGermanyCommon;
+
+impl Provider for AzureGermanyCommon {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::Germany, Audience::Common).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::Germany, Audience::Common).token_uri }
+}
+
+/// Only users with work or school accounts from Microsoft Cloud Deutschland tenants can
+/// sign in to the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGermanyOrganization;
+
+impl Provider for AzureGermanyOrganization {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &well_known(&AzureCloud::Germany, Audience::Organizations).auth_uri }
+    fn token_uri(&self) -> &Url { &well_known(&AzureCloud::Germany, Audience::Organizations).token_uri }
+}
+
+/// Only users from a specific Microsoft Cloud Deutschland tenant can sign in to the
+/// application. Accepts the same tenant identifiers as `AzureTenant`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureGermanyTenant {
+    tenant: AzureTenant
+}
+
+impl AzureGermanyTenant {
+    /// Creates a provider for a Microsoft Cloud Deutschland tenant.
+    ///
+    /// The tenant identifier isn't validated, so prefer `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(id: &str) -> Self {
+        Self { tenant: AzureTenant::new_in_cloud(id, AzureCloud::Germany) }
+    }
+
+    /// Creates a provider for a Microsoft Cloud Deutschland tenant, checking the tenant
+    /// identifier like `AzureTenant::try_new`.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenantError, AzureGermanyTenant};
+    /// assert!(AzureGermanyTenant::try_new("contoso.onmicrosoft.de").is_ok());
+    /// assert_eq!(AzureGermanyTenant::try_new("x/../../evil?a="), Err(AzureTenantError::InvalidCharacter('/')));
+    /// ```
+    pub fn try_new(id: &str) -> Result<Self, AzureTenantError> {
+        Ok(Self { tenant: AzureTenant::try_new_in_cloud(id, AzureCloud::Germany)? })
+    }
+}
+
+impl Provider for AzureGermanyTenant {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { self.tenant.auth_uri() }
+    fn token_uri(&self) -> &Url { self.tenant.token_uri() }
+}
@@ -0,0 +1,75 @@
+//! Token lifetimes.
+
+use std::marker::PhantomData;
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Expiring, Lifetime};
+
+use provider::AzureProvider;
+
+/// Wraps a provider to change the lifetime of the tokens it issues.
+///
+/// Every provider in this crate expects refresh tokens, but tokens from the client credentials
+/// grant, and from some B2C user flows, don't include one and fail to parse as
+/// `Bearer<Refresh>`. Wrapping the provider lets the same endpoints be used with `Expiring`
+/// or `Static` tokens instead.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::Client;
+/// # use inth_oauth2_azure::{AppOnly, AzureTenant};
+/// let client: Client<AppOnly<AzureTenant>> = Client::new(
+///     AppOnly::new(AzureTenant::new("contoso.onmicrosoft.com")),
+///     "client-id".into(),
+///     "client-secret".into(),
+///     None
+/// );
+///
+/// assert_eq!(client.provider.provider().tenant_id().to_string(), "contoso.onmicrosoft.com");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithLifetime<P, L> {
+    provider: P,
+    lifetime: PhantomData<L>
+}
+
+/// A provider issuing app-only tokens, which expire but can't be refreshed.
+pub type AppOnly<P> = WithLifetime<P, Expiring>;
+
+impl<P: Provider, L: Lifetime> WithLifetime<P, L> {
+    pub fn new(provider: P) -> Self {
+        Self { provider, lifetime: PhantomData }
+    }
+
+    /// Returns the wrapped provider.
+    pub fn provider(&self) -> &P { &self.provider }
+
+    /// Unwraps the provider.
+    pub fn into_inner(self) -> P { self.provider }
+}
+
+impl<P: Provider, L: Lifetime> From<P> for WithLifetime<P, L> {
+    fn from(provider: P) -> Self {
+        Self::new(provider)
+    }
+}
+
+impl<P: Provider, L: Lifetime> Provider for WithLifetime<P, L> {
+    type Lifetime = L;
+    type Token = Bearer<L>;
+
+    fn auth_uri(&self) -> &Url { self.provider.auth_uri() }
+    fn token_uri(&self) -> &Url { self.provider.token_uri() }
+    fn credentials_in_body(&self) -> bool { self.provider.credentials_in_body() }
+}
+
+impl<P: AzureProvider, L: Lifetime> AzureProvider for WithLifetime<P, L> {
+    fn device_authorization_uri(&self) -> Option<Url> { self.provider.device_authorization_uri() }
+    fn end_session_uri(&self) -> Option<Url> { self.provider.end_session_uri() }
+    fn jwks_uri(&self) -> Option<Url> { self.provider.jwks_uri() }
+    fn userinfo_uri(&self) -> Option<Url> { self.provider.userinfo_uri() }
+    fn admin_consent_uri(&self) -> Option<Url> { self.provider.admin_consent_uri() }
+    fn discovery_uri(&self) -> Option<Url> { self.provider.discovery_uri() }
+}
@@ -0,0 +1,94 @@
+//! Sign-out requests.
+
+use url::Url;
+
+use provider::AzureProvider;
+
+/// Builds the end session endpoint URI that signs the user out of Azure AD.
+///
+/// See [OpenID Connect RP-Initiated Logout 1.0](https://openid.net/specs/openid-connect-rpinitiated-1_0.html).
+///
+/// ```rust
+/// # use inth_oauth2_azure::{AzureOrganization, LogoutRequest};
+/// let uri = LogoutRequest::new(&AzureOrganization)
+///     .unwrap()
+///     .id_token_hint("eyJ0eXAiOiJKV1Qi...")
+///     .post_logout_redirect_uri("https://localhost/signed-out")
+///     .build();
+///
+/// assert_eq!(
+///     uri.as_str(),
+///     "https://login.microsoftonline.com/organizations/oauth2/v2.0/logout?id_token_hint=eyJ0eXAiOiJKV1Qi...&post_logout_redirect_uri=https%3A%2F%2Flocalhost%2Fsigned-out"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogoutRequest {
+    end_session_uri: Url,
+    id_token_hint: Option<String>,
+    logout_hint: Option<String>,
+    post_logout_redirect_uri: Option<String>,
+    state: Option<String>,
+}
+
+impl LogoutRequest {
+    /// Starts a request to the provider's end session endpoint, or returns `None` if it has
+    /// none.
+    pub fn new<P: AzureProvider>(provider: &P) -> Option<Self> {
+        Some(LogoutRequest {
+            end_session_uri: provider.end_session_uri()?,
+            id_token_hint: None,
+            logout_hint: None,
+            post_logout_redirect_uri: None,
+            state: None,
+        })
+    }
+
+    /// Identifies the session to end with the ID token it issued, so the user isn't asked
+    /// which account to sign out. B2C requires one to redirect after sign-out.
+    pub fn id_token_hint(mut self, id_token: &str) -> Self {
+        self.id_token_hint = Some(id_token.to_owned());
+        self
+    }
+
+    /// Identifies the account to sign out with the ID token's `login_hint` claim.
+    pub fn logout_hint(mut self, logout_hint: &str) -> Self {
+        self.logout_hint = Some(logout_hint.to_owned());
+        self
+    }
+
+    /// Sets where the user is sent after signing out, as registered for the application.
+    pub fn post_logout_redirect_uri(mut self, uri: &str) -> Self {
+        self.post_logout_redirect_uri = Some(uri.to_owned());
+        self
+    }
+
+    /// Sets a `state` to be returned to the post logout redirect URI.
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_owned());
+        self
+    }
+
+    /// Returns the end session URI to direct the user to.
+    pub fn build(self) -> Url {
+        let mut uri = self.end_session_uri;
+
+        {
+            let mut query = uri.query_pairs_mut();
+
+            if let Some(ref id_token_hint) = self.id_token_hint {
+                query.append_pair("id_token_hint", id_token_hint);
+            }
+            if let Some(ref logout_hint) = self.logout_hint {
+                query.append_pair("logout_hint", logout_hint);
+            }
+            if let Some(ref redirect_uri) = self.post_logout_redirect_uri {
+                query.append_pair("post_logout_redirect_uri", redirect_uri);
+            }
+            if let Some(ref state) = self.state {
+                query.append_pair("state", state);
+            }
+        }
+
+        uri
+    }
+}
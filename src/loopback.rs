@@ -0,0 +1,214 @@
+//! Loopback redirects for native apps.
+
+use std::cmp;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::ParseError;
+
+use callback::Callback;
+
+/// How long `LoopbackListener::accept` waits for the user to sign in, five minutes.
+pub const DEFAULT_SIGNIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait for the browser to send its request once connected.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check for a connection while waiting for the callback.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The longest request line or header accepted, in bytes.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// The largest `form_post` body accepted, in bytes.
+const MAX_BODY: usize = 64 * 1024;
+
+const RESPONSE_BODY: &str = "<!DOCTYPE html><html><body>Sign-in complete. You can close this window.</body></html>";
+
+/// Receives the authorization callback of a desktop or command line app on an ephemeral
+/// port of the IPv4 loopback address.
+///
+/// The app registration must include `http://127.0.0.1` as a redirect URI of the "Mobile
+/// and desktop applications" platform. Azure AD ignores the port of loopback redirect URIs,
+/// so any free port works. The redirect URI names the address rather than `localhost`,
+/// which some browsers resolve to `::1` first, where nothing listens.
+///
+/// Requires the `loopback` feature.
+///
+/// ```rust,no_run
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AuthorizationRequest, AzureCommon, Callback, LoopbackListener, Pkce};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = LoopbackListener::bind()?;
+///
+/// let (uri, session) = AuthorizationRequest::new(&AzureCommon, "client-id")
+///     .redirect_uri(listener.redirect_uri())
+///     .scope("openid offline_access")
+///     .pkce(Pkce::new())
+///     .build();
+/// println!("Sign in at {}", uri);
+///
+/// match listener.accept()? {
+///     Callback::Success(ref response) if response.state == session.state => { /* redeem the code */ },
+///     Callback::Success(_) => { /* unsolicited */ },
+///     Callback::Error(err) => return Err(err.into()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LoopbackListener {
+    listener: TcpListener,
+    redirect_uri: String,
+}
+
+impl LoopbackListener {
+    /// Listens on an ephemeral port of the IPv4 loopback address.
+    pub fn bind() -> io::Result<Self> {
+        Self::bind_port(0)
+    }
+
+    /// Listens on a specific port of the IPv4 loopback address.
+    pub fn bind_port(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let redirect_uri = format!("http://{}/", listener.local_addr()?);
+
+        Ok(LoopbackListener { listener, redirect_uri })
+    }
+
+    /// Returns the redirect URI to send with the authorization request.
+    pub fn redirect_uri(&self) -> &str { &self.redirect_uri }
+
+    /// Waits for the callback, responds to the browser, and parses the callback's query
+    /// string, or body with `response_mode=form_post`. Requests for other paths, such as the
+    /// browser's favicon, are answered with a 404.
+    ///
+    /// Connections that fail, such as preconnected sockets the browser never sends a request
+    /// on, are dropped and the listener keeps waiting for the callback. Gives up with an
+    /// `io::ErrorKind::TimedOut` error after `DEFAULT_SIGNIN_TIMEOUT`.
+    pub fn accept(self) -> Result<Callback, ClientError> {
+        self.accept_within(DEFAULT_SIGNIN_TIMEOUT)
+    }
+
+    /// Like `accept`, but gives up after `timeout` instead.
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2;
+    /// # extern crate inth_oauth2_azure;
+    /// # use std::io;
+    /// # use std::time::Duration;
+    /// # use inth_oauth2::ClientError;
+    /// # use inth_oauth2_azure::LoopbackListener;
+    /// # fn main() -> io::Result<()> {
+    /// let listener = LoopbackListener::bind()?;
+    /// assert!(listener.redirect_uri().starts_with("http://127.0.0.1:"));
+    ///
+    /// match listener.accept_within(Duration::from_millis(100)) {
+    ///     Err(ClientError::Io(ref err)) if err.kind() == io::ErrorKind::TimedOut => {},
+    ///     other => panic!("{:?}", other),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn accept_within(self, timeout: Duration) -> Result<Callback, ClientError> {
+        let deadline = Instant::now() + timeout;
+
+        // std can't time out a blocking accept, so the listener is polled
+        self.listener.set_nonblocking(true)?;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no authorization callback before the deadline").into());
+            }
+
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(cmp::min(POLL_INTERVAL, remaining));
+                    continue;
+                },
+                Err(err) => return Err(err.into()),
+            };
+
+            // A failed connection says nothing about the callback, which may still arrive
+            if let Ok(Some(callback)) = handle(stream, cmp::min(READ_TIMEOUT, remaining)) {
+                return Ok(callback?);
+            }
+        }
+    }
+}
+
+/// Reads one HTTP request, returning its parameters if it was for the redirect URI.
+fn handle(mut stream: TcpStream, read_timeout: Duration) -> io::Result<Option<Result<Callback, ParseError>>> {
+    // Accepted sockets inherit the listener's non-blocking mode on some platforms
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(read_timeout))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let target = parts.next().unwrap_or_default().to_owned();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if read_line(&mut reader, &mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+
+        let mut header = header.splitn(2, ':');
+        let name = header.next().unwrap_or_default();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = header.next().and_then(|v| v.trim().parse().ok()).unwrap_or(0);
+        }
+    }
+
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (&target[..], ""),
+    };
+
+    if path != "/" {
+        stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        return Ok(None);
+    }
+
+    if content_length > MAX_BODY {
+        stream.write_all(b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        return Ok(None);
+    }
+
+    let params = if method == "POST" {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        String::from_utf8_lossy(&body).into_owned()
+    } else {
+        query.to_owned()
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        RESPONSE_BODY.len(),
+        RESPONSE_BODY,
+    )?;
+
+    Ok(Some(Callback::from_form(&params)))
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, failing on longer ones.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    let read = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long"));
+    }
+
+    Ok(read)
+}
@@ -0,0 +1,91 @@
+//! Nonce storage.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use authorize::AuthorizationSession;
+
+/// How long `MemoryNonceStore` keeps a nonce by default.
+const DEFAULT_NONCE_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// Keeps the `nonce` of each authorization request across the redirect, keyed by its
+/// `state`, so the ID token of the callback can be validated against it.
+///
+/// Web apps with several instances should implement this with their session store or a
+/// shared cache; `MemoryNonceStore` serves a single process.
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AuthorizationRequest, AzureCommon, MemoryNonceStore, NonceStore};
+/// # fn main() {
+/// let store = MemoryNonceStore::new();
+///
+/// let (uri, session) = AuthorizationRequest::new(&AzureCommon, "client-id")
+///     .scope("openid")
+///     .build();
+/// store.save(&session);
+///
+/// // ...and on the callback, with the `state` it echoes
+/// let nonce = store.take(&session.state).unwrap();
+/// assert_eq!(Some(nonce), session.nonce);
+///
+/// // A nonce can only be used once
+/// assert_eq!(store.take(&session.state), None);
+/// # }
+/// ```
+pub trait NonceStore {
+    /// Stores the `nonce` of the request with the given `state`.
+    fn insert(&self, state: &str, nonce: &str);
+
+    /// Removes and returns the `nonce` of the request with the given `state`.
+    fn take(&self, state: &str) -> Option<String>;
+
+    /// Stores the `nonce` of an authorization request's session, if it has one.
+    fn save(&self, session: &AuthorizationSession) {
+        if let Some(ref nonce) = session.nonce {
+            self.insert(&session.state, nonce);
+        }
+    }
+}
+
+/// A `NonceStore` in memory, which forgets nonces after a lifetime so abandoned sign-ins
+/// don't accumulate.
+#[derive(Debug)]
+pub struct MemoryNonceStore {
+    lifetime: Duration,
+    nonces: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl MemoryNonceStore {
+    /// Creates a store that keeps nonces for 10 minutes.
+    pub fn new() -> Self {
+        MemoryNonceStore { lifetime: DEFAULT_NONCE_LIFETIME, nonces: Mutex::new(HashMap::new()) }
+    }
+
+    /// Sets how long nonces are kept.
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+}
+
+impl Default for MemoryNonceStore {
+    fn default() -> Self { Self::new() }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn insert(&self, state: &str, nonce: &str) {
+        let mut nonces = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        let lifetime = self.lifetime;
+        nonces.retain(|_, &mut (_, stored)| stored.elapsed() < lifetime);
+        nonces.insert(state.to_owned(), (nonce.to_owned(), Instant::now()));
+    }
+
+    fn take(&self, state: &str) -> Option<String> {
+        let mut nonces = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        nonces.remove(state)
+            .filter(|&(_, stored)| stored.elapsed() < self.lifetime)
+            .map(|(nonce, _)| nonce)
+    }
+}
@@ -0,0 +1,58 @@
+//! Proof Key for Code Exchange.
+
+use base64;
+use openssl::sha::sha256;
+
+use random::random_token;
+
+/// The only code challenge method Azure AD recommends.
+pub const PKCE_METHOD: &str = "S256";
+
+/// A PKCE code verifier and its S256 code challenge.
+///
+/// The challenge is sent with the authorization request and the verifier with the token
+/// request, proving both came from the same client. Azure AD requires PKCE for public
+/// clients and single-page apps.
+///
+/// See [RFC 7636](https://tools.ietf.org/html/rfc7636).
+///
+/// ```rust
+/// # use inth_oauth2_azure::Pkce;
+/// // The example from RFC 7636, appendix B
+/// let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+/// assert_eq!(pkce.challenge(), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+///
+/// assert_eq!(Pkce::new().verifier().len(), 43);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pkce {
+    verifier: String,
+    challenge: String
+}
+
+impl Pkce {
+    /// Generates a verifier from 32 random bytes.
+    pub fn new() -> Self {
+        Self::from_verifier(&random_token(32))
+    }
+
+    /// Uses an existing verifier, eg. one restored from the user's session.
+    pub fn from_verifier(verifier: &str) -> Self {
+        let challenge = base64::encode_config(&sha256(verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+
+        Pkce { verifier: verifier.to_owned(), challenge }
+    }
+
+    /// Returns the `code_verifier` for the token request.
+    pub fn verifier(&self) -> &str { &self.verifier }
+
+    /// Returns the `code_challenge` for the authorization request.
+    pub fn challenge(&self) -> &str { &self.challenge }
+}
+
+impl Default for Pkce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
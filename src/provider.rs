@@ -0,0 +1,153 @@
+//! Endpoints beyond authorize and token.
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+
+use cloud::AzureCloud;
+use tenant::{EndpointVersion, TenantId};
+use {AzureCommon, AzureConsumer, AzureOrganization, AzureTenant};
+use {AzureGovCommon, AzureGovOrganization, AzureGovTenant};
+use {AzureChinaCommon, AzureChinaConsumer, AzureChinaOrganization, AzureChinaTenant};
+use {AzureGermanyCommon, AzureGermanyOrganization, AzureGermanyTenant};
+
+/// The OpenID Connect endpoints an Azure authority exposes besides authorize and token.
+///
+/// Endpoints the authority doesn't support are `None`, eg. B2C has no device
+/// authorization endpoint.
+///
+/// ```rust
+/// # use inth_oauth2_azure::{AzureProvider, AzureTenant};
+/// let provider = AzureTenant::new("contoso.onmicrosoft.com");
+///
+/// assert_eq!(
+///     provider.jwks_uri().unwrap().as_str(),
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/discovery/v2.0/keys"
+/// );
+/// assert_eq!(provider.userinfo_uri().unwrap().as_str(), "https://graph.microsoft.com/oidc/userinfo");
+/// assert_eq!(
+///     provider.discovery_uri().unwrap().as_str(),
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/v2.0/.well-known/openid-configuration"
+/// );
+/// ```
+pub trait AzureProvider: Provider {
+    /// The device authorization endpoint URI.
+    ///
+    /// See [RFC 8628, section 3.1](https://tools.ietf.org/html/rfc8628#section-3.1).
+    fn device_authorization_uri(&self) -> Option<Url>;
+
+    /// The end session (logout) endpoint URI.
+    fn end_session_uri(&self) -> Option<Url>;
+
+    /// The URI of the JSON Web Key Set tokens are signed with.
+    fn jwks_uri(&self) -> Option<Url>;
+
+    /// The OpenID Connect userinfo endpoint URI.
+    fn userinfo_uri(&self) -> Option<Url>;
+
+    /// The admin consent endpoint URI, where an administrator grants an application's
+    /// permissions on behalf of their whole organization.
+    fn admin_consent_uri(&self) -> Option<Url> { None }
+
+    /// The URI of the authority's OpenID Connect discovery document.
+    ///
+    /// See [OpenID Connect Discovery, section 4](https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfig).
+    ///
+    /// Wrapped providers forward it:
+    ///
+    /// ```rust
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AppOnly, AzureProvider, AzureTenant, Shared};
+    /// let provider = AzureTenant::new("contoso.onmicrosoft.com");
+    /// let uri = provider.discovery_uri();
+    /// assert!(uri.is_some());
+    ///
+    /// assert_eq!((&provider).discovery_uri(), uri);
+    /// assert_eq!(Box::new(provider.clone()).discovery_uri(), uri);
+    /// assert_eq!(AppOnly::new(provider.clone()).discovery_uri(), uri);
+    /// assert_eq!(Shared::new(provider).discovery_uri(), uri);
+    /// ```
+    fn discovery_uri(&self) -> Option<Url> { None }
+}
+
+pub(crate) const DEVICE_CODE_PATH: &str = "oauth2/v2.0/devicecode";
+pub(crate) const LOGOUT_PATH: &str = "oauth2/v2.0/logout";
+pub(crate) const JWKS_PATH: &str = "discovery/v2.0/keys";
+pub(crate) const DISCOVERY_PATH: &str = "v2.0/.well-known/openid-configuration";
+const ADMIN_CONSENT_PATH: &str = "v2.0/adminconsent";
+
+// Personal accounts have no administrator to consent
+fn admin_consent_endpoint(cloud: &AzureCloud, tenant: &str) -> Option<Url> {
+    match tenant {
+        "consumers" => None,
+        _ => Some(cloud.endpoint(tenant, ADMIN_CONSENT_PATH)),
+    }
+}
+
+impl AzureProvider for AzureTenant {
+    fn device_authorization_uri(&self) -> Option<Url> {
+        Some(self.cloud().endpoint(&self.tenant_id().to_string(), &self.version().oauth2_path("devicecode")))
+    }
+
+    fn end_session_uri(&self) -> Option<Url> {
+        Some(self.cloud().endpoint(&self.tenant_id().to_string(), &self.version().oauth2_path("logout")))
+    }
+
+    fn jwks_uri(&self) -> Option<Url> {
+        Some(self.cloud().endpoint(&self.tenant_id().to_string(), self.version().jwks_path()))
+    }
+
+    fn userinfo_uri(&self) -> Option<Url> { self.cloud().userinfo_endpoint() }
+
+    fn admin_consent_uri(&self) -> Option<Url> {
+        // The v1.0 endpoints take prompt=admin_consent on the authorize endpoint instead
+        match (self.tenant_id(), self.version()) {
+            (&TenantId::Consumers, _) | (_, EndpointVersion::V1) => None,
+            (tenant, EndpointVersion::V2) => Some(self.cloud().endpoint(&tenant.to_string(), ADMIN_CONSENT_PATH)),
+        }
+    }
+
+    fn discovery_uri(&self) -> Option<Url> {
+        Some(self.cloud().endpoint(&self.tenant_id().to_string(), self.version().discovery_path()))
+    }
+}
+
+macro_rules! impl_endpoints {
+    ($t:ty, $cloud:expr, $tenant:expr) => {
+        impl AzureProvider for $t {
+            fn device_authorization_uri(&self) -> Option<Url> { Some($cloud.endpoint($tenant, DEVICE_CODE_PATH)) }
+            fn end_session_uri(&self) -> Option<Url> { Some($cloud.endpoint($tenant, LOGOUT_PATH)) }
+            fn jwks_uri(&self) -> Option<Url> { Some($cloud.endpoint($tenant, JWKS_PATH)) }
+            fn userinfo_uri(&self) -> Option<Url> { $cloud.userinfo_endpoint() }
+            fn admin_consent_uri(&self) -> Option<Url> { admin_consent_endpoint(&$cloud, $tenant) }
+            fn discovery_uri(&self) -> Option<Url> { Some($cloud.endpoint($tenant, DISCOVERY_PATH)) }
+        }
+    }
+}
+
+impl_endpoints!(AzureCommon, AzureCloud::Public, "common");
+impl_endpoints!(AzureOrganization, AzureCloud::Public, "organizations");
+impl_endpoints!(AzureConsumer, AzureCloud::Public, "consumers");
+impl_endpoints!(AzureGovCommon, AzureCloud::UsGov, "common");
+impl_endpoints!(AzureGovOrganization, AzureCloud::UsGov, "organizations");
+impl_endpoints!(AzureChinaCommon, AzureCloud::China, "common");
+impl_endpoints!(AzureChinaOrganization, AzureCloud::China, "organizations");
+impl_endpoints!(AzureChinaConsumer, AzureCloud::China, "consumers");
+impl_endpoints!(AzureGermanyCommon, AzureCloud::Germany, "common");
+impl_endpoints!(AzureGermanyOrganization, AzureCloud::Germany, "organizations");
+
+macro_rules! delegate_endpoints {
+    ($t:ty) => {
+        impl AzureProvider for $t {
+            fn device_authorization_uri(&self) -> Option<Url> { self.tenant.device_authorization_uri() }
+            fn end_session_uri(&self) -> Option<Url> { self.tenant.end_session_uri() }
+            fn jwks_uri(&self) -> Option<Url> { self.tenant.jwks_uri() }
+            fn userinfo_uri(&self) -> Option<Url> { self.tenant.userinfo_uri() }
+            fn admin_consent_uri(&self) -> Option<Url> { self.tenant.admin_consent_uri() }
+            fn discovery_uri(&self) -> Option<Url> { self.tenant.discovery_uri() }
+        }
+    }
+}
+
+delegate_endpoints!(AzureGovTenant);
+delegate_endpoints!(AzureChinaTenant);
+delegate_endpoints!(AzureGermanyTenant);
@@ -0,0 +1,221 @@
+//! Public clients.
+
+use std::time::Duration;
+
+use reqwest;
+use serde_json::Value;
+use url::Url;
+use url::form_urlencoded::Serializer;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::FromResponse;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Lifetime, Refresh, Token};
+
+use request::{post_token_from, TokenRequest};
+
+/// How long Azure AD's refresh tokens for single-page applications last. They can't be
+/// renewed past this, so the user must then sign in again.
+pub const SPA_REFRESH_TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An OAuth 2.0 client for applications registered as public clients, such as desktop,
+/// mobile and command line apps, which can't keep a secret.
+///
+/// `inth_oauth2::Client` always authenticates with a client secret, and Azure AD rejects
+/// token requests from public clients that include one. `PublicClient` mirrors its API but
+/// only sends the client ID. Authorization codes should be protected with PKCE instead.
+///
+/// ```rust
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureCommon, PublicClient};
+/// let client = PublicClient::new(
+///     AzureCommon,
+///     "client-id".into(),
+///     Some("http://localhost:8400".into())
+/// );
+///
+/// let uri = client.auth_uri(Some("openid offline_access"), Some("state"));
+/// assert!(uri.query().unwrap().contains("client_id=client-id"));
+///
+/// let client = client.spa("http://localhost:8400");
+/// assert_eq!(client.refresh_token_lifetime().unwrap().as_secs(), 86400);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicClient<P> {
+    /// OAuth provider.
+    pub provider: P,
+
+    /// Client ID.
+    pub client_id: String,
+
+    /// Redirect URI.
+    pub redirect_uri: Option<String>,
+
+    /// The origin token requests are sent from, for single-page applications.
+    pub origin: Option<String>,
+}
+
+impl<P: Provider> PublicClient<P> {
+    pub fn new(provider: P, client_id: String, redirect_uri: Option<String>) -> Self {
+        PublicClient { provider, client_id, redirect_uri, origin: None }
+    }
+
+    /// Switches to single-page application mode, for apps registered with the "Single-page
+    /// application" platform, which Azure AD only redeems codes for when the token request
+    /// is cross-origin.
+    ///
+    /// Token requests carry an `Origin` header, which must match one of the app's redirect
+    /// URIs, eg. `https://app.contoso.com`. Browsers set it themselves, so this is for
+    /// requests made outside of one, such as from a backend acting for the SPA or in tests.
+    /// Codes should be protected with PKCE, and refresh tokens expire after
+    /// `SPA_REFRESH_TOKEN_LIFETIME`, rotating with each refresh.
+    ///
+    /// This crate sends requests with blocking reqwest and validates tokens with OpenSSL, so
+    /// it doesn't build for WebAssembly. Code running elsewhere can still build the requests
+    /// of this mode with `code_request` and `refresh_request`, and send them itself.
+    pub fn spa(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.to_owned());
+        self
+    }
+
+    /// Returns how long refresh tokens last, if Azure AD limits it for the client: in
+    /// single-page application mode, `SPA_REFRESH_TOKEN_LIFETIME` from when the user signed
+    /// in, however often they're refreshed.
+    pub fn refresh_token_lifetime(&self) -> Option<Duration> {
+        self.origin.as_ref().map(|_| SPA_REFRESH_TOKEN_LIFETIME)
+    }
+
+    /// Returns an authorization endpoint URI to direct the user to.
+    pub fn auth_uri(&self, scope: Option<&str>, state: Option<&str>) -> Url {
+        let mut uri = self.provider.auth_uri().clone();
+
+        {
+            let mut query = uri.query_pairs_mut();
+
+            query.append_pair("response_type", "code");
+            query.append_pair("client_id", &self.client_id);
+
+            if let Some(ref redirect_uri) = self.redirect_uri {
+                query.append_pair("redirect_uri", redirect_uri);
+            }
+            if let Some(scope) = scope {
+                query.append_pair("scope", scope);
+            }
+            if let Some(state) = state {
+                query.append_pair("state", state);
+            }
+        }
+
+        uri
+    }
+
+    /// Requests an access token using an authorization code, and the PKCE code verifier
+    /// if the authorization request included a code challenge.
+    pub fn request_token(
+        &self,
+        http_client: &reqwest::Client,
+        code: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<P::Token, ClientError> {
+        let json = self.post_token(http_client, self.code_body(code, code_verifier))?;
+        let token = P::Token::from_response(&json)?;
+        Ok(token)
+    }
+
+    /// Builds the token request `request_token` would send, for an HTTP client of the
+    /// caller's.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2 as oauth;
+    /// # extern crate inth_oauth2_azure;
+    /// # use oauth::client::response::FromResponse;
+    /// # use oauth::token::{Bearer, Refresh};
+    /// # use inth_oauth2_azure::{AzureCommon, PublicClient, TokenRequest};
+    /// # fn main() -> Result<(), oauth::ClientError> {
+    /// let client = PublicClient::new(AzureCommon, "client-id".into(), Some("https://app.contoso.com/".into()))
+    ///     .spa("https://app.contoso.com");
+    ///
+    /// let request = client.code_request("code", Some("verifier"));
+    /// assert_eq!(request.uri.as_str(), "https://login.microsoftonline.com/common/oauth2/v2.0/token");
+    /// assert!(request.headers.contains(&("Origin", "https://app.contoso.com".to_owned())));
+    /// assert!(request.body.ends_with("&code_verifier=verifier&client_id=client-id"));
+    ///
+    /// // POST the request, then
+    /// # let json = json!({ "token_type": "Bearer", "access_token": "eyJ0eXAi", "refresh_token": "OAQABAAAAAAAGV_bv21oQQ4ROqh0_1-tA", "expires_in": 3600 });
+    /// let token = Bearer::<Refresh>::from_response(&TokenRequest::response(json)?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn code_request(&self, code: &str, code_verifier: Option<&str>) -> TokenRequest {
+        self.token_request(self.code_body(code, code_verifier))
+    }
+
+    /// Builds the token request `refresh_token` would send for `refresh_token`, for an HTTP
+    /// client of the caller's.
+    pub fn refresh_request(&self, refresh_token: &str, scope: Option<&str>) -> TokenRequest {
+        self.token_request(refresh_body(refresh_token, scope))
+    }
+
+    fn code_body(&self, code: &str, code_verifier: Option<&str>) -> Serializer<String> {
+        let mut body = Serializer::new(String::new());
+        body.append_pair("grant_type", "authorization_code");
+        body.append_pair("code", code);
+
+        if let Some(ref redirect_uri) = self.redirect_uri {
+            body.append_pair("redirect_uri", redirect_uri);
+        }
+        if let Some(code_verifier) = code_verifier {
+            body.append_pair("code_verifier", code_verifier);
+        }
+
+        body
+    }
+
+    fn token_request(&self, body: Serializer<String>) -> TokenRequest {
+        TokenRequest::public(self.provider.token_uri(), &self.client_id, self.origin.as_deref(), body)
+    }
+
+    fn post_token(&self, http_client: &reqwest::Client, body: Serializer<String>) -> Result<Value, ClientError> {
+        post_token_from(http_client, self.provider.token_uri(), &self.client_id, None, self.origin.as_deref(), body)
+    }
+}
+
+impl<P> PublicClient<P> where P: Provider, P::Token: Token<Refresh> {
+    /// Refreshes an access token.
+    pub fn refresh_token(
+        &self,
+        http_client: &reqwest::Client,
+        token: P::Token,
+        scope: Option<&str>,
+    ) -> Result<P::Token, ClientError> {
+        let body = refresh_body(token.lifetime().refresh_token(), scope);
+        let json = self.post_token(http_client, body)?;
+        let token = P::Token::from_response_inherit(&json, &token)?;
+        Ok(token)
+    }
+
+    /// Ensures an access token is valid by refreshing it if necessary.
+    pub fn ensure_token(
+        &self,
+        http_client: &reqwest::Client,
+        token: P::Token,
+    ) -> Result<P::Token, ClientError> {
+        if token.lifetime().expired() {
+            self.refresh_token(http_client, token, None)
+        } else {
+            Ok(token)
+        }
+    }
+}
+
+fn refresh_body(refresh_token: &str, scope: Option<&str>) -> Serializer<String> {
+    let mut body = Serializer::new(String::new());
+    body.append_pair("grant_type", "refresh_token");
+    body.append_pair("refresh_token", refresh_token);
+
+    if let Some(scope) = scope {
+        body.append_pair("scope", scope);
+    }
+
+    body
+}
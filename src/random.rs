@@ -0,0 +1,53 @@
+//! Random values.
+
+use std::time::Duration;
+
+use base64;
+
+#[cfg(feature = "rand")]
+use rand::{self, Rng};
+#[cfg(not(feature = "rand"))]
+use openssl::rand::rand_bytes;
+
+/// The entropy of generated state and nonce values, in bytes.
+pub const DEFAULT_ENTROPY: usize = 32;
+
+/// The least entropy `random_token` will generate, in bytes.
+pub const MIN_ENTROPY: usize = 16;
+
+/// Returns a URL-safe random value suitable for a `state` or `nonce`, from `bytes` bytes of
+/// entropy (at least `MIN_ENTROPY`).
+///
+/// Values come from a cryptographically secure generator: `rand`'s thread-local generator
+/// with the `rand` feature, otherwise OpenSSL's.
+///
+/// ```rust
+/// # use inth_oauth2_azure::random_token;
+/// let state = random_token(32);
+/// assert_eq!(state.len(), 43);
+/// assert!(state.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+/// ```
+pub fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0; bytes.max(MIN_ENTROPY)];
+    fill(&mut buf);
+
+    base64::encode_config(&buf, base64::URL_SAFE_NO_PAD)
+}
+
+/// Returns a random duration of at most `max`, to spread out scheduled requests.
+pub(crate) fn jitter(max: Duration) -> Duration {
+    let mut buf = [0; 4];
+    fill(&mut buf);
+
+    max.mul_f64(f64::from(u32::from_le_bytes(buf)) / f64::from(u32::MAX))
+}
+
+#[cfg(feature = "rand")]
+fn fill(buf: &mut [u8]) {
+    rand::thread_rng().fill(buf);
+}
+
+#[cfg(not(feature = "rand"))]
+fn fill(buf: &mut [u8]) {
+    rand_bytes(buf).expect("failed to generate random bytes");
+}
@@ -0,0 +1,57 @@
+//! Redirect URI validation.
+
+use url::{Host, Url};
+
+use error::RedirectUriError;
+
+/// The longest redirect URI an app registration accepts.
+const MAX_LENGTH: usize = 256;
+
+/// Checks a redirect URI against the rules Azure AD applies to app registrations, so that
+/// mistakes are caught before the authorization request fails.
+///
+/// Redirect URIs must be absolute, without a fragment or wildcard, and use `https` unless
+/// they point at the loopback interface. Custom schemes used by mobile and desktop apps,
+/// such as `msal{client_id}://auth`, are accepted.
+///
+/// Azure AD compares redirect URIs as strings, so `https://contoso.com/callback/` and
+/// `https://contoso.com/callback` are different URIs. Send the string exactly as registered
+/// rather than a normalized `Url`.
+///
+/// ```rust
+/// # use inth_oauth2_azure::validate_redirect_uri;
+/// assert!(validate_redirect_uri("https://contoso.com/callback").is_ok());
+/// assert!(validate_redirect_uri("http://localhost:8400").is_ok());
+/// assert!(validate_redirect_uri("http://contoso.com/callback").is_err());
+/// assert!(validate_redirect_uri("https://contoso.com/#/callback").is_err());
+/// ```
+pub fn validate_redirect_uri(uri: &str) -> Result<(), RedirectUriError> {
+    if uri.len() > MAX_LENGTH {
+        return Err(RedirectUriError::TooLong);
+    }
+    if uri.contains('*') {
+        return Err(RedirectUriError::Wildcard);
+    }
+
+    let url = Url::parse(uri)?;
+
+    if url.fragment().is_some() {
+        return Err(RedirectUriError::Fragment);
+    }
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if is_loopback(&url) => Ok(()),
+        "http" => Err(RedirectUriError::InsecureScheme),
+        _ => Ok(()),
+    }
+}
+
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
@@ -0,0 +1,76 @@
+//! Background refresh of key sets.
+//!
+//! The refresh runs on a plain `std::thread` rather than a tokio task: the crate is
+//! synchronous, built on inth-oauth2's blocking reqwest 0.9 client, and doesn't require a
+//! tokio runtime. A task would have to move every fetch onto the runtime's blocking pool.
+//! The thread sleeps between refreshes, so async services pay for one idle thread at most.
+
+use std::cmp;
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use reqwest;
+
+use jwks::JwksCache;
+use random::jitter;
+
+/// The first delay before retrying a failed refresh, doubled on each further failure.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The longest delay between retries of a failed refresh.
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// A thread refreshing a `JwksCache` ahead of expiry, see `JwksCache::refresh_in_background`.
+///
+/// The thread stops when this is dropped.
+#[derive(Debug)]
+pub struct BackgroundRefresh {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl BackgroundRefresh {
+    /// Stops refreshing, waiting for a refresh in progress to finish.
+    pub fn stop(self) {
+        let BackgroundRefresh { stop, thread } = self;
+        drop(stop);
+        let _ = thread.join();
+    }
+}
+
+/// Spawns a thread that refreshes `cache` right away, then after half to three quarters of
+/// its TTL, so the keys are refetched before they expire. Failures are retried with an
+/// exponential backoff, while the keys already cached remain in use.
+pub(crate) fn spawn(cache: JwksCache, http_client: reqwest::Client, ttl: Duration) -> io::Result<BackgroundRefresh> {
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let thread = thread::Builder::new()
+        .name("jwks-refresh".to_owned())
+        .spawn(move || {
+            let mut backoff = INITIAL_BACKOFF;
+
+            loop {
+                // Jitter keeps the instances of a service from refetching in step
+                let delay = match cache.refresh(&http_client) {
+                    Ok(_) => {
+                        backoff = INITIAL_BACKOFF;
+                        cmp::max(ttl / 2 + jitter(ttl / 4), INITIAL_BACKOFF)
+                    },
+                    Err(_) => {
+                        let delay = backoff + jitter(backoff / 2);
+                        backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+                        delay
+                    },
+                };
+
+                match stopped.recv_timeout(delay) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        })?;
+
+    Ok(BackgroundRefresh { stop, thread })
+}
@@ -0,0 +1,157 @@
+//! Token endpoint requests not covered by `inth_oauth2::Client`.
+
+use reqwest;
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, ORIGIN};
+use serde_json::{self, Value};
+use url::form_urlencoded::Serializer;
+use url::Url;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::FromResponse;
+use inth_oauth2::error::OAuth2Error;
+
+/// POSTs a form body to a token endpoint, returning the JSON response or the OAuth 2.0
+/// error it contains.
+///
+/// Confidential clients authenticate with HTTP basic auth, like `inth_oauth2::Client`.
+/// Public clients have no secret, so only identify themselves in the body.
+pub(crate) fn post_token(
+    http_client: &reqwest::Client,
+    token_uri: &Url,
+    client_id: &str,
+    client_secret: Option<&str>,
+    body: Serializer<String>,
+) -> Result<Value, ClientError> {
+    post_token_from(http_client, token_uri, client_id, client_secret, None, body)
+}
+
+/// Like `post_token`, but with an `Origin` header, as Azure AD requires of single-page
+/// applications.
+pub(crate) fn post_token_from(
+    http_client: &reqwest::Client,
+    token_uri: &Url,
+    client_id: &str,
+    client_secret: Option<&str>,
+    origin: Option<&str>,
+    mut body: Serializer<String>,
+) -> Result<Value, ClientError> {
+    let mut request = http_client.post(token_uri.clone());
+
+    if let Some(origin) = origin {
+        request = request.header(ORIGIN, origin);
+    }
+
+    let request = match client_secret {
+        Some(secret) => request.basic_auth(client_id, Some(secret)),
+        None => {
+            body.append_pair("client_id", client_id);
+            request
+        },
+    };
+
+    let mut response = request
+        .header(ACCEPT, "application/json")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(body.finish())
+        .send()?;
+
+    let json = serde_json::from_reader(&mut response)?;
+    TokenRequest::response(json)
+}
+
+/// A public client's token request, built without sending it, for callers with an HTTP
+/// client of their own.
+///
+/// POST `body` to `uri` with `headers`, then pass the JSON response to
+/// `TokenRequest::response`. See `PublicClient::code_request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRequest {
+    /// The token endpoint.
+    pub uri: Url,
+
+    /// The request headers: `Accept`, `Content-Type` and, for single-page applications,
+    /// `Origin`, which browsers set themselves and don't let scripts override.
+    pub headers: Vec<(&'static str, String)>,
+
+    /// The `application/x-www-form-urlencoded` body.
+    pub body: String,
+}
+
+impl TokenRequest {
+    /// Builds the request of a public client, which only identifies itself in the body.
+    pub(crate) fn public(token_uri: &Url, client_id: &str, origin: Option<&str>, mut body: Serializer<String>) -> Self {
+        body.append_pair("client_id", client_id);
+
+        let mut headers = vec![
+            ("Accept", "application/json".to_owned()),
+            ("Content-Type", "application/x-www-form-urlencoded".to_owned()),
+        ];
+        if let Some(origin) = origin {
+            headers.push(("Origin", origin.to_owned()));
+        }
+
+        TokenRequest { uri: token_uri.clone(), headers, body: body.finish() }
+    }
+
+    /// Returns the JSON response to a token request, or the OAuth 2.0 error it contains.
+    pub fn response(json: Value) -> Result<Value, ClientError> {
+        match OAuth2Error::from_response(&json) {
+            Ok(error) => Err(ClientError::from(error)),
+            Err(_) => Ok(json),
+        }
+    }
+}
+
+/// GETs a JSON document, such as a key set.
+///
+/// Error statuses fail with `ClientError::Reqwest`, rather than the error page failing to
+/// parse as JSON.
+pub(crate) fn get_json(http_client: &reqwest::Client, uri: &Url) -> Result<Value, ClientError> {
+    let mut response = http_client.get(uri.clone())
+        .header(ACCEPT, "application/json")
+        .send()?
+        .error_for_status()?;
+
+    Ok(serde_json::from_reader(&mut response)?)
+}
+
+/// A JSON document fetched along with its cache validators.
+#[derive(Debug, Clone)]
+pub(crate) struct Validated {
+    pub json: Value,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// GETs a JSON document unless it has changed since `previous` was fetched, in which case
+/// `previous` is returned as is. Error statuses fail like `get_json`.
+pub(crate) fn get_json_if_modified(
+    http_client: &reqwest::Client,
+    uri: &Url,
+    previous: Option<&Validated>,
+) -> Result<Validated, ClientError> {
+    let mut request = http_client.get(uri.clone()).header(ACCEPT, "application/json");
+
+    if let Some(previous) = previous {
+        if let Some(ref etag) = previous.etag {
+            request = request.header(IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(ref last_modified) = previous.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+    }
+
+    let mut response = request.send()?.error_for_status()?;
+
+    if let (StatusCode::NOT_MODIFIED, Some(previous)) = (response.status(), previous) {
+        return Ok(previous.clone());
+    }
+
+    let header = |name| response.headers().get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    Ok(Validated { json: serde_json::from_reader(&mut response)?, etag, last_modified })
+}
@@ -0,0 +1,74 @@
+//! Application roles.
+
+use std::hash::Hash;
+
+/// An app role defined in the application's registration, as found in the `roles` claim.
+///
+/// Implement it with the `app_roles!` macro, which declares an enum of the roles and their
+/// values, so role checks are against variants rather than strings that may be misspelled.
+pub trait AppRole: Sized + Copy + Eq + Hash {
+    /// Returns the role with the given value, or `None` if it isn't declared.
+    fn from_value(value: &str) -> Option<Self>;
+
+    /// Returns the role's value, as defined in the app registration.
+    fn value(&self) -> &'static str;
+}
+
+/// Declares the app roles of an application as an enum implementing `AppRole`.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # #[macro_use] extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AppRole, IdTokenClaims};
+/// app_roles! {
+///     /// The roles of the task list API.
+///     pub enum Role {
+///         Admin = "Admin",
+///         Reader = "Tasks.Read",
+///     }
+/// }
+///
+/// # fn main() {
+/// let claims = IdTokenClaims::from_json(&json!({
+///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "exp": 1536361411,
+///     "iat": 1536274711,
+///     "roles": ["Tasks.Read", "Tasks.Archive"],
+/// })).unwrap();
+///
+/// let roles = claims.app_roles::<Role>();
+/// assert!(roles.contains(&Role::Reader));
+/// assert!(!claims.has_app_role(Role::Admin));
+/// assert_eq!(Role::Reader.value(), "Tasks.Read");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! app_roles {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $( $(#[$variant_meta:meta])* $variant:ident = $value:expr ),+ $(,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $( $(#[$variant_meta])* $variant ),+
+        }
+
+        impl $crate::AppRole for $name {
+            fn from_value(value: &str) -> Option<Self> {
+                $( if value == $value { return Some($name::$variant); } )+
+                None
+            }
+
+            fn value(&self) -> &'static str {
+                match *self {
+                    $( $name::$variant => $value ),+
+                }
+            }
+        }
+    }
+}
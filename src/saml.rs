@@ -0,0 +1,103 @@
+//! SAML assertions from the on-behalf-of flow.
+
+use std::fmt;
+
+use base64;
+use serde_json::Value;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+
+/// The version of a SAML assertion, requested as its `requested_token_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SamlVersion {
+    /// SAML 1.1.
+    Saml1,
+
+    /// SAML 2.0.
+    Saml2,
+}
+
+impl SamlVersion {
+    /// Returns the token type URI of the version.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SamlVersion::Saml1 => "urn:ietf:params:oauth:token-type:saml1",
+            SamlVersion::Saml2 => "urn:ietf:params:oauth:token-type:saml2",
+        }
+    }
+
+    fn from_token_type(token_type: &str) -> Option<Self> {
+        match token_type {
+            "urn:ietf:params:oauth:token-type:saml1" => Some(SamlVersion::Saml1),
+            "urn:ietf:params:oauth:token-type:saml2" => Some(SamlVersion::Saml2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SamlVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A SAML assertion issued by the on-behalf-of flow, for APIs that only accept SAML.
+///
+/// Azure AD returns the assertion base64url encoded, as `access_token`.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::client::response::FromResponse;
+/// # use inth_oauth2_azure::{SamlAssertion, SamlVersion};
+/// # fn main() {
+/// let assertion = SamlAssertion::from_response(&json!({
+///     "token_type": "Bearer",
+///     "issued_token_type": "urn:ietf:params:oauth:token-type:saml2",
+///     "access_token": "PEFzc2VydGlvbi8-",
+///     "expires_in": 3599,
+/// })).unwrap();
+///
+/// assert_eq!(assertion.version, SamlVersion::Saml2);
+/// assert_eq!(assertion.xml().unwrap(), "<Assertion/>");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamlAssertion {
+    /// The base64url encoded assertion.
+    pub assertion: String,
+
+    /// The version of the assertion.
+    pub version: SamlVersion,
+
+    /// Seconds until the assertion expires.
+    pub expires_in: Option<u64>,
+}
+
+impl SamlAssertion {
+    /// Decodes the assertion's XML.
+    pub fn xml(&self) -> Option<String> {
+        let xml = base64::decode_config(self.assertion.trim_end_matches('='), base64::URL_SAFE_NO_PAD).ok()?;
+        String::from_utf8(xml).ok()
+    }
+}
+
+impl FromResponse for SamlAssertion {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let obj = json.as_object().ok_or(ParseError::ExpectedType("object"))?;
+
+        let assertion = obj.get("access_token")
+            .and_then(Value::as_str)
+            .ok_or(ParseError::ExpectedFieldType("access_token", "string"))?;
+        let version = obj.get("issued_token_type")
+            .and_then(Value::as_str)
+            .and_then(SamlVersion::from_token_type)
+            .ok_or(ParseError::ExpectedFieldType("issued_token_type", "SAML token type"))?;
+
+        Ok(SamlAssertion {
+            assertion: assertion.to_owned(),
+            version,
+            expires_in: obj.get("expires_in").and_then(Value::as_u64),
+        })
+    }
+}
@@ -0,0 +1,294 @@
+//! Well-known scopes.
+//!
+//! ```rust
+//! # use inth_oauth2_azure::scopes;
+//! let scope = [scopes::OPENID, scopes::OFFLINE_ACCESS, &scopes::graph("Mail.Read")].join(" ");
+//! assert_eq!(scope, "openid offline_access https://graph.microsoft.com/Mail.Read");
+//!
+//! assert_eq!(scopes::default_for(scopes::KEY_VAULT), scopes::KEY_VAULT_DEFAULT);
+//! ```
+//!
+//! Resources are those of the public cloud; see `AzureCloud::graph_default_scope` for other
+//! clouds.
+
+use std::fmt;
+
+use error::ScopeError;
+
+/// Sign the user in and return an ID token.
+pub const OPENID: &str = "openid";
+
+/// The user's name and other basic profile claims.
+pub const PROFILE: &str = "profile";
+
+/// The user's email address.
+pub const EMAIL: &str = "email";
+
+/// A refresh token.
+pub const OFFLINE_ACCESS: &str = "offline_access";
+
+/// Microsoft Graph's delegated permission to read the signed-in user's profile.
+pub const USER_READ: &str = "User.Read";
+
+/// Microsoft Graph.
+pub const GRAPH: &str = "https://graph.microsoft.com";
+
+/// Azure Resource Manager.
+pub const ARM: &str = "https://management.azure.com";
+
+/// Azure Key Vault.
+pub const KEY_VAULT: &str = "https://vault.azure.net";
+
+/// Azure Storage.
+pub const STORAGE: &str = "https://storage.azure.com";
+
+/// Azure DevOps, which is identified by its application ID.
+pub const AZURE_DEVOPS: &str = "499b84ac-1321-427f-aa17-267ca6975798";
+
+/// The permissions granted to the application on Microsoft Graph.
+pub const GRAPH_DEFAULT: &str = "https://graph.microsoft.com/.default";
+
+/// The permissions granted to the application on Azure Resource Manager.
+pub const ARM_DEFAULT: &str = "https://management.azure.com/.default";
+
+/// The permissions granted to the application on Azure Key Vault.
+pub const KEY_VAULT_DEFAULT: &str = "https://vault.azure.net/.default";
+
+/// The permissions granted to the application on Azure Storage.
+pub const STORAGE_DEFAULT: &str = "https://storage.azure.com/.default";
+
+/// The permissions granted to the application on Azure DevOps.
+pub const AZURE_DEVOPS_DEFAULT: &str = "499b84ac-1321-427f-aa17-267ca6975798/.default";
+
+/// The OpenID Connect scopes for signing a user in with a refresh token.
+pub const SIGN_IN: [&str; 4] = [OPENID, PROFILE, EMAIL, OFFLINE_ACCESS];
+
+/// Returns the `.default` scope of a resource: every permission granted to the application.
+pub fn default_for(resource: &str) -> String {
+    format!("{}/.default", resource.trim_end_matches('/'))
+}
+
+/// Returns a Microsoft Graph permission, eg. `graph("Mail.Read")`.
+pub fn graph(permission: &str) -> String {
+    format!("{}/{}", GRAPH, permission)
+}
+
+/// Converts a v1.0 `resource` into the equivalent v2.0 scope, `{resource}/.default`.
+///
+/// Unlike `default_for`, the resource is kept exactly as given: resources registered with a
+/// trailing slash, such as Azure Resource Manager's legacy `https://management.core.windows.net/`,
+/// need the resulting double slash.
+///
+/// ```rust
+/// # use inth_oauth2_azure::scopes::{resource_for_scope, scope_for_resource};
+/// assert_eq!(scope_for_resource("https://vault.azure.net"), "https://vault.azure.net/.default");
+/// assert_eq!(
+///     scope_for_resource("https://management.core.windows.net/"),
+///     "https://management.core.windows.net//.default"
+/// );
+///
+/// assert_eq!(resource_for_scope("https://management.core.windows.net//.default").unwrap(), "https://management.core.windows.net/");
+/// assert_eq!(resource_for_scope("User.Read").unwrap(), "https://graph.microsoft.com");
+/// assert_eq!(resource_for_scope("openid"), None);
+/// ```
+pub fn scope_for_resource(resource: &str) -> String {
+    format!("{}/.default", resource)
+}
+
+/// Returns the v1.0 `resource` a v2.0 scope belongs to, or `None` for the OpenID Connect
+/// scopes, which have no resource. Unqualified permissions such as `User.Read` belong to
+/// Microsoft Graph.
+pub fn resource_for_scope(scope: &str) -> Option<String> {
+    resource_of(scope).map(str::to_owned)
+}
+
+/// Returns true if an access token's `scp` claim, its space-delimited `granted` scopes, covers
+/// `scope`. Tokens list permissions without their resource, so `scope` may be qualified or
+/// not, but must be for the token's resource.
+///
+/// ```rust
+/// # use inth_oauth2_azure::scopes::is_granted;
+/// assert!(is_granted("User.Read Mail.Read", "https://graph.microsoft.com/Mail.Read"));
+/// assert!(is_granted("User.Read Mail.Read", "mail.read"));
+/// assert!(!is_granted("User.Read", "Mail.Send"));
+/// ```
+pub fn is_granted(granted: &str, scope: &str) -> bool {
+    let permission = match qualifier(scope) {
+        Some(i) => &scope[i + 1..],
+        None => scope,
+    };
+
+    granted.split_whitespace().any(|g| g.eq_ignore_ascii_case(permission))
+}
+
+/// A validated set of scopes for one token request.
+///
+/// Azure AD only issues a token for one resource at a time, and rejects requests mixing a
+/// resource's `.default` scope with its individual permissions. `ScopeSet` checks both, and
+/// that `offline_access` is requested when a refresh token is expected.
+///
+/// ```rust
+/// # use inth_oauth2_azure::scopes::{self, ScopeSet};
+/// let set = ScopeSet::builder()
+///     .scopes(&scopes::SIGN_IN)
+///     .scope("User.Read")
+///     .refresh_token(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(set.to_string(), "openid profile email offline_access User.Read");
+///
+/// assert!(ScopeSet::builder().scope(scopes::GRAPH_DEFAULT).scope("Mail.Read").build().is_err());
+/// assert!(ScopeSet::builder().scope(scopes::ARM_DEFAULT).scope(scopes::KEY_VAULT_DEFAULT).build().is_err());
+/// assert!(ScopeSet::builder().scope(scopes::OPENID).refresh_token(true).build().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeSet {
+    scopes: Vec<String>
+}
+
+impl ScopeSet {
+    pub fn builder() -> ScopeSetBuilder {
+        ScopeSetBuilder::default()
+    }
+
+    /// Splits a space-delimited `scope` parameter or `scp` claim into a set, without the
+    /// checks `build` makes of requested scopes.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::scopes::ScopeSet;
+    /// let granted = ScopeSet::parse("User.Read Tasks.Read  Tasks.Write");
+    /// assert!(granted.contains("Tasks.Read"));
+    /// assert!(granted.contains_all(&["Tasks.Read", "Tasks.Write"]));
+    /// assert!(!granted.contains_all(&["Tasks.Read", "Tasks.Delete"]));
+    /// assert!(ScopeSet::parse("").is_empty());
+    /// ```
+    pub fn parse(scope: &str) -> Self {
+        let mut scopes: Vec<String> = Vec::new();
+        for scope in scope.split_whitespace() {
+            if !scopes.iter().any(|s| s == scope) {
+                scopes.push(scope.to_owned());
+            }
+        }
+
+        ScopeSet { scopes }
+    }
+
+    /// Returns true if the set contains `scope`.
+    pub fn contains(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Returns true if the set contains every one of `scopes`.
+    pub fn contains_all<S: AsRef<str>>(&self, scopes: &[S]) -> bool {
+        scopes.iter().all(|scope| self.contains(scope.as_ref()))
+    }
+
+    /// Returns true if the set has no scopes, which only parsed sets can.
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Returns the scopes in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.scopes.iter().map(|s| &s[..])
+    }
+}
+
+/// Renders the space-delimited `scope` parameter.
+impl fmt::Display for ScopeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.scopes.join(" "))
+    }
+}
+
+/// Builds a `ScopeSet`, see `ScopeSet::builder`.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeSetBuilder {
+    scopes: Vec<String>,
+    refresh_token: bool,
+}
+
+impl ScopeSetBuilder {
+    /// Adds a scope. Duplicates are ignored.
+    pub fn scope(mut self, scope: &str) -> Self {
+        if !self.scopes.iter().any(|s| s == scope) {
+            self.scopes.push(scope.to_owned());
+        }
+        self
+    }
+
+    /// Adds several scopes.
+    pub fn scopes<S: AsRef<str>>(self, scopes: &[S]) -> Self {
+        scopes.iter().fold(self, |builder, scope| builder.scope(scope.as_ref()))
+    }
+
+    /// Requires `offline_access`, because the caller expects a refresh token.
+    pub fn refresh_token(mut self, refresh_token: bool) -> Self {
+        self.refresh_token = refresh_token;
+        self
+    }
+
+    /// Validates the scopes.
+    pub fn build(self) -> Result<ScopeSet, ScopeError> {
+        if self.scopes.is_empty() {
+            return Err(ScopeError::Empty);
+        }
+        if let Some(scope) = self.scopes.iter().find(|s| s.is_empty() || s.contains(char::is_whitespace)) {
+            return Err(ScopeError::Invalid(scope.clone()));
+        }
+        if self.refresh_token && !self.scopes.iter().any(|s| s == OFFLINE_ACCESS) {
+            return Err(ScopeError::MissingOfflineAccess);
+        }
+
+        let mut resource: Option<&str> = None;
+        for scope in &self.scopes {
+            let scope_resource = match resource_of(scope) {
+                Some(r) => r,
+                None => continue,
+            };
+
+            match resource {
+                Some(r) if !r.eq_ignore_ascii_case(scope_resource) => {
+                    return Err(ScopeError::MultipleResources(r.to_owned(), scope_resource.to_owned()));
+                },
+                _ => resource = Some(scope_resource),
+            }
+        }
+
+        if let Some(resource) = resource {
+            let default = self.scopes.iter().any(|s| is_default(s));
+            let others = self.scopes.iter().any(|s| resource_of(s).is_some() && !is_default(s));
+            if default && others {
+                return Err(ScopeError::MixedDefault(resource.to_owned()));
+            }
+        }
+
+        Ok(ScopeSet { scopes: self.scopes })
+    }
+}
+
+/// The OpenID Connect scopes, which can be requested alongside any resource.
+pub(crate) fn is_oidc(scope: &str) -> bool {
+    [OPENID, PROFILE, EMAIL, OFFLINE_ACCESS].contains(&scope)
+}
+
+fn is_default(scope: &str) -> bool {
+    scope.ends_with("/.default")
+}
+
+/// Returns the resource a scope belongs to. Unqualified permissions are Microsoft Graph's.
+fn resource_of(scope: &str) -> Option<&str> {
+    if is_oidc(scope) {
+        return None;
+    }
+
+    match qualifier(scope) {
+        Some(i) => Some(&scope[..i]),
+        None => Some(GRAPH),
+    }
+}
+
+/// Returns the index of the `/` between a qualified permission's resource and its name.
+pub(crate) fn qualifier(scope: &str) -> Option<usize> {
+    scope.rfind('/').filter(|&i| scope[..i].contains("://") || ::is_guid(&scope[..i]))
+}
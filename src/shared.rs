@@ -0,0 +1,134 @@
+//! Shared providers.
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::Bearer;
+
+use provider::AzureProvider;
+use {Adfs, AzureAuthority, AzureB2C, AzureExternalId, AzureTenant, AzureV1, CustomAzureProvider};
+use {AzureCommon, AzureConsumer, AzureOrganization};
+use {AzureGovCommon, AzureGovOrganization, AzureGovTenant};
+use {AzureChinaCommon, AzureChinaConsumer, AzureChinaOrganization, AzureChinaTenant};
+use {AzureGermanyCommon, AzureGermanyOrganization, AzureGermanyTenant};
+
+/// A provider behind an `Arc`, so that one provider can be shared by several `Client`s and
+/// threads without copying its URIs.
+///
+/// References and boxes of this crate's providers are providers themselves, but Rust's
+/// coherence rules don't allow the same for `Arc`, hence this wrapper.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use std::thread;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::{AzureTenant, Shared};
+/// let provider = Shared::new(AzureTenant::new("contoso.onmicrosoft.com"));
+///
+/// let workers: Vec<_> = (0..2).map(|_| {
+///     let provider = provider.clone();
+///     thread::spawn(move || {
+///         let client = oauth::Client::new(provider, "client-id".into(), "client-secret".into(), None);
+///         client.auth_uri(None, None)
+///     })
+/// }).collect();
+///
+/// for worker in workers {
+///     assert!(worker.join().unwrap().as_str().starts_with(provider.auth_uri().as_str()));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Shared<P>(Arc<P>);
+
+impl<P: Provider> Shared<P> {
+    pub fn new(provider: P) -> Self {
+        Shared(Arc::new(provider))
+    }
+}
+
+impl<P: Provider> From<P> for Shared<P> {
+    fn from(provider: P) -> Self {
+        Self::new(provider)
+    }
+}
+
+impl<P: Provider> From<Arc<P>> for Shared<P> {
+    fn from(provider: Arc<P>) -> Self {
+        Shared(provider)
+    }
+}
+
+impl<P> Deref for Shared<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P { &self.0 }
+}
+
+impl<P: Provider> Provider for Shared<P> {
+    type Lifetime = P::Lifetime;
+    type Token = P::Token;
+
+    fn auth_uri(&self) -> &Url { self.0.auth_uri() }
+    fn token_uri(&self) -> &Url { self.0.token_uri() }
+    fn credentials_in_body(&self) -> bool { self.0.credentials_in_body() }
+}
+
+impl<P: AzureProvider> AzureProvider for Shared<P> {
+    fn device_authorization_uri(&self) -> Option<Url> { self.0.device_authorization_uri() }
+    fn end_session_uri(&self) -> Option<Url> { self.0.end_session_uri() }
+    fn jwks_uri(&self) -> Option<Url> { self.0.jwks_uri() }
+    fn userinfo_uri(&self) -> Option<Url> { self.0.userinfo_uri() }
+    fn admin_consent_uri(&self) -> Option<Url> { self.0.admin_consent_uri() }
+    fn discovery_uri(&self) -> Option<Url> { self.0.discovery_uri() }
+}
+
+macro_rules! impl_indirect {
+    ($($t:ty),*) => {
+        $(
+            impl<'a> Provider for &'a $t {
+                type Lifetime = <$t as Provider>::Lifetime;
+                type Token = Bearer<Self::Lifetime>;
+
+                fn auth_uri(&self) -> &Url { (**self).auth_uri() }
+                fn token_uri(&self) -> &Url { (**self).token_uri() }
+                fn credentials_in_body(&self) -> bool { (**self).credentials_in_body() }
+            }
+
+            impl<'a> AzureProvider for &'a $t {
+                fn device_authorization_uri(&self) -> Option<Url> { (**self).device_authorization_uri() }
+                fn end_session_uri(&self) -> Option<Url> { (**self).end_session_uri() }
+                fn jwks_uri(&self) -> Option<Url> { (**self).jwks_uri() }
+                fn userinfo_uri(&self) -> Option<Url> { (**self).userinfo_uri() }
+                fn admin_consent_uri(&self) -> Option<Url> { (**self).admin_consent_uri() }
+                fn discovery_uri(&self) -> Option<Url> { (**self).discovery_uri() }
+            }
+
+            impl Provider for Box<$t> {
+                type Lifetime = <$t as Provider>::Lifetime;
+                type Token = Bearer<Self::Lifetime>;
+
+                fn auth_uri(&self) -> &Url { (**self).auth_uri() }
+                fn token_uri(&self) -> &Url { (**self).token_uri() }
+                fn credentials_in_body(&self) -> bool { (**self).credentials_in_body() }
+            }
+
+            impl AzureProvider for Box<$t> {
+                fn device_authorization_uri(&self) -> Option<Url> { (**self).device_authorization_uri() }
+                fn end_session_uri(&self) -> Option<Url> { (**self).end_session_uri() }
+                fn jwks_uri(&self) -> Option<Url> { (**self).jwks_uri() }
+                fn userinfo_uri(&self) -> Option<Url> { (**self).userinfo_uri() }
+                fn admin_consent_uri(&self) -> Option<Url> { (**self).admin_consent_uri() }
+                fn discovery_uri(&self) -> Option<Url> { (**self).discovery_uri() }
+            }
+        )*
+    }
+}
+
+impl_indirect!(AzureCommon, AzureOrganization, AzureConsumer, AzureTenant);
+impl_indirect!(AzureGovCommon, AzureGovOrganization, AzureGovTenant);
+impl_indirect!(AzureChinaCommon, AzureChinaOrganization, AzureChinaConsumer, AzureChinaTenant);
+impl_indirect!(AzureGermanyCommon, AzureGermanyOrganization, AzureGermanyTenant);
+impl_indirect!(AzureB2C, AzureExternalId, Adfs, AzureV1, CustomAzureProvider, AzureAuthority);
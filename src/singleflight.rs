@@ -0,0 +1,156 @@
+//! Deduplication of concurrent token refreshes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use inth_oauth2::ClientError;
+use inth_oauth2::error::OAuth2Error;
+
+use scopes::ScopeSet;
+
+/// What a refresh resulted in, as seen by the requests waiting for it.
+#[derive(Debug)]
+enum Outcome<T> {
+    Token(T),
+    Error(OAuth2Error),
+    // The error can't be shared, or the refresh panicked
+    Failed,
+}
+
+#[derive(Debug)]
+struct Flight<T> {
+    outcome: Mutex<Option<Outcome<T>>>,
+    done: Condvar,
+}
+
+type Key = (String, Vec<String>);
+
+/// Runs one refresh at a time per account and scopes, sharing its result with the requests
+/// that need the same token meanwhile.
+///
+/// When many requests find a token expired at once, each would otherwise refresh it, and
+/// Azure AD throttles the burst. The first request refreshes instead, while the others block
+/// until it's done and receive a clone of its token. If it fails with an OAuth 2.0 error,
+/// they fail with the same error; other errors, such as connection failures, can't be shared,
+/// so the next waiting request retries the refresh itself.
+///
+/// Scopes are compared as sets, so `"openid User.Read"` and `"User.Read openid"` share one
+/// refresh.
+///
+/// ```rust
+/// # extern crate inth_oauth2;
+/// # extern crate inth_oauth2_azure;
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::thread;
+/// # use std::time::Duration;
+/// # use inth_oauth2_azure::RefreshGroup;
+/// # fn main() {
+/// let group = Arc::new(RefreshGroup::new());
+/// let refreshes = Arc::new(AtomicUsize::new(0));
+///
+/// let requests: Vec<_> = (0..8).map(|_| {
+///     let (group, refreshes) = (group.clone(), refreshes.clone());
+///     thread::spawn(move || {
+///         group.refresh("account-id", "User.Read offline_access", || {
+///             refreshes.fetch_add(1, Ordering::SeqCst);
+///             thread::sleep(Duration::from_millis(100));
+///             Ok::<_, inth_oauth2::ClientError>("new-access-token".to_owned())
+///         })
+///     })
+/// }).collect();
+///
+/// for request in requests {
+///     assert_eq!(request.join().unwrap().unwrap(), "new-access-token");
+/// }
+/// assert!(refreshes.load(Ordering::SeqCst) < 8);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RefreshGroup<T> {
+    flights: Mutex<HashMap<Key, Arc<Flight<T>>>>,
+}
+
+impl<T: Clone> RefreshGroup<T> {
+    /// Creates a group with no refreshes in flight.
+    pub fn new() -> Self {
+        RefreshGroup { flights: Mutex::new(HashMap::new()) }
+    }
+
+    /// Calls `refresh` for the token of `account` with `scope`, unless a refresh of the same
+    /// token is already running, in which case its result is waited for and returned.
+    pub fn refresh<F>(&self, account: &str, scope: &str, refresh: F) -> Result<T, ClientError>
+    where
+        F: FnOnce() -> Result<T, ClientError>,
+    {
+        let mut scopes: Vec<String> = ScopeSet::parse(scope).iter().map(str::to_owned).collect();
+        scopes.sort();
+        let key = (account.to_owned(), scopes);
+
+        loop {
+            let (flight, leader) = {
+                let mut flights = self.flights.lock().unwrap_or_else(|e| e.into_inner());
+                match flights.get(&key) {
+                    Some(flight) => (flight.clone(), false),
+                    None => {
+                        let flight = Arc::new(Flight { outcome: Mutex::new(None), done: Condvar::new() });
+                        flights.insert(key.clone(), flight.clone());
+                        (flight, true)
+                    },
+                }
+            };
+
+            if leader {
+                let landing = Landing { group: self, key: &key, flight: &flight, outcome: None };
+                let result = refresh();
+                landing.land(&result);
+                return result;
+            }
+
+            let mut outcome = flight.outcome.lock().unwrap_or_else(|e| e.into_inner());
+            while outcome.is_none() {
+                outcome = flight.done.wait(outcome).unwrap_or_else(|e| e.into_inner());
+            }
+
+            match *outcome {
+                Some(Outcome::Token(ref token)) => return Ok(token.clone()),
+                Some(Outcome::Error(ref error)) => return Err(ClientError::OAuth2(error.clone())),
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl<T: Clone> Default for RefreshGroup<T> {
+    fn default() -> Self { Self::new() }
+}
+
+/// Completes a flight when its refresh returns, or fails it if the refresh panics, so
+/// waiters are never left blocked.
+struct Landing<'a, T: 'a> {
+    group: &'a RefreshGroup<T>,
+    key: &'a Key,
+    flight: &'a Flight<T>,
+    outcome: Option<Outcome<T>>,
+}
+
+impl<'a, T: Clone> Landing<'a, T> {
+    fn land(mut self, result: &Result<T, ClientError>) {
+        self.outcome = Some(match *result {
+            Ok(ref token) => Outcome::Token(token.clone()),
+            Err(ClientError::OAuth2(ref error)) => Outcome::Error(error.clone()),
+            Err(_) => Outcome::Failed,
+        });
+    }
+}
+
+impl<'a, T> Drop for Landing<'a, T> {
+    fn drop(&mut self) {
+        // Requests arriving from now on must refresh again
+        self.group.flights.lock().unwrap_or_else(|e| e.into_inner()).remove(self.key);
+
+        let outcome = self.outcome.take().unwrap_or(Outcome::Failed);
+        *self.flight.outcome.lock().unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+        self.flight.done.notify_all();
+    }
+}
@@ -0,0 +1,143 @@
+//! Signed state.
+
+use std::time::Duration;
+
+use base64;
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde_json::{self, Value};
+
+use error::StateError;
+use jwt::now;
+use random::random_token;
+
+/// Application data carried through the authorization redirect in the `state` parameter.
+///
+/// The payload is serialized with an expiry and a random value, and signed with HMAC-SHA256
+/// using a key only the application knows, so it can't be forged or replayed once expired.
+/// Binding it to a value from the user's session (eg. a session ID) protects against CSRF:
+/// a callback started in another browser won't carry the same binding.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2_azure;
+/// # use std::time::Duration;
+/// # use inth_oauth2_azure::{StateError, StatePayload};
+/// # fn main() {
+/// let key = b"a secret key of at least 32 bytes";
+///
+/// let state = StatePayload::new(json!({ "return_to": "/orders/42" }), Duration::from_secs(600))
+///     .with_binding("session-id")
+///     .encode(key)
+///     .unwrap();
+///
+/// // ...and on the callback
+/// let payload = StatePayload::decode(&state, key, Some("session-id")).unwrap();
+/// assert_eq!(payload.data["return_to"], "/orders/42");
+///
+/// assert!(StatePayload::decode(&state, key, Some("another-session")).is_err());
+/// assert!(StatePayload::decode(&state, b"the wrong key", Some("session-id")).is_err());
+///
+/// // Empty keys can't sign anything
+/// assert_eq!(StatePayload::new(json!(null), Duration::from_secs(600)).encode(b""), Err(StateError::Key));
+/// assert_eq!(StatePayload::decode(&state, b"", Some("session-id")), Err(StateError::Key));
+/// # assert_eq!(StatePayload::new(json!(null), Duration::from_secs(u64::max_value())).expires_at, u64::max_value());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatePayload {
+    /// The application's data.
+    pub data: Value,
+
+    /// When the state expires, in seconds since the Unix epoch.
+    pub expires_at: u64,
+
+    binding: Option<String>,
+    nonce: String
+}
+
+impl StatePayload {
+    /// Wraps `data` in a payload that is valid for `lifetime`.
+    pub fn new(data: Value, lifetime: Duration) -> Self {
+        StatePayload {
+            data,
+            expires_at: now().saturating_add(lifetime.as_secs()),
+            binding: None,
+            nonce: random_token(16),
+        }
+    }
+
+    /// Binds the payload to a value from the user's session.
+    pub fn with_binding(mut self, binding: &str) -> Self {
+        self.binding = Some(binding.to_owned());
+        self
+    }
+
+    /// Returns the value the payload is bound to.
+    pub fn binding(&self) -> Option<&str> {
+        self.binding.as_ref().map(|b| &b[..])
+    }
+
+    /// Serializes and signs the payload, returning the `state` parameter.
+    pub fn encode(&self, key: &[u8]) -> Result<String, StateError> {
+        let json = json!({
+            "d": self.data,
+            "exp": self.expires_at,
+            "b": self.binding,
+            "n": self.nonce,
+        });
+        let body = base64::encode_config(&serde_json::to_vec(&json).unwrap_or_default(), base64::URL_SAFE_NO_PAD);
+        let signature = base64::encode_config(&sign(key, &body)?, base64::URL_SAFE_NO_PAD);
+
+        Ok(format!("{}.{}", body, signature))
+    }
+
+    /// Verifies and decodes a `state` parameter, checking it hasn't expired and that it is
+    /// bound to `binding`.
+    pub fn decode(state: &str, key: &[u8], binding: Option<&str>) -> Result<Self, StateError> {
+        let mut parts = state.splitn(2, '.');
+        let body = parts.next().ok_or(StateError::Malformed)?;
+        let signature = parts.next()
+            .and_then(|s| base64::decode_config(s, base64::URL_SAFE_NO_PAD).ok())
+            .ok_or(StateError::Malformed)?;
+
+        let expected = sign(key, body)?;
+        if expected.len() != signature.len() || !memcmp::eq(&expected, &signature) {
+            return Err(StateError::Signature);
+        }
+
+        let json: Value = base64::decode_config(body, base64::URL_SAFE_NO_PAD).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(StateError::Malformed)?;
+
+        let payload = StatePayload {
+            data: json["d"].clone(),
+            expires_at: json["exp"].as_u64().ok_or(StateError::Malformed)?,
+            binding: json["b"].as_str().map(str::to_owned),
+            nonce: json["n"].as_str().map(str::to_owned).ok_or(StateError::Malformed)?,
+        };
+
+        if payload.expires_at < now() {
+            return Err(StateError::Expired);
+        }
+        if payload.binding() != binding {
+            return Err(StateError::Binding);
+        }
+
+        Ok(payload)
+    }
+}
+
+fn sign(key: &[u8], body: &str) -> Result<Vec<u8>, StateError> {
+    // OpenSSL refuses empty HMAC keys
+    if key.is_empty() {
+        return Err(StateError::Key);
+    }
+
+    let key = PKey::hmac(key).map_err(|_| StateError::Key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(|_| StateError::Key)?;
+    signer.update(body.as_bytes()).map_err(|_| StateError::Key)?;
+    signer.sign_to_vec().map_err(|_| StateError::Key)
+}
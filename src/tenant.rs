@@ -0,0 +1,221 @@
+//! Tenant identifiers.
+
+use std::fmt;
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use cloud::AzureCloud;
+use error::AzureTenantError;
+use AzureTenant;
+
+/// An Azure AD tenant identifier, as used in the path of an authority.
+///
+/// ```rust
+/// # use inth_oauth2_azure::TenantId;
+/// let tenant: TenantId = "8EAEF023-2B34-4DA1-9BAA-8BC8C9D6A490".parse().unwrap();
+/// assert!(tenant.as_guid().is_some());
+/// assert_eq!(tenant.to_string(), "8eaef023-2b34-4da1-9baa-8bc8c9d6a490");
+///
+/// assert_eq!("Contoso.onmicrosoft.com".parse(), Ok(TenantId::Domain("contoso.onmicrosoft.com".into())));
+/// assert_eq!("common".parse(), Ok(TenantId::Common));
+/// assert!("contoso".parse::<TenantId>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TenantId {
+    /// A tenant's GUID, eg. `8eaef023-2b34-4da1-9baa-8bc8c9d6a490`.
+    Guid(Uuid),
+
+    /// One of a tenant's verified domains, eg. `contoso.onmicrosoft.com`.
+    Domain(String),
+
+    /// The multi-tenant `common` endpoint.
+    Common,
+
+    /// The multi-tenant `organizations` endpoint.
+    Organizations,
+
+    /// The multi-tenant `consumers` endpoint.
+    Consumers,
+}
+
+impl TenantId {
+    /// Returns true if this identifies a single tenant rather than a multi-tenant endpoint.
+    pub fn is_specific(&self) -> bool {
+        matches!(*self, TenantId::Guid(_) | TenantId::Domain(_))
+    }
+
+    /// Returns the tenant GUID, if the tenant was identified by one.
+    pub fn as_guid(&self) -> Option<&Uuid> {
+        match *self {
+            TenantId::Guid(ref guid) => Some(guid),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TenantId {
+    type Err = AzureTenantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(AzureTenantError::Empty);
+        }
+
+        match s.to_lowercase().as_str() {
+            "common" => return Ok(TenantId::Common),
+            "organizations" => return Ok(TenantId::Organizations),
+            "consumers" => return Ok(TenantId::Consumers),
+            _ => {},
+        }
+
+        if ::is_guid(s) {
+            return Uuid::parse_str(s).map(TenantId::Guid).map_err(|_| AzureTenantError::InvalidDomain);
+        }
+
+        if let Some(c) = s.chars().find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '.')) {
+            return Err(AzureTenantError::InvalidCharacter(c));
+        }
+        if s.starts_with(['.', '-']) || s.ends_with(['.', '-']) {
+            return Err(AzureTenantError::InvalidBoundary);
+        }
+        if !s.contains('.') || s.contains("..") {
+            return Err(AzureTenantError::InvalidDomain);
+        }
+
+        Ok(TenantId::Domain(s.to_lowercase()))
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TenantId::Guid(ref guid) => write!(f, "{}", guid.to_hyphenated()),
+            TenantId::Domain(ref domain) => write!(f, "{}", domain),
+            TenantId::Common => write!(f, "common"),
+            TenantId::Organizations => write!(f, "organizations"),
+            TenantId::Consumers => write!(f, "consumers"),
+        }
+    }
+}
+
+impl From<Uuid> for TenantId {
+    fn from(guid: Uuid) -> Self {
+        TenantId::Guid(guid)
+    }
+}
+
+/// The Azure AD endpoint version a provider talks to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
+pub enum EndpointVersion {
+    /// The v1.0 endpoints, which issue tokens for a `resource`.
+    V1,
+
+    /// The v2.0 (Microsoft identity platform) endpoints, which issue tokens for scopes.
+    #[default]
+    V2,
+}
+
+impl EndpointVersion {
+    pub(crate) fn oauth2_path(self, endpoint: &str) -> String {
+        match self {
+            EndpointVersion::V1 => format!("oauth2/{}", endpoint),
+            EndpointVersion::V2 => format!("oauth2/v2.0/{}", endpoint),
+        }
+    }
+
+    pub(crate) fn jwks_path(self) -> &'static str {
+        match self {
+            EndpointVersion::V1 => "discovery/keys",
+            EndpointVersion::V2 => "discovery/v2.0/keys",
+        }
+    }
+
+    pub(crate) fn discovery_path(self) -> &'static str {
+        match self {
+            EndpointVersion::V1 => ".well-known/openid-configuration",
+            EndpointVersion::V2 => "v2.0/.well-known/openid-configuration",
+        }
+    }
+}
+
+/// Builds an `AzureTenant`, validating its tenant identifier and region.
+///
+/// See `AzureTenant::builder`.
+#[derive(Debug, Clone, Default)]
+pub struct AzureTenantBuilder {
+    tenant: Option<String>,
+    cloud: AzureCloud,
+    region: Option<String>,
+    version: EndpointVersion,
+}
+
+impl AzureTenantBuilder {
+    /// Sets the tenant identifier. This is required.
+    pub fn tenant(mut self, id: &str) -> Self {
+        self.tenant = Some(id.to_owned());
+        self
+    }
+
+    /// Sets the Azure cloud the tenant lives in. Defaults to the public cloud.
+    pub fn cloud(mut self, cloud: AzureCloud) -> Self {
+        self.cloud = cloud;
+        self
+    }
+
+    /// Sends token requests to a regional token service, see `AzureTenant::with_region`.
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_owned());
+        self
+    }
+
+    /// Selects the v1.0 or v2.0 endpoints. Defaults to v2.0.
+    pub fn version(mut self, version: EndpointVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Validates the configuration and returns the provider.
+    pub fn build(self) -> Result<AzureTenant, AzureTenantError> {
+        let tenant: TenantId = self.tenant.as_ref().map(|t| &t[..]).unwrap_or_default().parse()?;
+
+        if let Some(ref region) = self.region {
+            validate_region(region)?;
+        }
+
+        Ok(AzureTenant::build(tenant, self.cloud, self.version, self.region))
+    }
+}
+
+// Regions are prefixed to the authority host, so must be a single DNS label such as `westus2`
+pub(crate) fn validate_region(region: &str) -> Result<(), AzureTenantError> {
+    if region.is_empty() {
+        return Err(AzureTenantError::Empty);
+    }
+    if let Some(c) = region.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        return Err(AzureTenantError::InvalidCharacter(c));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::TenantId;
+
+    impl Serialize for TenantId {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TenantId {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(de::Error::custom)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! The OpenID Connect userinfo endpoint.
+
+use reqwest;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde_json::{self, Map, Value};
+use url::Url;
+use inth_oauth2::ClientError;
+use inth_oauth2::client::response::{FromResponse, ParseError};
+use inth_oauth2::error::{OAuth2Error, OAuth2ErrorCode};
+
+/// The profile of the signed-in user, as returned by the userinfo endpoint.
+///
+/// Which claims are present depends on the scopes the access token was issued for:
+/// `profile` for the names and picture, `email` for the email address.
+///
+/// See [OpenID Connect Core 1.0, section 5.3](https://openid.net/specs/openid-connect-core-1_0.html#UserInfo).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UserInfo {
+    /// The user's pairwise identifier, the same as the `sub` of their ID token.
+    pub sub: String,
+
+    /// The user's display name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+
+    /// The user's first name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub given_name: Option<String>,
+
+    /// The user's last name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub family_name: Option<String>,
+
+    /// The user's email address.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub email: Option<String>,
+
+    /// The URI of the user's photo on Graph, which requires the access token to fetch.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub picture: Option<String>,
+
+    /// Every other claim.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub additional: Map<String, Value>,
+}
+
+impl FromResponse for UserInfo {
+    fn from_response(json: &Value) -> Result<Self, ParseError> {
+        let mut obj = json.as_object().ok_or(ParseError::ExpectedType("object"))?.clone();
+
+        let mut string = |key: &'static str| match obj.remove(key) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            None | Some(Value::Null) => Ok(None),
+            Some(_) => Err(ParseError::ExpectedFieldType(key, "string")),
+        };
+
+        let sub = string("sub")?.ok_or(ParseError::ExpectedFieldType("sub", "string"))?;
+        let name = string("name")?;
+        let given_name = string("given_name")?;
+        let family_name = string("family_name")?;
+        let email = string("email")?;
+        let picture = string("picture")?;
+
+        Ok(UserInfo { sub, name, given_name, family_name, email, picture, additional: obj })
+    }
+}
+
+/// Fetches the signed-in user's profile from the userinfo endpoint, for apps that keep their
+/// ID tokens small but still need the user's name and email after sign-in.
+///
+/// `userinfo_uri` is the provider's, see `AzureProvider::userinfo_uri`, and `access_token`
+/// must be for Graph with the `openid` scope. Requires the `userinfo` feature.
+///
+/// ```rust,no_run
+/// # extern crate reqwest;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::{AzureCommon, AzureProvider, fetch_userinfo};
+/// # fn main() -> Result<(), inth_oauth2::ClientError> {
+/// # let graph_token = "";
+/// let uri = AzureCommon.userinfo_uri().unwrap();
+/// let profile = fetch_userinfo(&reqwest::Client::new(), &uri, graph_token)?;
+///
+/// println!("Signed in as {}", profile.name.unwrap_or(profile.sub));
+/// # Ok(())
+/// # }
+/// ```
+pub fn fetch_userinfo(
+    http_client: &reqwest::Client,
+    userinfo_uri: &Url,
+    access_token: &str,
+) -> Result<UserInfo, ClientError> {
+    let mut response = http_client.get(userinfo_uri.clone())
+        .header(AUTHORIZATION, format!("Bearer {}", access_token))
+        .header(ACCEPT, "application/json")
+        .send()?;
+
+    if !response.status().is_success() {
+        // Graph describes errors in its own format, if the body isn't empty
+        let json: Value = serde_json::from_reader(&mut response).unwrap_or_default();
+        let error = &json["error"];
+
+        return Err(ClientError::from(OAuth2Error {
+            code: OAuth2ErrorCode::from(error["code"].as_str().unwrap_or("invalid_token")),
+            description: error["message"].as_str().map(str::to_owned)
+                .or_else(|| Some(response.status().to_string())),
+            uri: None,
+        }));
+    }
+
+    let json: Value = serde_json::from_reader(&mut response)?;
+    Ok(UserInfo::from_response(&json)?)
+}
@@ -0,0 +1,109 @@
+//! Azure AD v1.0 endpoints.
+
+use url::Url;
+use inth_oauth2::provider::Provider;
+use inth_oauth2::token::{Bearer, Refresh};
+
+use cloud::AzureCloud;
+use error::AzureTenantError;
+use provider::AzureProvider;
+use tenant::TenantId;
+
+/// Users sign in through the Azure AD v1.0 endpoints, which issue tokens for a single
+/// `resource` rather than a set of v2.0 scopes.
+///
+/// The tenant can be any tenant identifier accepted by `AzureTenant`, or `common`.
+///
+/// ```rust
+/// # extern crate inth_oauth2 as oauth;
+/// # extern crate inth_oauth2_azure;
+/// # use oauth::provider::Provider;
+/// # use inth_oauth2_azure::AzureV1;
+/// let provider = AzureV1::new("contoso.onmicrosoft.com")
+///     .with_resource("https://management.core.windows.net/");
+///
+/// assert_eq!(
+///     provider.auth_uri().as_str(),
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/authorize?resource=https%3A%2F%2Fmanagement.core.windows.net%2F"
+/// );
+/// assert_eq!(
+///     provider.token_uri().as_str(),
+///     "https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/token"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AzureV1 {
+    resource: Option<String>,
+    auth_uri: Url,
+    token_uri: Url
+}
+
+impl AzureV1 {
+    /// Creates a v1.0 provider for a tenant in the public cloud.
+    ///
+    /// The tenant identifier isn't validated, so prefer `try_new` when it comes from
+    /// configuration or user input.
+    pub fn new(tenant: &str) -> Self {
+        Self::new_in_cloud(tenant, AzureCloud::Public)
+    }
+
+    /// Creates a v1.0 provider for a tenant in the public cloud, checking that the tenant
+    /// identifier is a plausible GUID or domain name, or `common`.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenantError, AzureV1};
+    /// assert!(AzureV1::try_new("contoso.onmicrosoft.com").is_ok());
+    /// assert!(AzureV1::try_new("common").is_ok());
+    /// assert_eq!(AzureV1::try_new("x/../../evil?a="), Err(AzureTenantError::InvalidCharacter('/')));
+    /// ```
+    pub fn try_new(tenant: &str) -> Result<Self, AzureTenantError> {
+        Self::try_new_in_cloud(tenant, AzureCloud::Public)
+    }
+
+    /// Creates a v1.0 provider for a tenant in the given Azure cloud, checking the tenant
+    /// identifier like `try_new`.
+    pub fn try_new_in_cloud(tenant: &str, cloud: AzureCloud) -> Result<Self, AzureTenantError> {
+        let tenant: TenantId = tenant.parse()?;
+
+        Ok(Self::new_in_cloud(&tenant.to_string(), cloud))
+    }
+
+    /// Creates a v1.0 provider for a tenant in the given Azure cloud.
+    ///
+    /// Like `new`, the tenant identifier isn't validated.
+    pub fn new_in_cloud(tenant: &str, cloud: AzureCloud) -> Self {
+        let auth_uri = cloud.endpoint(tenant, "oauth2/authorize");
+        let token_uri = cloud.endpoint(tenant, "oauth2/token");
+
+        Self { resource: None, auth_uri, token_uri }
+    }
+
+    /// Requests tokens for the given resource, eg. `https://graph.windows.net`.
+    ///
+    /// The resource is added to the authorization URI, so it's carried through
+    /// `Client::auth_uri` unchanged.
+    pub fn with_resource(mut self, resource: &str) -> Self {
+        self.auth_uri.query_pairs_mut().clear().append_pair("resource", resource);
+        self.resource = Some(resource.to_owned());
+        self
+    }
+
+    /// Returns the resource tokens are requested for, if any.
+    pub fn resource(&self) -> Option<&str> { self.resource.as_ref().map(|r| &r[..]) }
+}
+
+impl Provider for AzureV1 {
+    type Lifetime = Refresh;
+    type Token = Bearer<Self::Lifetime>;
+
+    fn auth_uri(&self) -> &Url { &self.auth_uri }
+    fn token_uri(&self) -> &Url { &self.token_uri }
+}
+
+impl AzureProvider for AzureV1 {
+    fn device_authorization_uri(&self) -> Option<Url> { self.token_uri.join("devicecode").ok() }
+    fn end_session_uri(&self) -> Option<Url> { self.token_uri.join("logout").ok() }
+    fn jwks_uri(&self) -> Option<Url> { self.token_uri.join("../discovery/keys").ok() }
+    fn userinfo_uri(&self) -> Option<Url> { self.token_uri.join("../openid/userinfo").ok() }
+    fn discovery_uri(&self) -> Option<Url> { self.token_uri.join("../.well-known/openid-configuration").ok() }
+}
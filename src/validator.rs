@@ -0,0 +1,1168 @@
+//! Validation of ID tokens, and of the access tokens web APIs receive.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use reqwest;
+use url::Url;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Padding;
+use openssl::sign::{RsaPssSaltlen, Verifier};
+
+use assertion::Thumbprint;
+use cache::MetadataCache;
+use discovery::OpenIdConfiguration;
+use error::ValidationError;
+use id_token::IdTokenClaims;
+use jwe;
+use jwks::{Jwk, Jwks, JwksCache};
+use jwt;
+use provider::AzureProvider;
+use tenant::TenantId;
+use {AzureTenant, CONSUMER_TENANT_ID, TENANT_ID_TEMPLATE};
+
+/// An asymmetric algorithm tokens may be signed with.
+///
+/// Unsigned (`none`) and HMAC (`HS256` etc.) tokens can't be represented, so they're
+/// rejected whatever the allowlist: anyone knowing the client secret, or no secret at all,
+/// could forge them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// RSASSA-PKCS1-v1_5 with SHA-256, which Azure AD signs every token with.
+    RS256,
+
+    /// RSASSA-PKCS1-v1_5 with SHA-384.
+    RS384,
+
+    /// RSASSA-PKCS1-v1_5 with SHA-512.
+    RS512,
+
+    /// RSASSA-PSS with SHA-256.
+    PS256,
+
+    /// RSASSA-PSS with SHA-384.
+    PS384,
+
+    /// RSASSA-PSS with SHA-512.
+    PS512,
+}
+
+impl Algorithm {
+    /// Returns the `alg` header value.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Algorithm::RS256 => "RS256",
+            Algorithm::RS384 => "RS384",
+            Algorithm::RS512 => "RS512",
+            Algorithm::PS256 => "PS256",
+            Algorithm::PS384 => "PS384",
+            Algorithm::PS512 => "PS512",
+        }
+    }
+
+    fn from_header(alg: &str) -> Option<Self> {
+        match alg {
+            "RS256" => Some(Algorithm::RS256),
+            "RS384" => Some(Algorithm::RS384),
+            "RS512" => Some(Algorithm::RS512),
+            "PS256" => Some(Algorithm::PS256),
+            "PS384" => Some(Algorithm::PS384),
+            "PS512" => Some(Algorithm::PS512),
+            _ => None,
+        }
+    }
+
+    fn digest(&self) -> MessageDigest {
+        match *self {
+            Algorithm::RS256 | Algorithm::PS256 => MessageDigest::sha256(),
+            Algorithm::RS384 | Algorithm::PS384 => MessageDigest::sha384(),
+            Algorithm::RS512 | Algorithm::PS512 => MessageDigest::sha512(),
+        }
+    }
+
+    fn is_pss(&self) -> bool {
+        matches!(*self, Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a token identifies its signing key.
+#[derive(Debug, Clone, Copy)]
+enum KeyRef<'a> {
+    Kid(&'a str),
+    Thumbprint(Thumbprint, &'a str),
+}
+
+impl<'a> KeyRef<'a> {
+    fn find(self, jwks: &Jwks) -> Option<&Jwk> {
+        match self {
+            KeyRef::Kid(kid) => jwks.find(kid),
+            KeyRef::Thumbprint(thumbprint, value) => jwks.find_by_thumbprint(thumbprint, value),
+        }
+    }
+
+    fn unknown(self) -> ValidationError {
+        match self {
+            KeyRef::Kid(id) | KeyRef::Thumbprint(_, id) => ValidationError::UnknownKey(Some(id.to_owned())),
+        }
+    }
+}
+
+/// Decides whether tokens from a tenant are accepted, given its ID.
+struct TenantFilter(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl fmt::Debug for TenantFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("TenantFilter")
+    }
+}
+
+/// Where a validator gets its keys.
+#[derive(Debug)]
+enum KeySource {
+    /// Fetched from a `jwks_uri`, and cached.
+    Remote(JwksCache),
+
+    /// Supplied by the application, as of when it was exported.
+    Offline(Jwks, Option<SystemTime>),
+}
+
+/// How far the clocks of the issuer and the validator may be apart by default.
+pub const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Validates ID tokens: their signature against the provider's JSON Web Key Set,
+/// their issuer and audience, and that they haven't expired.
+///
+/// The issuer may be a multi-tenant template such as `AzureCommon::issuer()`, in which case
+/// the token's `tid` claim is substituted for `{tenantid}` before comparing, as MSAL does.
+///
+/// The `nonce` of the authorization request, kept across the redirect with a `NonceStore`,
+/// is checked by `validate_nonce` and `validate_with_nonce`.
+///
+/// The keys are cached, see `JwksCache`. A validator should be kept for the lifetime of the
+/// application, and can be shared between threads.
+///
+/// ```rust
+/// # extern crate base64;
+/// # extern crate openssl;
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2;
+/// # extern crate url;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2::client::response::FromResponse;
+/// # use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::Signer};
+/// # use inth_oauth2_azure::{AzureCommon, IdTokenValidator, Jwks, ValidationError};
+/// # fn main() {
+/// # let b64 = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+/// # let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+/// # let rsa = key.rsa().unwrap();
+/// # let jwks = Jwks::from_response(&json!({ "keys": [{
+/// #     "kty": "RSA", "kid": "key-1", "n": b64(&rsa.n().to_vec()), "e": b64(&rsa.e().to_vec()),
+/// # }]})).unwrap();
+/// # let sign = |claims: serde_json::Value| {
+/// #     let input = format!("{}.{}", b64(json!({ "alg": "RS256", "kid": "key-1" }).to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+/// #     let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+/// #     signer.update(input.as_bytes()).unwrap();
+/// #     format!("{}.{}", input, b64(&signer.sign_to_vec().unwrap()))
+/// # };
+/// # let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+/// let validator = IdTokenValidator::new(
+///     url::Url::parse("https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/discovery/v2.0/keys").unwrap(),
+///     "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// );
+///
+/// // `validate` fetches the keys from the JWKS URI, `validate_with` uses the keys given
+/// let id_token = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// let claims = validator.validate_with(&jwks, &id_token).unwrap();
+/// assert_eq!(claims.sub, "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ");
+///
+/// let with_nonce = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "nonce": "123523",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// assert!(validator.validate_with_nonce(&jwks, &with_nonce, "123523").is_ok());
+/// match validator.validate_with_nonce(&jwks, &id_token, "123523") {
+///     Err(ValidationError::Nonce) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// let expired = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now - 3660,
+///     "exp": now - 60,
+/// }));
+/// // Within the default clock skew of 5 minutes
+/// assert!(validator.validate_with(&jwks, &expired).is_ok());
+/// # let validator = validator.clock_skew(std::time::Duration::from_secs(0));
+/// # match validator.validate_with(&jwks, &expired) {
+/// #     Err(ValidationError::Expired) => {},
+/// #     other => panic!("{:?}", other),
+/// # }
+/// # let validator = validator.clock_skew(inth_oauth2_azure::DEFAULT_CLOCK_SKEW);
+///
+/// // Unsigned and HMAC tokens are rejected whatever their key ID
+/// let claims = json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// });
+/// for alg in &["none", "HS256"] {
+///     let forged = format!("{}.{}.", b64(json!({ "alg": alg, "kid": "key-1" }).to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+///     match validator.validate_with(&jwks, &forged) {
+///         Err(ValidationError::Algorithm(_)) => {},
+///         other => panic!("{:?}", other),
+///     }
+/// }
+///
+/// let tampered = format!("{}x", &id_token[..id_token.len() - 1]);
+/// assert!(validator.validate_with(&jwks, &tampered).is_err());
+///
+/// let other_app = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "00000000-0000-0000-0000-000000000000",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// match validator.validate_with(&jwks, &other_app) {
+///     Err(ValidationError::Audience(_)) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// let validator = validator.audience("api://6cb04018-a3f5-46a7-b995-940c78f5aef3");
+/// let app_id_uri = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "api://6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// assert!(validator.validate_with(&jwks, &app_id_uri).is_ok());
+///
+/// // High-risk operations can require multi-factor authentication
+/// let strict = IdTokenValidator::new(
+///     url::Url::parse("https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/discovery/v2.0/keys").unwrap(),
+///     "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// ).require_mfa();
+/// match strict.validate_with(&jwks, &id_token) {
+///     Err(ValidationError::Mfa) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// // Sensitive operations can require a recent sign-in, as requested with `max_age`
+/// let recent = IdTokenValidator::new(
+///     url::Url::parse("https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/discovery/v2.0/keys").unwrap(),
+///     "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// ).max_age(std::time::Duration::from_secs(600));
+/// let stale = sign(json!({
+///     "iss": "https://login.microsoftonline.com/9188040d-6c67-4c5b-b112-36a304b66dad/v2.0",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "auth_time": now - 3600,
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// match recent.validate_with(&jwks, &stale) {
+///     Err(ValidationError::AuthTime(Some(_))) => {},
+///     other => panic!("{:?}", other),
+/// }
+/// match recent.validate_with(&jwks, &id_token) {
+///     Err(ValidationError::AuthTime(None)) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// // Tokens from the `common` endpoint are issued by the user's tenant
+/// let common = IdTokenValidator::new(
+///     url::Url::parse("https://login.microsoftonline.com/common/discovery/v2.0/keys").unwrap(),
+///     &AzureCommon.issuer(),
+///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// );
+/// let tenant_token = sign(json!({
+///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+///     "tid": "8eaef023-2b34-4da1-9baa-8bc8c9d6a490",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// assert!(common.validate_with(&jwks, &tenant_token).is_ok());
+///
+/// let other_tenant = sign(json!({
+///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+///     "tid": "9188040d-6c67-4c5b-b112-36a304b66dad",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "iat": now,
+///     "exp": now + 3600,
+/// }));
+/// match common.validate_with(&jwks, &other_tenant) {
+///     Err(ValidationError::Issuer(_)) => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// let onboarded = common.tenant_filter(|tid| tid == "72f988bf-86f1-41af-91ab-2d7cd011db47");
+/// match onboarded.validate_with(&jwks, &tenant_token) {
+///     Err(ValidationError::Tenant(Some(_))) => {},
+///     other => panic!("{:?}", other),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct IdTokenValidator {
+    keys: KeySource,
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    tenant: Option<String>,
+    clock_skew: Duration,
+    algorithms: Vec<Algorithm>,
+    require_mfa: bool,
+    max_age: Option<Duration>,
+    decryption_key: Option<PKey<Private>>,
+    pins: Vec<(Thumbprint, String)>,
+    tenant_filter: Option<TenantFilter>,
+    clients: Vec<String>,
+    require_certificate_client: bool,
+}
+
+impl IdTokenValidator {
+    /// Validates tokens signed with the keys at `jwks_uri`, issued by `issuer` to the
+    /// application `client_id`. `issuer` may contain `{tenantid}`.
+    pub fn new(jwks_uri: Url, issuer: &str, client_id: &str) -> Self {
+        Self::with_keys(KeySource::Remote(JwksCache::new(jwks_uri)), issuer, client_id)
+    }
+
+    /// Validates tokens signed with the keys of a locally supplied key set, for deployments
+    /// that can't reach Azure AD. The keys are never fetched, so `validate` doesn't use its
+    /// HTTP client.
+    ///
+    /// Azure AD rolls its keys over periodically, so the key set must be updated with it;
+    /// see `is_jwks_stale`.
+    ///
+    /// ```rust
+    /// # extern crate serde_json;
+    /// # extern crate inth_oauth2;
+    /// # extern crate inth_oauth2_azure;
+    /// # use std::time::{Duration, SystemTime};
+    /// # use inth_oauth2::client::response::FromResponse;
+    /// # use inth_oauth2_azure::{IdTokenValidator, Jwks};
+    /// # fn main() {
+    /// // eg. read from a file shipped with the deployment
+    /// let document = r#"{"keys":[{"kty":"RSA","use":"sig","kid":"nOo3ZDrODXEK1jKWhXslHR_KXEg","n":"oaLLT9hkcSj2tGfZsjbu7Xz1Krs0qEicXPmEsJKOBQHauZ_kRM1HdEkgOJbUznUspE6xOuOSXjlzErqBxXAu4SCvcvVOCYG2v9G3-uIrLF5dstD0sYHBo1VomtKxzF90Vslrkn6rNQgUGIWgvuQTxm1uRklYFPEcTIRw0LnYknzJ06GC9ljKR617wABVrZNkBuDgQKj37qcyxoaxIGdxEcmVFZXJyrxDgdXh9owRmZn6LIJlGjZ9m59emfuwnBnsIQG7DirJwe9SXrLB6gPVzgj8GRsvURbhCAvnUSdDr2e_dtscCwHIU6RKOllqXImB5iNUqVQ0ykHhsXeVgCGyTQ","e":"AQAB"}]}"#;
+    /// let jwks = Jwks::from_response(&serde_json::from_str(document).unwrap()).unwrap();
+    ///
+    /// let exported = SystemTime::now() - Duration::from_secs(45 * 24 * 60 * 60);
+    /// let validator = IdTokenValidator::offline(
+    ///     jwks,
+    ///     "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    /// ).jwks_exported_at(exported);
+    ///
+    /// if validator.is_jwks_stale(Duration::from_secs(30 * 24 * 60 * 60)) {
+    ///     eprintln!("The signing keys are over 30 days old, and may have been rolled over");
+    /// }
+    /// # assert!(validator.is_jwks_stale(Duration::from_secs(30 * 24 * 60 * 60)));
+    /// # }
+    /// ```
+    pub fn offline(jwks: Jwks, issuer: &str, client_id: &str) -> Self {
+        Self::with_keys(KeySource::Offline(jwks, None), issuer, client_id)
+    }
+
+    fn with_keys(keys: KeySource, issuer: &str, client_id: &str) -> Self {
+        IdTokenValidator {
+            keys,
+            issuers: vec![issuer.to_owned()],
+            audiences: vec![client_id.to_owned()],
+            tenant: None,
+            clock_skew: DEFAULT_CLOCK_SKEW,
+            algorithms: vec![Algorithm::RS256],
+            require_mfa: false,
+            max_age: None,
+            decryption_key: None,
+            pins: Vec::new(),
+            tenant_filter: None,
+            clients: Vec::new(),
+            require_certificate_client: false,
+        }
+    }
+
+    /// Validates tokens issued by `provider` to the application `client_id`.
+    ///
+    /// Both v1.0 and v2.0 tokens of the provider's tenant are accepted. Tokens from a single
+    /// tenant's provider must carry its `tid`, even though its issuer already contains it;
+    /// call `any_tenant` to accept every tenant instead. Returns `None` for tenants
+    /// identified by a domain name, whose issuer isn't known without discovery; see
+    /// `from_configuration`.
+    ///
+    /// ```rust
+    /// # use inth_oauth2_azure::{AzureTenant, IdTokenValidator};
+    /// let provider = AzureTenant::new("8eaef023-2b34-4da1-9baa-8bc8c9d6a490");
+    /// let validator = IdTokenValidator::for_tenant(&provider, "6cb04018-a3f5-46a7-b995-940c78f5aef3").unwrap();
+    /// assert_eq!(validator.expected_tenant(), Some("8eaef023-2b34-4da1-9baa-8bc8c9d6a490"));
+    ///
+    /// let common = IdTokenValidator::for_tenant(&AzureTenant::new("common"), "6cb04018-a3f5-46a7-b995-940c78f5aef3").unwrap();
+    /// assert_eq!(common.expected_tenant(), None);
+    ///
+    /// assert_eq!(validator.issuers(), [
+    ///     "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "https://sts.windows.net/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/",
+    /// ]);
+    ///
+    /// assert!(IdTokenValidator::for_tenant(&AzureTenant::new("contoso.onmicrosoft.com"), "6cb04018-a3f5-46a7-b995-940c78f5aef3").is_none());
+    /// ```
+    pub fn for_tenant(provider: &AzureTenant, client_id: &str) -> Option<Self> {
+        let (tenant, segment) = match *provider.tenant_id() {
+            TenantId::Guid(ref guid) => (Some(guid.to_string()), guid.to_string()),
+            TenantId::Consumers => (Some(CONSUMER_TENANT_ID.to_owned()), CONSUMER_TENANT_ID.to_owned()),
+            TenantId::Common | TenantId::Organizations => (None, TENANT_ID_TEMPLATE.to_owned()),
+            TenantId::Domain(_) => return None,
+        };
+
+        // The format of tokens depends on the application's accessTokenAcceptedVersion rather
+        // than the endpoint they're requested from, so both versions' issuers are accepted
+        let cloud = provider.cloud();
+        let validator = IdTokenValidator::new(provider.jwks_uri()?, &cloud.issuer(&segment), client_id)
+            .issuer(&cloud.issuer_v1(&segment));
+        Some(IdTokenValidator { tenant, ..validator })
+    }
+
+    /// Validates tokens issued by the authority of a discovery document to the application
+    /// `client_id`, with the issuer and key set it publishes.
+    ///
+    /// Only `RS256` is accepted, whatever algorithms the document lists; see `algorithms`.
+    pub fn from_configuration(configuration: &OpenIdConfiguration, client_id: &str) -> Self {
+        Self::new(configuration.jwks_uri.clone(), &configuration.issuer, client_id)
+    }
+
+    /// Only accepts tokens whose `tid` claim is `tenant`.
+    pub fn tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_owned());
+        self
+    }
+
+    /// Accepts tokens from any tenant the issuer allows, for multi-tenant applications.
+    pub fn any_tenant(mut self) -> Self {
+        self.tenant = None;
+        self.tenant_filter = None;
+        self
+    }
+
+    /// Only accepts tokens from the given tenants, for multi-tenant applications that only
+    /// serve the organizations that have onboarded.
+    ///
+    /// ```rust
+    /// # extern crate url;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureCommon, IdTokenValidator};
+    /// # fn main() {
+    /// let validator = IdTokenValidator::new(
+    ///     url::Url::parse("https://login.microsoftonline.com/common/discovery/v2.0/keys").unwrap(),
+    ///     &AzureCommon.issuer(),
+    ///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    /// ).allow_tenants(&["8eaef023-2b34-4da1-9baa-8bc8c9d6a490"]);
+    /// # }
+    /// ```
+    pub fn allow_tenants<S: AsRef<str>>(self, tenants: &[S]) -> Self {
+        let tenants: Vec<String> = tenants.iter().map(|tenant| tenant.as_ref().to_owned()).collect();
+        self.tenant_filter(move |tid| tenants.iter().any(|tenant| tenant.eq_ignore_ascii_case(tid)))
+    }
+
+    /// Only accepts tokens from tenants `filter` returns true for, given the token's `tid`,
+    /// eg. by looking the tenant up among the application's customers. The filter is only
+    /// called for tokens whose signature, issuer and audience are valid.
+    pub fn tenant_filter<F: Fn(&str) -> bool + Send + Sync + 'static>(mut self, filter: F) -> Self {
+        self.tenant_filter = Some(TenantFilter(Arc::new(filter)));
+        self
+    }
+
+    /// Sets how far the clocks of the issuer and the validator may be apart when checking
+    /// `exp`, `nbf` and `iat`. Defaults to `DEFAULT_CLOCK_SKEW`.
+    pub fn clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    /// Sets the algorithms tokens may be signed with. Defaults to RS256 only, the one Azure AD
+    /// uses; tokens with any other `alg` header are rejected before their key is looked up.
+    ///
+    /// ```rust
+    /// # extern crate url;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{Algorithm, IdTokenValidator};
+    /// # fn main() {
+    /// let validator = IdTokenValidator::new(
+    ///     url::Url::parse("https://login.microsoftonline.com/common/discovery/v2.0/keys").unwrap(),
+    ///     "https://login.microsoftonline.com/{tenantid}/v2.0",
+    ///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    /// ).algorithms(&[Algorithm::RS256, Algorithm::PS256]);
+    /// # }
+    /// ```
+    pub fn algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        self.algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Only accepts tokens of users who authenticated with multiple factors, for high-risk
+    /// operations. Azure AD requires MFA through Conditional Access, which can target an
+    /// authentication context requested with `ClaimsRequest`.
+    pub fn require_mfa(mut self) -> Self {
+        self.require_mfa = true;
+        self
+    }
+
+    /// Only accepts tokens of users who authenticated within `max_age`, according to their
+    /// `auth_time`, allowing for the clock skew. Use the `max_age` sent with the
+    /// authorization request, see `AuthorizationRequest::max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Decrypts encrypted tokens with `key`, the private key of the certificate configured
+    /// for token encryption in the app registration. The nested token is then validated as
+    /// usual. `RSA-OAEP` and `RSA-OAEP-256` keys with `A256GCM` content encryption are
+    /// supported.
+    ///
+    /// ```rust
+    /// # extern crate base64;
+    /// # extern crate openssl;
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2;
+    /// # extern crate url;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2::client::response::FromResponse;
+    /// # use openssl::{encrypt::Encrypter, hash::MessageDigest, pkey::PKey, rsa::{Padding, Rsa}, sign::Signer, symm};
+    /// # use inth_oauth2_azure::{IdTokenValidator, Jwks, ValidationError};
+    /// # fn main() {
+    /// # let b64 = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+    /// # let signing_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    /// # let rsa = signing_key.rsa().unwrap();
+    /// # let jwks = Jwks::from_response(&json!({ "keys": [{
+    /// #     "kty": "RSA", "kid": "key-1", "n": b64(&rsa.n().to_vec()), "e": b64(&rsa.e().to_vec()),
+    /// # }]})).unwrap();
+    /// # let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    /// # let claims = json!({
+    /// #     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    /// #     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    /// #     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+    /// #     "iat": now,
+    /// #     "exp": now + 3600,
+    /// # });
+    /// # let input = format!("{}.{}", b64(json!({ "alg": "RS256", "kid": "key-1" }).to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+    /// # let mut signer = Signer::new(MessageDigest::sha256(), &signing_key).unwrap();
+    /// # signer.update(input.as_bytes()).unwrap();
+    /// # let signed = format!("{}.{}", input, b64(&signer.sign_to_vec().unwrap()));
+    /// let decryption_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    /// # let header = b64(json!({ "alg": "RSA-OAEP", "enc": "A256GCM", "cty": "JWT" }).to_string().as_bytes());
+    /// # let (cek, iv) = ([7u8; 32], [9u8; 12]);
+    /// # let encrypted_key = {
+    /// # let mut encrypter = Encrypter::new(&decryption_key).unwrap();
+    /// # encrypter.set_rsa_padding(Padding::PKCS1_OAEP).unwrap();
+    /// # let mut encrypted_key = vec![0; encrypter.encrypt_len(&cek).unwrap()];
+    /// # let len = encrypter.encrypt(&cek, &mut encrypted_key).unwrap();
+    /// # encrypted_key.truncate(len);
+    /// # encrypted_key
+    /// # };
+    /// # let mut tag = [0u8; 16];
+    /// # let ciphertext = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), &cek, Some(&iv), header.as_bytes(), signed.as_bytes(), &mut tag).unwrap();
+    /// # let id_token = format!("{}.{}.{}.{}.{}", header, b64(&encrypted_key), b64(&iv), b64(&ciphertext), b64(&tag));
+    /// let validator = IdTokenValidator::new(
+    ///     url::Url::parse("https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/discovery/v2.0/keys").unwrap(),
+    ///     "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+    ///     "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+    /// );
+    ///
+    /// // `id_token` has five parts
+    /// match validator.validate_with(&jwks, &id_token) {
+    ///     Err(ValidationError::Decryption) => {},
+    ///     other => panic!("{:?}", other),
+    /// }
+    ///
+    /// let validator = validator.decryption_key(decryption_key);
+    /// let claims = validator.validate_with(&jwks, &id_token).unwrap();
+    /// assert_eq!(claims.sub, "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ");
+    ///
+    /// // Truncated authentication tags are rejected
+    /// # let truncated = format!("{}.{}.{}.{}.{}", header, b64(&encrypted_key), b64(&iv), b64(&ciphertext), b64(&tag[..4]));
+    /// match validator.validate_with(&jwks, &truncated) {
+    ///     Err(ValidationError::Decryption) => {},
+    ///     other => panic!("{:?}", other),
+    /// }
+    /// # }
+    /// ```
+    pub fn decryption_key(mut self, key: PKey<Private>) -> Self {
+        self.decryption_key = Some(key);
+        self
+    }
+
+    /// Only accepts tokens signed with the key of the certificate with the base64url
+    /// `thumbprint`, computed from the certificate the key set publishes. May be called
+    /// several times to pin each certificate across a key rollover.
+    ///
+    /// Azure AD rolls its keys over without notice, so pinned validators must be updated
+    /// when it does.
+    ///
+    /// Signatures are then checked with the key of the pinned certificate, so a key set
+    /// pairing the certificate with another key's `n` and `e` doesn't get past the pin:
+    ///
+    /// ```rust
+    /// # extern crate base64;
+    /// # extern crate openssl;
+    /// # #[macro_use] extern crate serde_json;
+    /// # extern crate inth_oauth2;
+    /// # extern crate url;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2::client::response::FromResponse;
+    /// # use openssl::{asn1::Asn1Time, hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::Signer, x509::X509};
+    /// # use inth_oauth2_azure::{IdTokenValidator, Jwks, Thumbprint, ValidationError};
+    /// # fn main() {
+    /// # let b64 = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+    /// # let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    /// # let mut builder = X509::builder().unwrap();
+    /// # builder.set_pubkey(&key).unwrap();
+    /// # builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    /// # builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+    /// # builder.sign(&key, MessageDigest::sha256()).unwrap();
+    /// # let certificate = builder.build();
+    /// # let attacker = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    /// # let rsa = attacker.rsa().unwrap();
+    /// # let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    /// # let sign = |key: &PKey<openssl::pkey::Private>| {
+    /// #     let claims = json!({ "iss": "https://issuer", "aud": "client-id", "sub": "subject", "iat": now, "exp": now + 3600 });
+    /// #     let input = format!("{}.{}", b64(json!({ "alg": "RS256", "kid": "key-1" }).to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+    /// #     let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+    /// #     signer.update(input.as_bytes()).unwrap();
+    /// #     format!("{}.{}", input, b64(&signer.sign_to_vec().unwrap()))
+    /// # };
+    /// let thumbprint = b64(&certificate.digest(MessageDigest::sha256()).unwrap());
+    /// let validator = IdTokenValidator::new(url::Url::parse("https://issuer/keys").unwrap(), "https://issuer", "client-id")
+    ///     .pin_certificate(Thumbprint::Sha256, &thumbprint);
+    ///
+    /// // The pinned certificate, published along with the attacker's modulus and exponent
+    /// let jwks = Jwks::from_response(&json!({ "keys": [{
+    ///     "kty": "RSA", "kid": "key-1",
+    ///     "n": b64(&rsa.n().to_vec()), "e": b64(&rsa.e().to_vec()),
+    ///     "x5c": [base64::encode(&certificate.to_der().unwrap())],
+    /// }]})).unwrap();
+    ///
+    /// assert!(validator.validate_with(&jwks, &sign(&key)).is_ok());
+    /// match validator.validate_with(&jwks, &sign(&attacker)) {
+    ///     Err(ValidationError::Signature) => {},
+    ///     other => panic!("{:?}", other),
+    /// }
+    /// # }
+    /// ```
+    pub fn pin_certificate(mut self, thumbprint: Thumbprint, value: &str) -> Self {
+        self.pins.push((thumbprint, value.to_owned()));
+        self
+    }
+
+    /// Only accepts tokens requested by the applications with the given client IDs, according
+    /// to `azp`, or `appid` in v1.0 tokens.
+    pub fn allow_clients<S: AsRef<str>>(mut self, client_ids: &[S]) -> Self {
+        self.clients.extend(client_ids.iter().map(|id| id.as_ref().to_owned()));
+        self
+    }
+
+    /// Only accepts tokens requested by applications that authenticated with a certificate,
+    /// according to `azpacr`, or `appidacr` in v1.0 tokens.
+    pub fn require_certificate_client(mut self) -> Self {
+        self.require_certificate_client = true;
+        self
+    }
+
+    /// Returns the accepted issuers.
+    pub fn issuers(&self) -> &[String] { &self.issuers }
+
+    /// Returns the tenant tokens must be issued by, if any.
+    pub fn expected_tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    /// Also accepts tokens issued by `issuer`, such as the v1.0 issuer of a tenant whose
+    /// application issues v1.0 access tokens. It may contain `{tenantid}`.
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuers.push(issuer.to_owned());
+        self
+    }
+
+    /// Also accepts tokens issued to `audience`, such as the application's App ID URI, which
+    /// access tokens for it carry instead of its client ID.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audiences.push(audience.to_owned());
+        self
+    }
+
+    /// Sets how long the provider's keys are cached. Defaults to `DEFAULT_JWKS_TTL`. Has no
+    /// effect on offline validators.
+    pub fn jwks_ttl(mut self, ttl: Duration) -> Self {
+        if let KeySource::Remote(cache) = self.keys {
+            self.keys = KeySource::Remote(cache.with_ttl(ttl));
+        }
+        self
+    }
+
+    /// Caches the provider's keys in `cache` rather than in memory, eg. to share them between
+    /// the instances of a service. Has no effect on offline validators.
+    pub fn jwks_cache(mut self, cache: Arc<dyn MetadataCache>) -> Self {
+        if let KeySource::Remote(keys) = self.keys {
+            self.keys = KeySource::Remote(keys.with_cache(cache));
+        }
+        self
+    }
+
+    /// Sets when the key set of an offline validator was exported, to tell if it's stale.
+    /// Has no effect on other validators.
+    pub fn jwks_exported_at(mut self, exported: SystemTime) -> Self {
+        if let KeySource::Offline(_, ref mut at) = self.keys {
+            *at = Some(exported);
+        }
+        self
+    }
+
+    /// Returns true if the key set of an offline validator was exported longer than
+    /// `max_age` ago, so may be missing keys Azure AD has rolled over to since. Key sets
+    /// without an export time, and fetched ones, are never stale.
+    pub fn is_jwks_stale(&self, max_age: Duration) -> bool {
+        match self.keys {
+            KeySource::Offline(_, Some(exported)) => exported.elapsed().is_ok_and(|age| age > max_age),
+            _ => false,
+        }
+    }
+
+    /// Returns the cache of the keys the validator fetches, or `None` for an offline one.
+    ///
+    /// ```rust,no_run
+    /// # extern crate reqwest;
+    /// # extern crate inth_oauth2_azure;
+    /// # use inth_oauth2_azure::{AzureTenant, IdTokenValidator};
+    /// # fn main() -> std::io::Result<()> {
+    /// let validator = IdTokenValidator::for_tenant(&AzureTenant::new("common"), "6cb04018-a3f5-46a7-b995-940c78f5aef3").unwrap();
+    ///
+    /// // Keep the handle for as long as the validator is used
+    /// let refresh = validator.jwks().unwrap().refresh_in_background(reqwest::Client::new())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn jwks(&self) -> Option<&JwksCache> {
+        match self.keys {
+            KeySource::Remote(ref cache) => Some(cache),
+            KeySource::Offline(..) => None,
+        }
+    }
+
+    /// Validates `id_token` with the provider's keys, fetching them if they aren't cached,
+    /// and returns its claims.
+    pub fn validate(&self, http_client: &reqwest::Client, id_token: &str) -> Result<IdTokenClaims, ValidationError> {
+        self.fetch_and_check(http_client, id_token, None)
+    }
+
+    /// Validates `id_token` like `validate`, and checks that its `nonce` is the one sent with
+    /// the authorization request.
+    pub fn validate_nonce(
+        &self,
+        http_client: &reqwest::Client,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, ValidationError> {
+        self.fetch_and_check(http_client, id_token, Some(nonce))
+    }
+
+    /// Validates `id_token` with the given keys, returning its claims.
+    pub fn validate_with(&self, jwks: &Jwks, id_token: &str) -> Result<IdTokenClaims, ValidationError> {
+        self.check_with(jwks, id_token, None)
+    }
+
+    /// Validates `id_token` like `validate_with`, and checks that its `nonce` is the one sent
+    /// with the authorization request.
+    pub fn validate_with_nonce(&self, jwks: &Jwks, id_token: &str, nonce: &str) -> Result<IdTokenClaims, ValidationError> {
+        self.check_with(jwks, id_token, Some(nonce))
+    }
+
+    fn fetch_and_check(
+        &self,
+        http_client: &reqwest::Client,
+        id_token: &str,
+        nonce: Option<&str>,
+    ) -> Result<IdTokenClaims, ValidationError> {
+        let keys = match self.keys {
+            KeySource::Remote(ref keys) => keys,
+            KeySource::Offline(ref jwks, _) => return self.check_with(jwks, id_token, nonce),
+        };
+
+        let token = self.decode(id_token)?;
+        let (alg, key_ref) = self.check_header(&token)?;
+
+        let key = match key_ref {
+            KeyRef::Kid(kid) => keys.find(http_client, kid),
+            KeyRef::Thumbprint(thumbprint, value) => keys.find_by_thumbprint(http_client, thumbprint, value),
+        };
+        let key = key.map_err(ValidationError::Jwks)?.ok_or_else(|| key_ref.unknown())?;
+
+        self.check(&token, alg, &key, nonce)
+    }
+
+    fn check_with(&self, jwks: &Jwks, id_token: &str, nonce: Option<&str>) -> Result<IdTokenClaims, ValidationError> {
+        let token = self.decode(id_token)?;
+        let (alg, key_ref) = self.check_header(&token)?;
+
+        let key = key_ref.find(jwks).ok_or_else(|| key_ref.unknown())?;
+
+        self.check(&token, alg, key, nonce)
+    }
+
+    /// Decodes the token, decrypting it first if it's encrypted.
+    fn decode(&self, id_token: &str) -> Result<jwt::Jwt, ValidationError> {
+        if !jwe::is_encrypted(id_token) {
+            return jwt::decode(id_token).ok_or(ValidationError::Malformed);
+        }
+
+        let key = self.decryption_key.as_ref().ok_or(ValidationError::Decryption)?;
+        let nested = jwe::decrypt(id_token, key)?;
+        jwt::decode(&nested).ok_or(ValidationError::Malformed)
+    }
+
+    fn check(&self, token: &jwt::Jwt, alg: Algorithm, key: &Jwk, nonce: Option<&str>) -> Result<IdTokenClaims, ValidationError> {
+        let public_key = if self.pins.is_empty() {
+            key.public_key()
+        } else {
+            let pinned = self.pins.iter()
+                .any(|&(thumbprint, ref value)| key.certificate_thumbprint(thumbprint).as_ref() == Some(value));
+            if !pinned {
+                return Err(ValidationError::Unpinned);
+            }
+
+            // The pin vouches for the certificate only, so its key is used over `n` and `e`
+            key.certificate_key()
+        };
+        let public_key = public_key.map_err(ValidationError::Key)?;
+
+        let mut verifier = Verifier::new(alg.digest(), &public_key).map_err(ValidationError::Key)?;
+        if alg.is_pss() {
+            verifier.set_rsa_padding(Padding::PKCS1_PSS).map_err(ValidationError::Key)?;
+            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH).map_err(ValidationError::Key)?;
+        }
+        verifier.update(token.signing_input.as_bytes()).map_err(ValidationError::Key)?;
+        if !verifier.verify(&token.signature).unwrap_or(false) {
+            return Err(ValidationError::Signature);
+        }
+
+        let claims = IdTokenClaims::from_json(&token.claims).map_err(ValidationError::Claims)?;
+
+        let now = jwt::now();
+        let skew = self.clock_skew.as_secs();
+        if claims.exp.saturating_add(skew) <= now {
+            return Err(ValidationError::Expired);
+        }
+        if claims.nbf.is_some_and(|nbf| nbf > now + skew) || claims.iat > now + skew {
+            return Err(ValidationError::NotYetValid);
+        }
+        if !self.issuers.iter().any(|issuer| issuer_matches(issuer, &claims.iss, claims.tid.as_deref())) {
+            return Err(ValidationError::Issuer(claims.iss));
+        }
+        if !self.audiences.contains(&claims.aud) {
+            return Err(ValidationError::Audience(claims.aud));
+        }
+        if let Some(ref tenant) = self.tenant {
+            if !claims.tid.as_ref().is_some_and(|tid| tid.eq_ignore_ascii_case(tenant)) {
+                return Err(ValidationError::Tenant(claims.tid));
+            }
+        }
+        if let Some(TenantFilter(ref filter)) = self.tenant_filter {
+            match claims.tid {
+                Some(ref tid) if filter(tid) => {},
+                tid => return Err(ValidationError::Tenant(tid)),
+            }
+        }
+        if !self.clients.is_empty() && !claims.azp.as_ref().is_some_and(|azp| self.clients.contains(azp)) {
+            return Err(ValidationError::AuthorizedParty(claims.azp));
+        }
+        if self.require_certificate_client && claims.azpacr.as_deref() != Some("2") {
+            return Err(ValidationError::ClientAuthentication(claims.azpacr));
+        }
+        if self.require_mfa && !claims.has_mfa() {
+            return Err(ValidationError::Mfa);
+        }
+        if let Some(max_age) = self.max_age {
+            match claims.auth_time {
+                Some(auth_time) if auth_time.saturating_add(max_age.as_secs() + skew) >= now => {},
+                auth_time => return Err(ValidationError::AuthTime(auth_time)),
+            }
+        }
+        if nonce.is_some() && claims.nonce.as_deref() != nonce {
+            return Err(ValidationError::Nonce);
+        }
+
+        Ok(claims)
+    }
+
+    /// Checks the token's algorithm, and returns it with the key it's signed with: that with
+    /// its `kid`, or failing that its certificate thumbprint.
+    fn check_header<'a>(&self, token: &'a jwt::Jwt) -> Result<(Algorithm, KeyRef<'a>), ValidationError> {
+        let alg = token.header["alg"].as_str().unwrap_or_default();
+        let algorithm = Algorithm::from_header(alg)
+            .filter(|algorithm| self.algorithms.contains(algorithm))
+            .ok_or_else(|| ValidationError::Algorithm(alg.to_owned()))?;
+
+        let header = &token.header;
+        let key_ref = if let Some(kid) = header["kid"].as_str() {
+            KeyRef::Kid(kid)
+        } else if let Some(x5t) = header["x5t#S256"].as_str() {
+            KeyRef::Thumbprint(Thumbprint::Sha256, x5t)
+        } else if let Some(x5t) = header["x5t"].as_str() {
+            KeyRef::Thumbprint(Thumbprint::Sha1, x5t)
+        } else {
+            return Err(ValidationError::UnknownKey(None));
+        };
+
+        Ok((algorithm, key_ref))
+    }
+}
+
+/// Validates the bearer access tokens a web API protected by Azure AD receives: their
+/// signature against the tenant's keys, their issuer, that they're for the API's App ID URI,
+/// that they haven't expired, and optionally their scopes and roles.
+///
+/// Access tokens are validated like ID tokens, so the options of an `IdTokenValidator` can
+/// be set before converting it with `From`. Only tokens requested for the API can be
+/// validated; tokens for Microsoft Graph are signed differently, for Graph alone.
+///
+/// ```rust
+/// # extern crate base64;
+/// # extern crate openssl;
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate inth_oauth2;
+/// # extern crate url;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2::client::response::FromResponse;
+/// # use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::Signer};
+/// # use inth_oauth2_azure::{AccessTokenValidator, AzureTenant, CallerKind, Jwks, ValidationError, bearer_token};
+/// # fn main() {
+/// # let b64 = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+/// # let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+/// # let rsa = key.rsa().unwrap();
+/// # let jwks = Jwks::from_response(&json!({ "keys": [{
+/// #     "kty": "RSA", "kid": "key-1", "n": b64(&rsa.n().to_vec()), "e": b64(&rsa.e().to_vec()),
+/// # }]})).unwrap();
+/// # let sign = |claims: serde_json::Value| {
+/// #     let input = format!("{}.{}", b64(json!({ "alg": "RS256", "kid": "key-1" }).to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+/// #     let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+/// #     signer.update(input.as_bytes()).unwrap();
+/// #     format!("{}.{}", input, b64(&signer.sign_to_vec().unwrap()))
+/// # };
+/// # let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+/// let provider = AzureTenant::new("8eaef023-2b34-4da1-9baa-8bc8c9d6a490");
+/// let validator = AccessTokenValidator::for_tenant(&provider, "api://6cb04018-a3f5-46a7-b995-940c78f5aef3")
+///     .unwrap()
+///     .require_scope("Tasks.Read");
+///
+/// # let access_token = sign(json!({
+/// #     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+/// #     "tid": "8eaef023-2b34-4da1-9baa-8bc8c9d6a490",
+/// #     "aud": "api://6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// #     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+/// #     "scp": "Tasks.Read Tasks.Write",
+/// #     "iat": now,
+/// #     "exp": now + 3600,
+/// # }));
+/// # let authorization = format!("Bearer {}", access_token);
+/// let token = bearer_token(&authorization).unwrap();
+/// let claims = validator.validate_with(&jwks, token).unwrap();
+/// assert_eq!(claims.caller_kind(), CallerKind::User);
+///
+/// # let app_only = sign(json!({
+/// #     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+/// #     "tid": "8eaef023-2b34-4da1-9baa-8bc8c9d6a490",
+/// #     "aud": "api://6cb04018-a3f5-46a7-b995-940c78f5aef3",
+/// #     "sub": "00000000-0000-0000-0000-000000000001",
+/// #     "roles": ["Tasks.Read.All"],
+/// #     "iat": now,
+/// #     "exp": now + 3600,
+/// # }));
+/// match validator.validate_with(&jwks, &app_only) {
+///     Err(ValidationError::Scope(ref scope)) if scope == "Tasks.Read" => {},
+///     other => panic!("{:?}", other),
+/// }
+///
+/// // Only the front end may call the API
+/// let validator = validator.allow_clients(&["c44b4083-3bb0-49c1-b47d-974e53cbdf3c"]);
+/// match validator.validate_with(&jwks, token) {
+///     Err(ValidationError::AuthorizedParty(None)) => {},
+///     other => panic!("{:?}", other),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AccessTokenValidator {
+    validator: IdTokenValidator,
+    scopes: Vec<String>,
+    roles: Vec<String>,
+}
+
+impl AccessTokenValidator {
+    /// Validates tokens signed with the keys at `jwks_uri`, issued by `issuer` for the API
+    /// `audience`, its App ID URI or client ID. `issuer` may contain `{tenantid}`.
+    pub fn new(jwks_uri: Url, issuer: &str, audience: &str) -> Self {
+        IdTokenValidator::new(jwks_uri, issuer, audience).into()
+    }
+
+    /// Validates tokens issued by `provider` for the API `audience`, accepting both v1.0 and
+    /// v2.0 tokens. See `IdTokenValidator::for_tenant`.
+    pub fn for_tenant(provider: &AzureTenant, audience: &str) -> Option<Self> {
+        IdTokenValidator::for_tenant(provider, audience).map(Self::from)
+    }
+
+    /// Also accepts tokens for `audience`. Tokens for APIs that accept v1.0 tokens carry
+    /// the App ID URI or client ID depending on how they were requested, so both are usually
+    /// needed.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.validator = self.validator.audience(audience);
+        self
+    }
+
+    /// Only accepts tokens requested by the client applications with the given IDs. See
+    /// `IdTokenValidator::allow_clients`.
+    pub fn allow_clients<S: AsRef<str>>(mut self, client_ids: &[S]) -> Self {
+        self.validator = self.validator.allow_clients(client_ids);
+        self
+    }
+
+    /// Only accepts tokens requested by client applications that authenticated with a
+    /// certificate.
+    pub fn require_certificate_client(mut self) -> Self {
+        self.validator = self.validator.require_certificate_client();
+        self
+    }
+
+    /// Only accepts tokens granted the delegated permission `scope` in their `scp` claim.
+    /// App-only tokens have no scopes, so are rejected.
+    pub fn require_scope(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_owned());
+        self
+    }
+
+    /// Only accepts tokens whose caller was assigned the app role `role`.
+    pub fn require_role(mut self, role: &str) -> Self {
+        self.roles.push(role.to_owned());
+        self
+    }
+
+    /// Validates `access_token` with the tenant's keys, fetching them if they aren't cached,
+    /// and returns its claims.
+    pub fn validate(&self, http_client: &reqwest::Client, access_token: &str) -> Result<IdTokenClaims, ValidationError> {
+        let claims = self.validator.validate(http_client, access_token)?;
+        self.authorize(claims)
+    }
+
+    /// Validates `access_token` with the given keys, returning its claims.
+    pub fn validate_with(&self, jwks: &Jwks, access_token: &str) -> Result<IdTokenClaims, ValidationError> {
+        let claims = self.validator.validate_with(jwks, access_token)?;
+        self.authorize(claims)
+    }
+
+    fn authorize(&self, claims: IdTokenClaims) -> Result<IdTokenClaims, ValidationError> {
+        let granted = claims.scopes();
+        if let Some(scope) = self.scopes.iter().find(|scope| !granted.contains(scope)) {
+            return Err(ValidationError::Scope(scope.clone()));
+        }
+        if let Some(role) = self.roles.iter().find(|role| !claims.has_role(role)) {
+            return Err(ValidationError::Role(role.clone()));
+        }
+
+        Ok(claims)
+    }
+}
+
+impl From<IdTokenValidator> for AccessTokenValidator {
+    fn from(validator: IdTokenValidator) -> Self {
+        AccessTokenValidator { validator, scopes: Vec::new(), roles: Vec::new() }
+    }
+}
+
+/// Returns the token of an `Authorization: Bearer` header value.
+///
+/// ```rust
+/// # use inth_oauth2_azure::bearer_token;
+/// assert_eq!(bearer_token("Bearer eyJ0eXAi"), Some("eyJ0eXAi"));
+/// assert_eq!(bearer_token("bearer  eyJ0eXAi "), Some("eyJ0eXAi"));
+/// assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+/// ```
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let authorization = authorization.trim();
+    let scheme = authorization.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("bearer ") {
+        return None;
+    }
+
+    let token = authorization[7..].trim();
+    if token.is_empty() { None } else { Some(token) }
+}
+
+/// Decodes the claims of a token **without verifying it**, eg. to read its `tid` and pick
+/// the validator of that tenant.
+///
+/// Anyone can make up a token with any claims, so they mustn't be trusted or used to
+/// authorize: validate the token before deciding anything but which validator to use.
+/// Encrypted tokens can't be read without their key and are `Malformed`.
+///
+/// ```rust
+/// # extern crate base64;
+/// # extern crate inth_oauth2_azure;
+/// # use inth_oauth2_azure::dangerous_unverified_claims;
+/// # fn main() {
+/// let encode = |json: &str| base64::encode_config(json, base64::URL_SAFE_NO_PAD);
+/// let token = format!("{}.{}.c2ln", encode(r#"{"alg":"RS256","kid":"nOo3ZDrODXEK1jKWhXslHR_KXEg"}"#), encode(r#"{
+///     "iss": "https://login.microsoftonline.com/8eaef023-2b34-4da1-9baa-8bc8c9d6a490/v2.0",
+///     "sub": "AAAAAAAAAAAAAAAAAAAAAIkzqFVrSaSaFHy782bbtaQ",
+///     "aud": "6cb04018-a3f5-46a7-b995-940c78f5aef3",
+///     "exp": 1536361411,
+///     "iat": 1536274711,
+///     "tid": "8eaef023-2b34-4da1-9baa-8bc8c9d6a490"
+/// }"#));
+///
+/// let unverified = dangerous_unverified_claims(&token).unwrap();
+/// assert_eq!(unverified.tid.unwrap(), "8eaef023-2b34-4da1-9baa-8bc8c9d6a490");
+/// # }
+/// ```
+pub fn dangerous_unverified_claims(token: &str) -> Result<IdTokenClaims, ValidationError> {
+    let token = jwt::decode(token).ok_or(ValidationError::Malformed)?;
+
+    IdTokenClaims::from_json(&token.claims).map_err(ValidationError::Claims)
+}
+
+/// Compares a token's `iss` claim with the expected issuer, substituting its `tid` into
+/// multi-tenant issuer templates.
+fn issuer_matches(expected: &str, iss: &str, tid: Option<&str>) -> bool {
+    if !expected.contains(TENANT_ID_TEMPLATE) {
+        return iss == expected;
+    }
+
+    // Without a GUID tenant, a token could substitute anything, even a path, for the template
+    match tid {
+        Some(tid) if ::is_guid(tid) => iss == expected.replace(TENANT_ID_TEMPLATE, tid),
+        _ => false,
+    }
+}